@@ -0,0 +1,21 @@
+#include <stdio.h>
+#include <unistd.h>
+
+long spin(long n) {
+    long total = 0;
+    for (long i = 0; i < n; i++) {
+        total += i % 7;
+    }
+    return total;
+}
+
+long work(void) {
+    return spin(100000000) + spin(50000000);
+}
+
+int main() {
+    long total = work();
+    usleep(100000);
+    printf("%ld\n", total);
+    return 0;
+}
@@ -0,0 +1,82 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+struct node {
+    int value;
+    struct node *next;
+};
+
+struct list {
+    struct node *head;
+    int length;
+};
+
+struct ring {
+    int data[4];
+    unsigned start;
+    unsigned count;
+};
+
+struct entry {
+    char key[16];
+    int value;
+    struct entry *next;
+};
+
+struct table {
+    struct entry **buckets;
+    unsigned size;
+};
+
+struct point {
+    int x;
+    int y;
+};
+
+void push(struct list *list, int value) {
+    struct node *node = malloc(sizeof(struct node));
+    node->value = value;
+    node->next = list->head;
+    list->head = node;
+    list->length++;
+}
+
+void ring_push(struct ring *ring, int value) {
+    ring->data[(ring->start + ring->count) % 4] = value;
+    if (ring->count < 4) {
+        ring->count++;
+    } else {
+        ring->start = (ring->start + 1) % 4;
+    }
+}
+
+void insert(struct table *table, const char *key, int value) {
+    unsigned hash = 0;
+    for (const char *c = key; *c; c++) {
+        hash += *c;
+    }
+    struct entry *entry = malloc(sizeof(struct entry));
+    strncpy(entry->key, key, sizeof(entry->key));
+    entry->value = value;
+    entry->next = table->buckets[hash % table->size];
+    table->buckets[hash % table->size] = entry;
+}
+
+int main() {
+    struct list numbers = {NULL, 0};
+    for (int i = 3; i > 0; i--) {
+        push(&numbers, i);
+    }
+    struct ring recent = {{0}, 0, 0};
+    for (int i = 1; i <= 6; i++) {
+        ring_push(&recent, i * 10);
+    }
+    struct table ages = {calloc(8, sizeof(struct entry *)), 8};
+    insert(&ages, "alice", 31);
+    insert(&ages, "bob", 27);
+    insert(&ages, "carol", 45);
+    struct point corner = {3, 4};
+    printf("%d %u %d\n", numbers.length, recent.count, corner.x + corner.y);
+    return 0;
+}
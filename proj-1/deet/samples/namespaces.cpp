@@ -0,0 +1,20 @@
+#include <cstdio>
+
+namespace geometry {
+
+struct Point {
+    int x;
+    int y;
+    int sum() const { return x + y; }
+};
+
+int add(int a, int b) {
+    return a + b;
+}
+
+}
+
+int main() {
+    geometry::Point p = {1, 2};
+    printf("%d %d\n", geometry::add(p.x, p.y), p.sum());
+}
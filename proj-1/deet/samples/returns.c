@@ -0,0 +1,52 @@
+#include <stdio.h>
+
+struct vector {
+    double x, y;
+};
+
+struct mixed {
+    long count;
+    double mean;
+};
+
+struct triple {
+    float a, b, c;
+};
+
+struct big {
+    long values[4];
+};
+
+struct vector make_vector(double x, double y) {
+    struct vector v = {x, y};
+    return v;
+}
+
+struct mixed make_mixed(long count) {
+    struct mixed m = {count, count / 2.0};
+    return m;
+}
+
+struct triple make_triple(void) {
+    struct triple t = {1.5f, 2.5f, 3.5f};
+    return t;
+}
+
+struct big make_big(void) {
+    struct big b = {{1, 2, 3, 4}};
+    return b;
+}
+
+long double make_long_double(void) {
+    return 1.25L;
+}
+
+int main() {
+    struct vector v = make_vector(3.0, 4.0);
+    struct mixed m = make_mixed(7);
+    struct triple t = make_triple();
+    struct big b = make_big();
+    long double d = make_long_double();
+    printf("%g %g %ld %g %g %ld %Lg\n", v.x, v.y, m.count, m.mean, t.c, b.values[3], d);
+    return 0;
+}
@@ -0,0 +1,25 @@
+use std::rc::Rc;
+
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn show(name: &str, numbers: &Vec<i32>) -> usize {
+    println!("{} has {} numbers", name, numbers.len());
+    numbers.len()
+}
+
+fn main() {
+    let greeting = String::from("hello");
+    let slice: &str = "a slice";
+    let numbers = vec![1, 2, 3];
+    let some: Option<i32> = Some(7);
+    let none: Option<i32> = None;
+    let boxed = Box::new(Point { x: 1, y: 2 });
+    let shared = Rc::new(String::from("shared"));
+    let other = Rc::clone(&shared);
+    let total = show(slice, &numbers);
+    println!("{} {} {:?} {:?}", greeting, total, some, none);
+    println!("{} {} {}", boxed.x + boxed.y, shared, Rc::strong_count(&other));
+}
@@ -0,0 +1,120 @@
+//! The `deet` command line: picks the debugger, the syscall tracer or the profiler from the
+//! arguments and runs it.
+
+use crate::debug_file;
+use crate::debugger::Debugger;
+use crate::profile::{self, ProfileOptions};
+use crate::trace::{self, TraceOptions};
+use crate::tui::{self, Tui};
+use nix::sys::signal::{signal, SigHandler, Signal};
+use std::env;
+
+fn usage(program: &str) -> ! {
+    println!(
+        "Usage: {} [--tui] [--debug-file-directory <dir>[:<dir>...]] <target program>",
+        program
+    );
+    println!(
+        "       {} --trace-syscalls [-f] [-e trace=<set>] <target program> [args...]",
+        program
+    );
+    println!(
+        "       {} --profile [--hz <rate>] [--top <count>] [-o <file>] <target program> [args...]",
+        program
+    );
+    tui::exit(1);
+}
+
+pub fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut debug_file_directories = vec![debug_file::DEFAULT_DEBUG_FILE_DIRECTORY.to_string()];
+    let mut target = None;
+    let mut trace_options = None;
+    let mut profile_options: Option<ProfileOptions> = None;
+    let mut tui = false;
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--trace-syscalls" => trace_options = Some(TraceOptions::default()),
+            "-f" if trace_options.is_some() => trace_options.as_mut().unwrap().follow_forks = true,
+            "-e" if trace_options.is_some() => {
+                i += 1;
+                let expression = args.get(i).unwrap_or_else(|| usage(&args[0]));
+                match TraceOptions::parse_filter(expression) {
+                    Ok(filter) => trace_options.as_mut().unwrap().filter = Some(filter),
+                    Err(err) => {
+                        eprintln!("deet: {}", err);
+                        tui::exit(1);
+                    }
+                }
+            }
+            // Everything after the program is its arguments
+            arg if trace_options.is_some() && !arg.starts_with('-') => {
+                let program_args = args[i + 1..].to_vec();
+                tui::exit(trace::trace_syscalls(
+                    arg,
+                    &program_args,
+                    &trace_options.unwrap(),
+                ));
+            }
+            "--profile" => profile_options = Some(ProfileOptions::default()),
+            "--hz" | "--top" if profile_options.is_some() => {
+                let option = args[i].clone();
+                i += 1;
+                let value = args.get(i).unwrap_or_else(|| usage(&args[0]));
+                let options = profile_options.as_mut().unwrap();
+                match value.parse::<u32>() {
+                    Ok(rate) if rate > 0 && option == "--hz" => options.hz = rate,
+                    Ok(count) if option == "--top" => options.top = count as usize,
+                    _ => {
+                        println!("deet: invalid {} '{}'", option, value);
+                        tui::exit(1);
+                    }
+                }
+            }
+            "-o" if profile_options.is_some() => {
+                i += 1;
+                let output = args.get(i).unwrap_or_else(|| usage(&args[0]));
+                profile_options.as_mut().unwrap().output = output.to_string();
+            }
+            arg if profile_options.is_some() && !arg.starts_with('-') => {
+                // ctrl+c ends the profile rather than deet, which then reports it
+                unsafe { signal(Signal::SIGINT, SigHandler::SigIgn) }
+                    .expect("Error disabling SIGINT handling");
+                let program_args = args[i + 1..].to_vec();
+                tui::exit(profile::profile(
+                    arg,
+                    &program_args,
+                    &profile_options.unwrap(),
+                    &debug_file_directories,
+                ));
+            }
+            "--tui" => tui = true,
+            "--debug-file-directory" => {
+                i += 1;
+                let dirs = args.get(i).unwrap_or_else(|| usage(&args[0]));
+                debug_file_directories = dirs.split(':').map(|s| s.to_string()).collect();
+            }
+            arg if target.is_none() && !arg.starts_with("--") => target = Some(arg.to_string()),
+            _ => usage(&args[0]),
+        }
+        i += 1;
+    }
+    let target = target.unwrap_or_else(|| usage(&args[0]));
+
+    // Disable handling of ctrl+c in this process (so that ctrl+c only gets delivered to child
+    // processes)
+    unsafe { signal(Signal::SIGINT, SigHandler::SigIgn) }.expect("Error disabling SIGINT handling");
+
+    let mut debugger = Debugger::new(&target, &debug_file_directories);
+    if tui {
+        match Tui::new() {
+            Ok(tui) => debugger.set_tui(tui),
+            Err(err) => {
+                println!("deet: {}", err);
+                tui::exit(1);
+            }
+        }
+    }
+    debugger.run();
+}
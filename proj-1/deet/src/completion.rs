@@ -0,0 +1,220 @@
+//! Tab completion at the deet prompt. The command names, and what each command takes as
+//! arguments, come from the command list in debugger_command; functions, source files and
+//! variables come from the program's debugging information.
+
+use crate::debugger_command::{self, Arguments, COMMANDS};
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+
+pub struct DeetHelper {
+    functions: Vec<String>,
+    /// Source file names, without their directories
+    files: Vec<String>,
+    globals: Vec<String>,
+    /// Variables visible in the selected frame, kept up to date by the debugger
+    pub locals: Vec<String>,
+    paths: FilenameCompleter,
+}
+
+impl DeetHelper {
+    pub fn new(functions: Vec<String>, files: Vec<String>, globals: Vec<String>) -> DeetHelper {
+        DeetHelper {
+            functions,
+            files,
+            globals,
+            locals: Vec::new(),
+            paths: FilenameCompleter::new(),
+        }
+    }
+
+    /// Completes the word at `pos`, which follows the arguments `words` already given.
+    fn complete_arguments(
+        &self,
+        arguments: &Arguments,
+        words: &[&str],
+        line: &str,
+        pos: usize,
+        ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before
+            .rfind(char::is_whitespace)
+            .map_or(0, |space| space + 1);
+        let word = &before[start..];
+        match arguments {
+            Arguments::Location => match word.find(':') {
+                Some(colon) => Ok((
+                    start + colon + 1,
+                    candidates(&word[colon + 1..], &self.functions),
+                )),
+                None => {
+                    let files = self.files.iter().map(|file| format!("{}:", file));
+                    let names = self.functions.iter().cloned().chain(files);
+                    Ok((start, candidates(word, names)))
+                }
+            },
+            Arguments::Expression => {
+                // Complete the identifier the expression ends with, as in `print *node`
+                let identifier = before
+                    .char_indices()
+                    .rev()
+                    .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+                    .map_or(0, |(i, c)| i + c.len_utf8());
+                // Members of structs are not known here
+                if before[..identifier].ends_with('.') || before[..identifier].ends_with("->") {
+                    return Ok((pos, Vec::new()));
+                }
+                let names = self
+                    .locals
+                    .iter()
+                    .chain(&self.globals)
+                    .chain(&self.functions);
+                Ok((identifier, candidates(&before[identifier..], names)))
+            }
+            Arguments::File | Arguments::Program => self.paths.complete(line, pos, ctx),
+            Arguments::Subcommands(subcommands) => match words.first() {
+                None => {
+                    let names = subcommands.iter().map(|(name, _)| *name);
+                    Ok((start, candidates(word, names)))
+                }
+                Some(given) => match subcommands.iter().find(|(name, _)| name == given) {
+                    Some((_, arguments)) => {
+                        self.complete_arguments(arguments, &words[1..], line, pos, ctx)
+                    }
+                    None => Ok((pos, Vec::new())),
+                },
+            },
+            Arguments::Command if words.is_empty() => {
+                let names = COMMANDS.iter().map(|command| command.names[0]);
+                Ok((start, candidates(word, names)))
+            }
+            Arguments::Command | Arguments::None => Ok((pos, Vec::new())),
+        }
+    }
+}
+
+/// Returns the names that start with `prefix`, sorted and without duplicates.
+fn candidates<I, S>(prefix: &str, names: I) -> Vec<Pair>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut matching: Vec<String> = names
+        .into_iter()
+        .filter(|name| name.as_ref().starts_with(prefix))
+        .map(|name| name.as_ref().to_string())
+        .collect();
+    matching.sort();
+    matching.dedup();
+    matching
+        .into_iter()
+        .map(|name| Pair {
+            display: name.clone(),
+            replacement: name,
+        })
+        .collect()
+}
+
+impl Completer for DeetHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before
+            .rfind(char::is_whitespace)
+            .map_or(0, |space| space + 1);
+        let word = &before[start..];
+        let words: Vec<&str> = before[..start].split_whitespace().collect();
+        let command = match words.first() {
+            Some(name) => match debugger_command::lookup(name) {
+                Some(command) => command,
+                None => return Ok((pos, Vec::new())),
+            },
+            None => {
+                let names = COMMANDS.iter().map(|command| command.names[0]);
+                return Ok((start, candidates(word, names)));
+            }
+        };
+        self.complete_arguments(&command.arguments, &words[1..], line, pos, ctx)
+    }
+}
+
+impl Hinter for DeetHelper {}
+
+impl Highlighter for DeetHelper {}
+
+impl Validator for DeetHelper {}
+
+impl Helper for DeetHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustyline::history::History;
+
+    /// Completes at the end of `line` and checks where the replacement starts and the names offered.
+    fn check(line: &str, start: usize, names: &[&str]) {
+        let mut helper = DeetHelper::new(
+            vec![
+                "main".to_string(),
+                "make_list".to_string(),
+                "push".to_string(),
+            ],
+            vec!["list.c".to_string(), "main.c".to_string()],
+            vec!["max_length".to_string()],
+        );
+        helper.locals = vec!["list".to_string(), "node".to_string()];
+        let history = History::new();
+        let (position, pairs) = helper
+            .complete(line, line.len(), &Context::new(&history))
+            .unwrap();
+        let replacements: Vec<&str> = pairs.iter().map(|pair| &pair.replacement[..]).collect();
+        assert_eq!((position, &replacements[..]), (start, names), "{}", line);
+    }
+
+    #[test]
+    fn commands() {
+        check(
+            "s",
+            0,
+            &["save", "set", "show", "source", "start", "step", "stepi"],
+        );
+        check("  fin", 2, &["finish"]);
+        check("bogus ma", 8, &[]);
+        check("help che", 5, &["checkpoint"]);
+        check("help checkpoint ", 16, &[]);
+    }
+
+    #[test]
+    fn locations() {
+        check("break ma", 6, &["main", "main.c:", "make_list"]);
+        check("b list.c:p", 9, &["push"]);
+        check("until l", 6, &["list.c:"]);
+    }
+
+    #[test]
+    fn expressions() {
+        check("print *l", 7, &["list"]);
+        check("p 1+ma", 4, &["main", "make_list", "max_length"]);
+        check("p list->n", 9, &[]);
+        check("p node.l", 8, &[]);
+        check("display no", 8, &["node"]);
+    }
+
+    #[test]
+    fn subcommands() {
+        check("info f", 5, &["frame", "functions"]);
+        check("i line list.c:m", 14, &["main", "make_list"]);
+        check("info address no", 13, &["node"]);
+        check("info frame x", 12, &[]);
+        check("info bogus x", 12, &[]);
+    }
+}
@@ -0,0 +1,210 @@
+//! This file contains code for finding the separate debug file of a stripped executable. Release
+//! builds often ship binaries whose DWARF sections were moved into a `.debug` file with
+//! `objcopy --only-keep-debug`; the binary then only records where to find them, either through
+//! a `.note.gnu.build-id` note or a `.gnu_debuglink` section. The search order follows gdb's:
+//! https://sourceware.org/gdb/onlinedocs/gdb/Separate-Debug-Files.html
+
+use object::Object;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// The global debug directory that is searched when no other directory is configured.
+pub const DEFAULT_DEBUG_FILE_DIRECTORY: &str = "/usr/lib/debug";
+
+/// Note type of the build ID note in `.note.gnu.build-id`.
+const NT_GNU_BUILD_ID: u32 = 3;
+
+fn read_u32(bytes: &[u8], little_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = [
+        *bytes.get(0)?,
+        *bytes.get(1)?,
+        *bytes.get(2)?,
+        *bytes.get(3)?,
+    ];
+    Some(if little_endian {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    })
+}
+
+fn align_to_4(n: usize) -> usize {
+    (n + 3) & !3
+}
+
+/// Returns the build ID stored in the `.note.gnu.build-id` section, if there is one.
+pub fn build_id(object: &object::File) -> Option<Vec<u8>> {
+    let data = object.section_data_by_name(".note.gnu.build-id")?;
+    parse_build_id_note(&data, object.is_little_endian())
+}
+
+/// Finds the GNU build ID note among the notes in `data`. Each note is a (namesz, descsz, type)
+/// header followed by the name and the descriptor, both padded to 4 bytes.
+fn parse_build_id_note(data: &[u8], little_endian: bool) -> Option<Vec<u8>> {
+    let mut offset = 0;
+    while offset + 12 <= data.len() {
+        let name_size = read_u32(&data[offset..], little_endian)? as usize;
+        let desc_size = read_u32(&data[offset + 4..], little_endian)? as usize;
+        let note_type = read_u32(&data[offset + 8..], little_endian)?;
+        let name_start = offset + 12;
+        let desc_start = name_start.checked_add(align_to_4(name_size))?;
+        let desc_end = desc_start.checked_add(desc_size)?;
+        if desc_end > data.len() {
+            return None;
+        }
+        if note_type == NT_GNU_BUILD_ID && &data[name_start..name_start + name_size] == b"GNU\0" {
+            return Some(data[desc_start..desc_end].to_vec());
+        }
+        offset = desc_start + align_to_4(desc_size);
+    }
+    None
+}
+
+/// Returns the file name and CRC stored in the `.gnu_debuglink` section, if there is one.
+pub fn gnu_debuglink(object: &object::File) -> Option<(String, u32)> {
+    let data = object.section_data_by_name(".gnu_debuglink")?;
+    parse_gnu_debuglink(&data, object.is_little_endian())
+}
+
+/// Parses a `.gnu_debuglink` section: a NUL-terminated file name padded to 4 bytes, followed by
+/// a 4-byte CRC.
+fn parse_gnu_debuglink(data: &[u8], little_endian: bool) -> Option<(String, u32)> {
+    let name_len = data.iter().position(|&b| b == 0)?;
+    let name = String::from_utf8(data[..name_len].to_vec()).ok()?;
+    let crc = read_u32(data.get(align_to_4(name_len + 1)..)?, little_endian)?;
+    Some((name, crc))
+}
+
+/// Computes the CRC32 that `.gnu_debuglink` uses to identify the matching debug file.
+fn file_crc(path: &Path) -> Option<u32> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let count = file.read(&mut buf).ok()?;
+        if count == 0 {
+            break;
+        }
+        hasher.update(&buf[..count]);
+    }
+    Some(hasher.finalize())
+}
+
+/// Returns the build ID of the object file at `path`, if it can be opened and has one.
+fn file_build_id(path: &Path) -> Option<Vec<u8>> {
+    let file = fs::File::open(path).ok()?;
+    let mmap = unsafe { memmap::Mmap::map(&file).ok()? };
+    let object = object::File::parse(&*mmap).ok()?;
+    build_id(&object)
+}
+
+/// Returns the path of `/<debug dir>/.build-id/xx/yyyy.debug` for the given build ID.
+fn build_id_path(debug_dir: &str, build_id: &[u8]) -> Option<PathBuf> {
+    let (first, rest) = build_id.split_first()?;
+    let rest: String = rest.iter().map(|b| format!("{:02x}", b)).collect();
+    Some(
+        Path::new(debug_dir)
+            .join(".build-id")
+            .join(format!("{:02x}", first))
+            .join(format!("{}.debug", rest)),
+    )
+}
+
+/// Looks for the separate debug file of the executable at `target`. Candidates named by build ID
+/// are tried first (and must have the same build ID), then the `.gnu_debuglink` name is looked up
+/// next to the executable, in its `.debug` subdirectory and under each debug directory (and must
+/// have the recorded CRC).
+pub fn find_debug_file(
+    target: &str,
+    object: &object::File,
+    debug_file_directories: &[String],
+) -> Option<PathBuf> {
+    if let Some(id) = build_id(object) {
+        for dir in debug_file_directories {
+            if let Some(path) = build_id_path(dir, &id) {
+                if path.is_file() && file_build_id(&path).as_ref() == Some(&id) {
+                    return Some(path);
+                }
+            }
+        }
+    }
+
+    let (name, crc) = gnu_debuglink(object)?;
+    let target_path = fs::canonicalize(target).ok()?;
+    let target_dir = target_path.parent()?;
+    let mut candidates = vec![
+        target_dir.join(&name),
+        target_dir.join(".debug").join(&name),
+    ];
+    for dir in debug_file_directories {
+        // The executable's directory is absolute, so strip the leading "/" before appending it.
+        let relative_dir = target_dir.strip_prefix("/").unwrap_or(target_dir);
+        candidates.push(Path::new(dir).join(relative_dir).join(&name));
+    }
+    candidates
+        .into_iter()
+        .filter(|path| path.is_file() && *path != target_path)
+        .find(|path| file_crc(path) == Some(crc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a note with the given name, type and descriptor, padded the way the linker pads it.
+    fn note(name: &[u8], note_type: u32, desc: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&note_type.to_le_bytes());
+        bytes.extend_from_slice(name);
+        bytes.resize(align_to_4(bytes.len()), 0);
+        bytes.extend_from_slice(desc);
+        bytes.resize(align_to_4(bytes.len()), 0);
+        bytes
+    }
+
+    #[test]
+    fn build_id_note() {
+        let id = [0xde, 0xad, 0xbe, 0xef, 0x01];
+        let mut data = note(b"Go\0", 4, b"abc");
+        data.extend(note(b"GNU\0", NT_GNU_BUILD_ID, &id));
+        assert_eq!(parse_build_id_note(&data, true), Some(id.to_vec()));
+        assert_eq!(parse_build_id_note(&note(b"GNU\0", 1, &id), true), None);
+        // A descriptor running past the end of the section
+        assert_eq!(parse_build_id_note(&data[..data.len() - 4], true), None);
+        assert_eq!(parse_build_id_note(&[0xff; 12], true), None);
+    }
+
+    #[test]
+    fn gnu_debuglink_section() {
+        let data = b"prog.debug\0\0\x78\x56\x34\x12";
+        assert_eq!(
+            parse_gnu_debuglink(data, true),
+            Some(("prog.debug".to_string(), 0x1234_5678))
+        );
+        assert_eq!(
+            parse_gnu_debuglink(data, false),
+            Some(("prog.debug".to_string(), 0x7856_3412))
+        );
+        // Truncated sections
+        assert_eq!(parse_gnu_debuglink(b"abcd\0", true), None);
+        assert_eq!(parse_gnu_debuglink(b"abc\0\x01\x02", true), None);
+        assert_eq!(parse_gnu_debuglink(b"abcd", true), None);
+    }
+
+    #[test]
+    fn crc_and_build_id_path() {
+        let path = std::env::temp_dir().join(format!("deet-crc-{}", std::process::id()));
+        fs::write(&path, b"123456789").unwrap();
+        let crc = file_crc(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(crc, Some(0xcbf4_3926));
+        assert_eq!(
+            build_id_path("/usr/lib/debug", &[0xab, 0x01, 0x2f]),
+            Some(PathBuf::from("/usr/lib/debug/.build-id/ab/012f.debug"))
+        );
+        assert_eq!(build_id_path("/usr/lib/debug", &[]), None);
+    }
+}
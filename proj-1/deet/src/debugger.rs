@@ -1,173 +1,2088 @@
-use crate::dwarf_data::{DwarfData, Error as DwarfError};
-use crate::inferior::Inferior;
-use crate::{debugger_command::DebuggerCommand, inferior::Status};
+use crate::completion::DeetHelper;
+use crate::debugger_command::{self, Catch, DebuggerCommand, ExamineFormat};
+use crate::dwarf_data::{Encoding, FrameBase, Function, Location, Type, TypeKind, Variable};
+use crate::expression;
+use crate::format;
+use crate::inferior::{Event, Frame, Inferior, Launch, ReturnLocation};
+use crate::regex::Regex;
+use crate::session::{self, CatchpointKind, Caught, Error, Notice, Session, Stop};
+use crate::syscalls;
+use crate::tui::{self, Pane, PaneLine, Screen, Tui};
+use nix::sys::signal;
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
-use Status::{Exited, Signaled, Stopped};
+use std::collections::{HashMap, VecDeque};
+use std::convert::{TryFrom, TryInto};
+use std::fs;
+use std::path::Path;
 
+/// Number of source lines shown by `list`.
+const LIST_SIZE: usize = 10;
+
+/// An expression registered with `display`.
+struct Display {
+    number: usize,
+    expression: String,
+}
+
+/// A copy of the inferior saved with `checkpoint`, kept stopped so that `restart` can go back to
+/// it.
+struct Checkpoint {
+    number: usize,
+    inferior: Inferior,
+}
+
+/// The command line front end. The program is run through a `Session`, which decides where it
+/// stops; the debugger reads commands and tells the user what happened.
 pub struct Debugger {
-    target: String,
     history_path: String,
-    readline: Editor<()>,
-    inferior: Option<Inferior>,
-    debug_data: DwarfData,
-    break_point: Vec<usize>,
+    readline: Editor<DeetHelper>,
+    session: Session,
+    /// Debugger commands run whenever a breakpoint is hit (`commands N`), by breakpoint number
+    breakpoint_commands: HashMap<usize, Vec<String>>,
+    /// Where `x` without an address continues from
+    next_examine: Option<usize>,
+    /// Format of the last `x`, which `x` without a format reuses
+    examine_format: ExamineFormat,
+    /// Commands of the breakpoint the inferior last stopped at that have not run yet
+    command_queue: VecDeque<String>,
+    /// True while the command queue is being drained
+    draining_commands: bool,
+    /// Set by `quit`, including when it comes from a breakpoint's command list
+    quitting: bool,
+    displays: Vec<Display>,
+    next_display_number: usize,
+    /// Index of the frame that `print`, `list` and `info frame` apply to (0 is innermost)
+    selected_frame: usize,
+    /// Source file and first line that the next `list` without arguments shows
+    next_list: Option<(String, usize)>,
+    checkpoints: Vec<Checkpoint>,
+    next_checkpoint_number: usize,
+    /// Arguments the program is run with, including redirections (`set args`)
+    args: Vec<String>,
+    /// Environment, working directory and terminal the program is run with
+    launch: Launch,
+    /// The panes of `--tui`, if it is on
+    tui: Option<Tui>,
 }
 
 impl Debugger {
     /// Initializes the debugger.
-    pub fn new(target: &str) -> Debugger {
-        let debug_data = match DwarfData::from_file(target) {
-            Ok(val) => val,
-            Err(DwarfError::ErrorOpeningFile) => {
-                println!("Could not open file {}", target);
-                std::process::exit(1);
-            }
-            Err(DwarfError::DwarfFormatError(err)) => {
-                println!("Could not debugging symbols from {}: {:?}", target, err);
-                std::process::exit(1);
+    pub fn new(target: &str, debug_file_directories: &[String]) -> Debugger {
+        let session = match Session::with_debug_file_directories(target, debug_file_directories) {
+            Ok(session) => session,
+            Err(err) => {
+                println!("{}", err);
+                tui::exit(1);
             }
         };
+        let debug_data = session.debug_data();
+        if let Some(debug_file) = debug_data.debug_file() {
+            println!("Reading symbols from {}", debug_file.display());
+        }
         let history_path = format!("{}/.deet_history", std::env::var("HOME").unwrap());
-        let mut readline = Editor::<()>::new();
+        let mut readline = Editor::<DeetHelper>::new();
         // Attempt to load history from ~/.deet_history if it exists
         let _ = readline.load_history(&history_path);
-        debug_data.print();
+        readline.set_helper(Some(DeetHelper::new(
+            debug_data.function_names(),
+            debug_data.file_names(),
+            debug_data.global_variable_names(),
+        )));
         Debugger {
-            target: target.to_string(),
             history_path,
             readline,
-            inferior: None,
-            debug_data,
-            break_point: Vec::new(),
+            session,
+            breakpoint_commands: HashMap::new(),
+            next_examine: None,
+            examine_format: ExamineFormat::default(),
+            command_queue: VecDeque::new(),
+            draining_commands: false,
+            quitting: false,
+            displays: Vec::new(),
+            next_display_number: 1,
+            selected_frame: 0,
+            next_list: None,
+            checkpoints: Vec::new(),
+            next_checkpoint_number: 1,
+            args: Vec::new(),
+            launch: Launch {
+                environment: Some(std::env::vars().collect()),
+                ..Launch::default()
+            },
+            tui: None,
         }
     }
 
     fn contin(&mut self) {
-        let inf = match &mut self.inferior {
+        if !self.session.is_running() {
+            println!("No child process now");
+            return;
+        }
+        let stop = self.session.cont();
+        self.report_stop(stop);
+        self.refresh_tui();
+        self.run_queued_commands();
+    }
+
+    /// Shows where a step ended: the line it got to, or why it stopped before getting there.
+    fn report_step(&mut self, stop: Result<Stop, Error>) {
+        match stop {
+            Ok(Stop::Step) => {
+                self.print_notices();
+                self.next_list = None;
+                self.command_queue.clear();
+                self.select_frame(0);
+                self.do_displays();
+            }
+            stop => self.report_stop(stop),
+        }
+        self.refresh_tui();
+        self.run_queued_commands();
+    }
+
+    /// Redraws the panes of `--tui`, if it is on.
+    fn refresh_tui(&mut self) {
+        if let Some(mut tui) = self.tui.take() {
+            tui.draw(&self.tui_screen());
+            self.tui = Some(tui);
+        }
+    }
+
+    fn tui_screen(&self) -> Screen {
+        let frames = self.frames_quietly();
+        let not_running = || vec![PaneLine::new("The program is not being run.".to_string())];
+        let locals = if self.selected_frame >= frames.len() {
+            not_running()
+        } else {
+            match self.frame_function(&frames, self.selected_frame) {
+                Some(function) => function
+                    .variables
+                    .iter()
+                    .map(|var| {
+                        let frame = &frames[self.selected_frame];
+                        let value = self.read_variable(var, frame, function.frame_base);
+                        PaneLine::new(format!("{} = {}", var.name, value))
+                    })
+                    .collect(),
+                None => vec![PaneLine::new("No symbol table info available.".to_string())],
+            }
+        };
+        let registers = match self.session.inferior().map(|inf| inf.registers()) {
+            Some(Ok(regs)) => {
+                let values = [
+                    ("rip", regs.rip),
+                    ("rsp", regs.rsp),
+                    ("rbp", regs.rbp),
+                    ("rax", regs.rax),
+                    ("rbx", regs.rbx),
+                    ("rcx", regs.rcx),
+                    ("rdx", regs.rdx),
+                    ("rsi", regs.rsi),
+                    ("rdi", regs.rdi),
+                    ("r8", regs.r8),
+                    ("r9", regs.r9),
+                    ("r10", regs.r10),
+                    ("r11", regs.r11),
+                    ("r12", regs.r12),
+                    ("r13", regs.r13),
+                    ("r14", regs.r14),
+                    ("r15", regs.r15),
+                    ("eflags", regs.eflags),
+                ];
+                values
+                    .iter()
+                    .map(|(name, value)| PaneLine::new(format!("{:<7}{:#018x}", name, value)))
+                    .collect()
+            }
+            _ => not_running(),
+        };
+        let backtrace = if frames.is_empty() {
+            not_running()
+        } else {
+            (0..frames.len())
+                .map(|index| PaneLine {
+                    text: self.format_frame(&frames, index),
+                    highlight: index == self.selected_frame,
+                })
+                .collect()
+        };
+        let mut backtrace = Pane::new("Backtrace", backtrace);
+        backtrace.focus = Some(self.selected_frame);
+        Screen {
+            source: self.tui_source_pane(&frames),
+            locals: Pane::new("Locals", locals),
+            registers: Pane::new("Registers", registers),
+            backtrace,
+        }
+    }
+
+    /// Shows the lines last listed with `list`, or else those around where the selected frame
+    /// is, or else main. The selected frame's line is highlighted and lines with breakpoints are
+    /// marked.
+    fn tui_source_pane(&self, frames: &[Frame]) -> Pane {
+        let current = frames
+            .get(self.selected_frame)
+            .and_then(|frame| frame.line.clone());
+        let main = || {
+            let function = self.session.debug_data().get_function("main")?;
+            self.session
+                .debug_data()
+                .get_line_from_addr(function.address)
+        };
+        let (file, focus) = match (&self.next_list, &current) {
+            (Some((file, next)), _) => (file.clone(), next.saturating_sub(LIST_SIZE / 2 + 1)),
+            (None, Some(line)) => (line.file.clone(), line.number),
+            (None, None) => match main() {
+                Some(line) => (line.file, line.number),
+                None => return Pane::new("Source", Vec::new()),
+            },
+        };
+        let source = match fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(err) => {
+                let message = format!("Could not read {}: {}", file, err);
+                return Pane::new(&file, vec![PaneLine::new(message)]);
+            }
+        };
+        let breakpoint_lines: Vec<usize> = self
+            .session
+            .breakpoints()
+            .iter()
+            .filter_map(|bp| self.session.debug_data().get_line_from_addr(bp.addr))
+            .filter(|line| line.file == file)
+            .map(|line| line.number)
+            .collect();
+        let current_line = current
+            .filter(|line| line.file == file)
+            .map(|line| line.number);
+        let lines = source
+            .lines()
+            .enumerate()
+            .map(|(index, text)| {
+                let number = index + 1;
+                let is_current = current_line == Some(number);
+                let marker = if breakpoint_lines.contains(&number) {
+                    'B'
+                } else {
+                    ' '
+                };
+                PaneLine {
+                    text: format!(
+                        "{}{}{:>4}  {}",
+                        marker,
+                        if is_current { '>' } else { ' ' },
+                        number,
+                        text
+                    ),
+                    highlight: is_current,
+                }
+            })
+            .collect();
+        let mut pane = Pane::new(&file, lines);
+        pane.focus = Some(focus.saturating_sub(1));
+        pane
+    }
+
+    /// Runs the commands of the breakpoint the inferior stopped at. A command that resumes the
+    /// inferior replaces whatever is left with the commands of the next breakpoint it stops at,
+    /// so a list ending in `continue` is picked up again by the outermost caller rather than by
+    /// nested calls.
+    fn run_queued_commands(&mut self) {
+        if self.draining_commands {
+            return;
+        }
+        self.draining_commands = true;
+        while !self.quitting {
+            let line = match self.command_queue.pop_front() {
+                Some(line) => line,
+                None => break,
+            };
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match DebuggerCommand::from_tokens(&tokens) {
+                Some(cmd) => self.execute(cmd),
+                None => println!("Unrecognized command \"{}\".", line),
+            }
+        }
+        self.draining_commands = false;
+    }
+
+    /// Tells the user why the inferior stopped and resets the state that depends on where it
+    /// stopped.
+    fn report_stop(&mut self, stop: Result<Stop, Error>) {
+        self.print_notices();
+        self.selected_frame = 0;
+        self.next_list = None;
+        self.command_queue.clear();
+        let stop = match stop {
+            Ok(stop) => stop,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        let pc = self
+            .session
+            .inferior()
+            .and_then(|inf| inf.registers().ok())
+            .map(|regs| regs.rip as usize);
+        match stop {
+            Stop::Breakpoint(_)
+            | Stop::Step
+            | Stop::Signal(_)
+            | Stop::Catchpoint(_, Caught::Signal(_)) => {
+                let mut silent = false;
+                for number in self.session.hit_breakpoints() {
+                    if let Some(commands) = self.breakpoint_commands.get(number) {
+                        // A command list starting with `silent` suppresses the stop message
+                        let starts_silent = commands.first().map(String::as_str) == Some("silent");
+                        silent |= starts_silent;
+                        let commands = commands.iter().skip(starts_silent as usize);
+                        self.command_queue.extend(commands.cloned());
+                    }
+                }
+                // Forget the commands of temporary breakpoints that were deleted
+                let breakpoints = self.session.breakpoints();
+                self.breakpoint_commands
+                    .retain(|number, _| breakpoints.iter().any(|bp| bp.number == *number));
+                if silent {
+                    return;
+                }
+                match stop {
+                    Stop::Catchpoint(number, Caught::Signal(signal)) => {
+                        println!("Catchpoint {} (signal {})", number, signal)
+                    }
+                    Stop::Signal(signal) => println!("Child stopped (signal {})", signal),
+                    _ => println!("Child stopped (signal {})", signal::SIGTRAP),
+                }
+                if let Some(pc) = pc {
+                    self.print_stop_location(pc);
+                }
+                self.do_displays();
+            }
+            Stop::Catchpoint(catchpoint, Caught::Syscall(entry, number)) => {
+                let inf = self.session.inferior().unwrap();
+                let regs = match inf.registers() {
+                    Ok(regs) => regs,
+                    Err(err) => {
+                        println!("Could not read registers: {}", err);
+                        return;
+                    }
+                };
+                let args = [regs.rdi, regs.rsi, regs.rdx, regs.r10, regs.r8, regs.r9];
+                let read = |addr, len| inf.read_memory(addr, len).ok();
+                let name = syscalls::name(number);
+                if entry {
+                    println!(
+                        "Catchpoint {} (call to syscall {}), {}",
+                        catchpoint,
+                        name,
+                        syscalls::format_call(number, &args, None, &read)
+                    );
+                } else {
+                    let ret = regs.rax as i64;
+                    println!(
+                        "Catchpoint {} (returned from syscall {}), {} = {}",
+                        catchpoint,
+                        name,
+                        syscalls::format_call(number, &args, Some(ret), &read),
+                        syscalls::format_return(number, ret)
+                    );
+                }
+                self.print_stop_location(regs.rip as usize);
+                self.do_displays();
+            }
+            Stop::Catchpoint(catchpoint, Caught::Event(event)) => {
+                match event {
+                    Event::Fork(pid) => {
+                        println!("Catchpoint {} (forked process {})", catchpoint, pid)
+                    }
+                    Event::Vfork(pid) => {
+                        println!("Catchpoint {} (vforked process {})", catchpoint, pid)
+                    }
+                    Event::Exec => {
+                        let inf = self.session.inferior().unwrap();
+                        Debugger::print_exec(inf.pid(), &inf.executable());
+                        println!("Catchpoint {} (exec'd {})", catchpoint, inf.executable());
+                    }
+                    Event::Exit(status) if libc::WIFSIGNALED(status) => {
+                        let signal = libc::WTERMSIG(status);
+                        let signal = signal::Signal::try_from(signal)
+                            .map_or(signal.to_string(), |signal| signal.to_string());
+                        println!("Catchpoint {} (exiting from signal {})", catchpoint, signal);
+                    }
+                    Event::Exit(status) => println!(
+                        "Catchpoint {} (exiting with status {})",
+                        catchpoint,
+                        libc::WEXITSTATUS(status)
+                    ),
+                }
+                if let Some(pc) = pc {
+                    self.print_stop_location(pc);
+                }
+                self.do_displays();
+            }
+            Stop::Exited(code) => println!("Child exited (status {})", code),
+            Stop::Signaled(signal) => println!("Child exited (signal {})", signal),
+        }
+    }
+
+    /// Tells the user what happened while the inferior ran that did not stop it.
+    fn print_notices(&mut self) {
+        for notice in self.session.take_notices() {
+            match notice {
+                Notice::UnsupportedInstruction(addr) => println!(
+                    "Process record does not support the instruction at address {:#x}.",
+                    addr
+                ),
+                Notice::ConditionError { number, error } => {
+                    println!("Error in testing condition for breakpoint {}:", number);
+                    println!("{}", error);
+                }
+                Notice::Detached(pid) => {
+                    println!("[Detaching after fork from child process {}]", pid)
+                }
+                Notice::Exec { pid, program } => Debugger::print_exec(pid, &program),
+                Notice::TemporaryBreakpointDeleted(number) => {
+                    println!("Temporary breakpoint {} deleted", number)
+                }
+            }
+        }
+    }
+
+    /// Tells the user that the inferior replaced its program.
+    fn print_exec(pid: nix::unistd::Pid, program: &str) {
+        println!("process {} is executing new program: {}", pid, program);
+    }
+
+    /// Says which function and line `addr` is in, if it is in the program's own code.
+    fn print_stop_location(&self, addr: usize) {
+        let func = self.session.debug_data().get_function_from_addr(addr);
+        let line = self.session.debug_data().get_line_from_addr(addr);
+        if let (Some(func), Some(line)) = (func, line) {
+            println!("Stop at {} ({}:{})", func, line.file, line.number);
+        }
+    }
+
+    /// Runs until the selected frame returns, then prints the value it returned.
+    fn finish(&mut self) {
+        let frames = match self.frames() {
+            Some(frames) => frames,
+            None => return,
+        };
+        let index = self.selected_frame.min(frames.len() - 1);
+        if let Err(err) = self.session.check_finish(index) {
+            println!("{}", err);
+            return;
+        }
+        println!("Run till exit from {}", self.format_frame(&frames, index));
+        let finish = match self.session.finish(index) {
+            Ok(finish) => finish,
+            Err(err) => {
+                self.print_notices();
+                println!("{}", err);
+                return;
+            }
+        };
+        self.report_stop(Ok(finish.stop));
+        match finish.value {
+            Some(Ok(value)) => println!("Value returned is {}", self.session.format_value(&value)),
+            Some(Err(err)) => println!("Could not read the returned value: {}", err),
+            None => {}
+        }
+        self.run_queued_commands();
+    }
+
+    /// Pops the selected frame (and every frame inside it) without running the rest of its
+    /// function, returning `value` to the caller. Callee-saved registers other than %rbp are not
+    /// restored.
+    fn force_return(&mut self, value: Option<String>) {
+        let frames = match self.frames() {
+            Some(frames) => frames,
+            None => return,
+        };
+        let index = self.selected_frame.min(frames.len() - 1);
+        let frame = &frames[index];
+        if frame.inlined {
+            println!("Cannot return from an inlined frame.");
+            return;
+        }
+        let return_type = self
+            .frame_function(&frames, index)
+            .and_then(|function| function.return_type.clone());
+        let value = match value {
+            Some(value) => match self.return_value(&value, return_type) {
+                Some(value) => Some(value),
+                None => return,
+            },
+            None => None,
+        };
+        let inf = self.session.inferior().unwrap();
+        let mut regs = match inf.registers() {
+            Ok(regs) => regs,
+            Err(err) => {
+                println!("Error reading registers: {}", err);
+                return;
+            }
+        };
+        if let Some(value) = value {
+            let mut bytes = value.bytes;
+            bytes.resize(16, 0);
+            if session::is_floating_point(&value.ty) {
+                let result = inf.fp_registers().and_then(|mut fpregs| {
+                    for (i, word) in bytes.chunks(4).enumerate() {
+                        fpregs.xmm_space[i] = u32::from_le_bytes(word.try_into().unwrap());
+                    }
+                    inf.set_fp_registers(fpregs)
+                });
+                if let Err(err) = result {
+                    println!("Error setting %xmm0: {}", err);
+                    return;
+                }
+            } else {
+                regs.rax = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+            }
+        }
+        regs.rip = frame.return_addr as u64;
+        regs.rsp = frame.cfa as u64;
+        regs.rbp = frame.caller_base_ptr as u64;
+        if let Err(err) = inf.set_registers(regs) {
+            println!("Error setting registers: {}", err);
+            return;
+        }
+        self.selected_frame = 0;
+        self.select_frame(0);
+    }
+
+    /// Evaluates the value `return` gives back and converts it to the function's return type.
+    /// Only integers, floats, enums and pointers can be returned: they come back in %rax or
+    /// %xmm0, while structs may need two registers or memory the caller provided.
+    fn return_value(&self, value: &str, return_type: Option<Type>) -> Option<expression::Value> {
+        let return_type = match return_type {
+            Some(return_type) => return_type,
+            None => {
+                println!("The function returns void; no value can be returned.");
+                return None;
+            }
+        };
+        let scalar = match return_type.kind {
+            TypeKind::Base(_) | TypeKind::Enum(_) | TypeKind::Pointer(_) => {
+                ReturnLocation::of(self.session.debug_data(), &return_type) != ReturnLocation::X87
+            }
+            _ => false,
+        };
+        if !scalar {
+            println!("Returning a {} is not supported.", return_type.name);
+            return None;
+        }
+        let value = self
+            .evaluate(value)
+            .and_then(|value| Ok(expression::argument(value, Some(&return_type))?));
+        match value {
+            Ok(value) => Some(value),
+            Err(err) => {
+                println!("{}", err);
+                None
+            }
+        }
+    }
+
+    /// Returns the frames of the stopped inferior, or None (after saying why) if there are none.
+    fn frames(&self) -> Option<Vec<Frame>> {
+        let inf = match self.session.inferior() {
             Some(inf) => inf,
             None => {
                 println!("No child process now");
+                return None;
+            }
+        };
+        match inf.backtrace(self.session.debug_data()) {
+            Ok(frames) if !frames.is_empty() => Some(frames),
+            Ok(_) => {
+                println!("No function information found");
+                None
+            }
+            Err(err) => {
+                println!("Error reading the stack: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Returns the function whose variables are visible in frame `index`.
+    fn frame_function(&self, frames: &[Frame], index: usize) -> Option<&Function> {
+        self.session.debug_data().get_frame_function(frames, index)
+    }
+
+    fn format_frame(&self, frames: &[Frame], index: usize) -> String {
+        let frame = &frames[index];
+        let func = frame.function.as_deref().unwrap_or("??");
+        let args: Vec<String> = match self.frame_function(frames, index) {
+            Some(function) => function
+                .variables
+                .iter()
+                .filter(|var| var.is_parameter)
+                .map(|var| {
+                    let value = self.read_variable(var, frame, function.frame_base);
+                    format!("{}={}", var.name, value)
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let inlined = if frame.inlined { " [inlined]" } else { "" };
+        match &frame.line {
+            Some(line) => format!(
+                "#{:<2} {} ({}) at {}{}",
+                index,
+                func,
+                args.join(", "),
+                line,
+                inlined
+            ),
+            None => format!("#{:<2} {:#x} in {} (){}", index, frame.pc, func, inlined),
+        }
+    }
+
+    /// Prints the backtrace. With `full`, the local variables of each frame are printed too. A
+    /// positive `limit` prints only that many innermost frames, a negative one that many
+    /// outermost frames.
+    fn print_backtrace(&self, full: bool, limit: Option<isize>) {
+        let frames = match self.frames() {
+            Some(frames) => frames,
+            None => return,
+        };
+        let range = match limit {
+            Some(limit) if limit < 0 => {
+                frames.len().saturating_sub(limit.abs() as usize)..frames.len()
+            }
+            Some(limit) => 0..(limit as usize).min(frames.len()),
+            None => 0..frames.len(),
+        };
+        let end = range.end;
+        for i in range {
+            println!("{}", self.format_frame(&frames, i));
+            if !full {
+                continue;
+            }
+            let function = match self.frame_function(&frames, i) {
+                Some(function) => function,
+                None => continue,
+            };
+            let locals: Vec<&Variable> = function
+                .variables
+                .iter()
+                .filter(|var| !var.is_parameter)
+                .collect();
+            if locals.is_empty() {
+                println!("        No locals.");
+            }
+            for var in locals {
+                let value = self.read_variable(var, &frames[i], function.frame_base);
+                println!("        {} = {}", var.name, value);
+            }
+        }
+        if end < frames.len() {
+            println!("(More stack frames follow...)");
+        }
+    }
+
+    /// Reads a source file line, if the file is available.
+    fn source_line(file: &str, number: usize) -> Option<String> {
+        let source = fs::read_to_string(file).ok()?;
+        Some(source.lines().nth(number.checked_sub(1)?)?.to_string())
+    }
+
+    /// Makes frame `index` the selected frame and prints it.
+    fn select_frame(&mut self, index: usize) {
+        let frames = match self.frames() {
+            Some(frames) => frames,
+            None => return,
+        };
+        let frame = match frames.get(index) {
+            Some(frame) => frame,
+            None => {
+                println!("No frame at level {}", index);
                 return;
             }
         };
-        let re = inf.cont().expect("Error continuing inferior");
-        match re {
-            Stopped(signal, reg) => {
-                println!("Child stopped (signal {})", signal);
-                let func = match self.debug_data.get_function_from_addr(reg) {
-                    Some(func) => func,
-                    None => return,
+        self.selected_frame = index;
+        self.next_list = None;
+        println!("{}", self.format_frame(&frames, index));
+        if let Some(line) = &frame.line {
+            if let Some(text) = Debugger::source_line(&line.file, line.number) {
+                println!("{}\t{}", line.number, text);
+            }
+        }
+    }
+
+    fn print_frame_info(&self) {
+        let frames = match self.frames() {
+            Some(frames) => frames,
+            None => return,
+        };
+        let index = self.selected_frame.min(frames.len() - 1);
+        let frame = &frames[index];
+        println!("Stack level {}, frame at {:#x}:", index, frame.cfa);
+        let location = match &frame.line {
+            Some(line) => format!(" ({})", line),
+            None => String::new(),
+        };
+        println!(
+            " rip = {:#x} in {}{}; saved rip = {:#x}",
+            frame.pc,
+            frame.function.as_deref().unwrap_or("??"),
+            location,
+            frame.return_addr
+        );
+        if frame.inlined {
+            println!(" inlined into frame {}", index + 1);
+        }
+        if let Some(caller) = frames.get(index + 1) {
+            println!(" called by frame at {:#x}", caller.cfa);
+        }
+        if index > 0 {
+            println!(" caller of frame at {:#x}", frames[index - 1].cfa);
+        }
+    }
+
+    /// Compiles the regular expression of `info functions` and the like. No pattern matches
+    /// every name.
+    fn compile_pattern(pattern: &Option<String>) -> Option<Regex> {
+        match Regex::new(pattern.as_deref().unwrap_or("")) {
+            Ok(regex) => Some(regex),
+            Err(err) => {
+                println!("Invalid regexp: {}", err);
+                None
+            }
+        }
+    }
+
+    fn print_symbols_heading(what: &str, pattern: &Option<String>) {
+        match pattern {
+            Some(pattern) => println!("All {} matching regular expression \"{}\":", what, pattern),
+            None => println!("All defined {}:", what),
+        }
+    }
+
+    /// Formats a declaration of `name` with type `type_name`, as in `char *name`.
+    fn declaration(type_name: &str, name: &str) -> String {
+        if type_name.ends_with('*') {
+            format!("{}{}", type_name, name)
+        } else {
+            format!("{} {}", type_name, name)
+        }
+    }
+
+    fn info_functions(&self, pattern: Option<String>) {
+        let regex = match Debugger::compile_pattern(&pattern) {
+            Some(regex) => regex,
+            None => return,
+        };
+        Debugger::print_symbols_heading("functions", &pattern);
+        for (file, functions) in self.session.debug_data().functions_matching(&regex) {
+            println!("\nFile {}:", file);
+            for function in functions {
+                let return_type = match &function.return_type {
+                    Some(return_type) => return_type.name.as_str(),
+                    None => "void",
                 };
-                let line = match self.debug_data.get_line_from_addr(reg) {
-                    Some(line) => line,
-                    None => return,
+                let parameters: Vec<&str> = function
+                    .variables
+                    .iter()
+                    .filter(|var| var.is_parameter)
+                    .map(|var| var.entity_type.name.as_str())
+                    .collect();
+                let parameters = if parameters.is_empty() {
+                    "void".to_string()
+                } else {
+                    parameters.join(", ")
                 };
-                println!("Stop at {} ({}:{})", func, line.file, line.number);
+                println!(
+                    "{}:\t{}({});",
+                    function.line_number,
+                    Debugger::declaration(return_type, &function.name),
+                    parameters
+                );
+            }
+        }
+    }
+
+    fn info_variables(&self, pattern: Option<String>) {
+        let regex = match Debugger::compile_pattern(&pattern) {
+            Some(regex) => regex,
+            None => return,
+        };
+        Debugger::print_symbols_heading("variables", &pattern);
+        for (file, variables) in self.session.debug_data().global_variables_matching(&regex) {
+            println!("\nFile {}:", file);
+            for var in variables {
+                println!(
+                    "{}:\t{};",
+                    var.line_number,
+                    Debugger::declaration(&var.entity_type.name, &var.name)
+                );
             }
-            Exited(code) => {
-                println!("Child exited (status {})", code);
-                self.inferior = None;
+        }
+    }
+
+    fn info_types(&self, pattern: Option<String>) {
+        let regex = match Debugger::compile_pattern(&pattern) {
+            Some(regex) => regex,
+            None => return,
+        };
+        Debugger::print_symbols_heading("types", &pattern);
+        for name in self.session.debug_data().type_names_matching(&regex) {
+            println!("\t{}", name);
+        }
+    }
+
+    /// Formats a code address with the function it is in, as in `0x401156 <func2+4>`.
+    fn format_code_address(&self, addr: usize) -> String {
+        match self.session.debug_data().get_function_containing(addr) {
+            Some(function) if function.address == addr => {
+                format!("{:#x} <{}>", addr, function.name)
             }
-            Signaled(signal) => {
-                println!("Child exited (signal {})", signal);
-                self.inferior = None;
+            Some(function) => format!(
+                "{:#x} <{}+{}>",
+                addr,
+                function.name,
+                addr - function.address
+            ),
+            None => format!("{:#x}", addr),
+        }
+    }
+
+    /// Tells where the code of a line is. Without a location, the line is the one the selected
+    /// frame is at.
+    fn info_line(&self, location: Option<String>) {
+        let addr = match location {
+            // The line of a function is the one it starts on, not the one after its prologue
+            Some(location) if self.session.debug_data().get_function(&location).is_some() => {
+                self.session
+                    .debug_data()
+                    .get_function(&location)
+                    .unwrap()
+                    .address
             }
+            Some(location) => match self.parse_location(&location) {
+                Some(addr) => addr,
+                None => {
+                    println!("Function \"{}\" not defined.", location);
+                    return;
+                }
+            },
+            None => match self.frames_quietly().get(self.selected_frame) {
+                Some(frame) => frame.pc,
+                None => {
+                    println!("No line number information available.");
+                    return;
+                }
+            },
+        };
+        match self.session.debug_data().get_line_range(addr) {
+            Some((line, end)) => println!(
+                "Line {} of \"{}\" starts at address {} and ends at {}.",
+                line.number,
+                line.file,
+                self.format_code_address(line.address),
+                self.format_code_address(end)
+            ),
+            None => println!(
+                "No line number information available for address {}",
+                self.format_code_address(addr)
+            ),
         }
     }
 
-    fn parse_address(addr: &str) -> Option<usize> {
-        let addr_without_0x = if addr.to_lowercase().starts_with("0x") {
-            &addr[2..]
+    /// Tells where a variable or function is stored. Variables of the selected frame hide
+    /// global ones.
+    fn info_address(&self, symbol: &str) {
+        let frames = self.frames_quietly();
+        let local = if self.selected_frame < frames.len() {
+            self.frame_function(&frames, self.selected_frame)
+                .and_then(|function| {
+                    let var = function.variables.iter().find(|var| var.name == symbol)?;
+                    Some((var, function.frame_base))
+                })
         } else {
-            &addr
+            None
         };
-        usize::from_str_radix(addr_without_0x, 16).ok()
+        if let Some((var, frame_base)) = local {
+            let kind = if var.is_parameter {
+                "an argument"
+            } else {
+                "a variable"
+            };
+            match var.location {
+                Location::FramePointerOffset(offset) => {
+                    let base = match frame_base {
+                        FrameBase::Cfa => "the frame's CFA",
+                        FrameBase::Rbp => "%rbp",
+                    };
+                    println!(
+                        "Symbol \"{}\" is {} at offset {} from {}.",
+                        symbol, kind, offset, base
+                    )
+                }
+                Location::Address(addr) => {
+                    println!(
+                        "Symbol \"{}\" is static storage at address {:#x}.",
+                        symbol, addr
+                    )
+                }
+            }
+        } else if let Some(var) = self.session.debug_data().get_global_variable(symbol) {
+            match var.location {
+                Location::Address(addr) => {
+                    println!(
+                        "Symbol \"{}\" is static storage at address {:#x}.",
+                        symbol, addr
+                    )
+                }
+                Location::FramePointerOffset(_) => {
+                    println!("Symbol \"{}\" is optimized out.", symbol)
+                }
+            }
+        } else if let Some(function) = self.session.debug_data().get_function(symbol) {
+            println!(
+                "Symbol \"{}\" is a function at address {:#x}.",
+                symbol, function.address
+            );
+        } else {
+            println!("No symbol \"{}\" in current context.", symbol);
+        }
     }
 
-    fn insert_bp(&mut self, addr:usize) {
-        self.break_point.push(addr);
-        println!("Breakpoint at 0x{:x}", addr);
-        let inf = match &mut self.inferior {
-            Some(inf) => inf,
-            None => {
+    /// Tells which source file the selected frame, or the last `list`, is in.
+    fn info_source(&self) {
+        let frames = self.frames_quietly();
+        let file = match frames
+            .get(self.selected_frame)
+            .and_then(|frame| frame.line.as_ref())
+        {
+            Some(line) => line.file.clone(),
+            None => match &self.next_list {
+                Some((file, _)) => file.clone(),
+                None => {
+                    println!("No current source file.");
+                    return;
+                }
+            },
+        };
+        println!("Current source file is {}", file);
+        if let Ok(path) = fs::canonicalize(&file) {
+            println!("Located in {}", path.display());
+        }
+        if let Ok(source) = fs::read_to_string(&file) {
+            println!("Contains {} lines.", source.lines().count());
+        }
+    }
+
+    /// Reads the value of a variable as seen from `frame` and formats it. Pointers are shown as
+    /// addresses, followed by the string they point to if they are C strings.
+    fn read_variable(&self, var: &Variable, frame: &Frame, frame_base: FrameBase) -> String {
+        let inf = self.session.inferior().unwrap();
+        let addr = frame.variable_address(var, frame_base);
+        match inf.read_memory(addr, var.entity_type.size) {
+            Ok(bytes) => self.format_typed_value(&bytes, &var.entity_type),
+            Err(_) => format!("<error: Cannot access memory at address {:#x}>", addr),
+        }
+    }
+
+    /// Formats a value of the given type the way `print` shows it.
+    fn format_typed_value(&self, bytes: &[u8], entity_type: &Type) -> String {
+        self.session.format_bytes(bytes, entity_type)
+    }
+
+    /// Parses and evaluates an expression in the selected frame.
+    fn evaluate(&self, expression: &str) -> Result<expression::Value, Error> {
+        self.session.evaluate(expression, self.selected_frame)
+    }
+
+    /// Evaluates an expression for its side effects, such as calling a function, and prints its
+    /// value unless it is void.
+    fn call_expression(&self, expression: &str) {
+        match self.evaluate(expression) {
+            Ok(ref value) if Debugger::is_void(&value.ty) => {}
+            Ok(value) => println!(
+                "{} = {}",
+                expression,
+                self.format_typed_value(&value.bytes, &value.ty)
+            ),
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    fn is_void(entity_type: &Type) -> bool {
+        match entity_type.kind {
+            TypeKind::Unknown => entity_type.size == 0,
+            _ => false,
+        }
+    }
+
+    /// Prints the value of an expression in the selected frame.
+    fn print_expression(&self, expression: &str) {
+        match self.evaluate(expression) {
+            Ok(ref value) if Debugger::is_void(&value.ty) => println!("{} = void", expression),
+            Ok(value) => println!(
+                "{} = {}",
+                expression,
+                self.format_typed_value(&value.bytes, &value.ty)
+            ),
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    /// Prints one displayed expression, unless it is not in scope.
+    fn print_display(&self, display: &Display) {
+        let value = match self.evaluate(&display.expression) {
+            Ok(value) => self.format_typed_value(&value.bytes, &value.ty),
+            Err(Error::Expression(expression::Error::NoSymbol(_))) => return,
+            Err(err) => format!("<error: {}>", err),
+        };
+        println!("{}: {} = {}", display.number, display.expression, value);
+    }
+
+    /// Prints every displayed expression that is in scope where the inferior stopped.
+    fn do_displays(&self) {
+        if !self.session.is_running() {
+            return;
+        }
+        for display in &self.displays {
+            self.print_display(display);
+        }
+    }
+
+    fn add_display(&mut self, expression: String) {
+        let display = Display {
+            number: self.next_display_number,
+            expression,
+        };
+        self.next_display_number += 1;
+        if self.session.is_running() {
+            self.print_display(&display);
+        }
+        self.displays.push(display);
+    }
+
+    fn undisplay(&mut self, number: usize) {
+        let count = self.displays.len();
+        self.displays.retain(|display| display.number != number);
+        if self.displays.len() == count {
+            println!("No display number {}.", number);
+        }
+    }
+
+    fn print_display_info(&self) {
+        if self.displays.is_empty() {
+            println!("There are no auto-display expressions now.");
+            return;
+        }
+        println!("Auto-display expressions now in effect:");
+        println!("Num Expression");
+        for display in &self.displays {
+            println!(
+                "{:<3} {}",
+                format!("{}:", display.number),
+                display.expression
+            );
+        }
+    }
+
+    /// Lists source lines around `line`, around the selected frame's line, or after the lines
+    /// listed last.
+    fn list_source(&mut self, line: Option<usize>) {
+        let (file, first) = match (line, self.next_list.take()) {
+            (None, Some(next)) => next,
+            (line, _) => {
+                let frames = match self.frames() {
+                    Some(frames) => frames,
+                    None => return,
+                };
+                let frame_line = match frames[self.selected_frame.min(frames.len() - 1)]
+                    .line
+                    .clone()
+                {
+                    Some(frame_line) => frame_line,
+                    None => {
+                        println!("No line information for the selected frame");
+                        return;
+                    }
+                };
+                let center = line.unwrap_or(frame_line.number);
+                (frame_line.file, center.saturating_sub(LIST_SIZE / 2).max(1))
+            }
+        };
+        let source = match fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(err) => {
+                println!("Could not read {}: {}", file, err);
                 return;
             }
         };
-        let _ = inf.insert_breakpoint(addr);
+        let lines: Vec<&str> = source.lines().collect();
+        if first > lines.len() {
+            println!(
+                "Line number {} out of range; \"{}\" has {} lines.",
+                first,
+                file,
+                lines.len()
+            );
+            return;
+        }
+        let last = (first + LIST_SIZE - 1).min(lines.len());
+        for number in first..=last {
+            println!("{}\t{}", number, lines[number - 1]);
+        }
+        self.next_list = Some((file, last + 1));
     }
 
-    pub fn run(&mut self) {
-        loop {
-            match self.get_next_command() {
-                DebuggerCommand::Run(args) => {
-                    if let Some(inferior) = Inferior::new(&self.target, &args, &self.break_point) {
-                        // Create the inferior
-                        match &mut self.inferior {
-                            Some(inf) => {
-                                inf.kill();
+    /// Resolves a location given as `*address`, a line number or a function name, optionally
+    /// preceded by the source file.
+    fn parse_location(&self, location: &str) -> Option<usize> {
+        self.session.debug_data().get_addr_for_location(location)
+    }
+
+    /// Sets a catchpoint.
+    fn catch(&mut self, catch: Catch) {
+        let recording = self
+            .session
+            .inferior()
+            .and_then(|inf| inf.recorded_instructions())
+            .is_some();
+        let (kind, description) = match catch {
+            Catch::Syscall(_) if recording => {
+                println!("Cannot catch syscalls while recording.");
+                return;
+            }
+            Catch::Syscall(names) => {
+                let mut numbers = Vec::new();
+                for name in &names {
+                    let number = match name.parse::<usize>() {
+                        Ok(number) => number,
+                        Err(_) => match syscalls::lookup_name(name) {
+                            Some(syscall) => syscall.number,
+                            None => {
+                                println!("Unknown syscall name '{}'.", name);
+                                return;
                             }
-                            None => {}
+                        },
+                    };
+                    numbers.push(number);
+                }
+                let description = match numbers.len() {
+                    0 => "any syscall".to_string(),
+                    count => {
+                        let syscalls: Vec<String> = numbers
+                            .iter()
+                            .map(|number| format!("'{}' [{}]", syscalls::name(*number), number))
+                            .collect();
+                        let noun = if count == 1 { "syscall" } else { "syscalls" };
+                        format!("{} {}", noun, syscalls.join(" "))
+                    }
+                };
+                (CatchpointKind::Syscall(numbers), description)
+            }
+            Catch::Signal(names) => {
+                let mut signals = Vec::new();
+                for name in &names {
+                    match Debugger::parse_signal(name) {
+                        Some(signal) => signals.push(signal),
+                        None => {
+                            println!("Only signals 1-64 are valid as numeric signals.");
+                            return;
                         }
-                        self.inferior = Some(inferior);
-                        self.contin();
-                    } else {
-                        println!("Error starting subprocess");
                     }
                 }
-                DebuggerCommand::Contin => self.contin(),
-                DebuggerCommand::Backtrace => match &self.inferior {
-                    Some(inf) => {
-                        let _ = inf.print_backtrace(&self.debug_data);
+                let description = match signals.len() {
+                    0 => "standard signals".to_string(),
+                    1 => format!("signal {}", signals[0]),
+                    _ => {
+                        let names: Vec<String> =
+                            signals.iter().map(|signal| signal.to_string()).collect();
+                        format!("signals {}", names.join(" "))
                     }
-                    None => {
-                        println!("No child process now");
-                    }
-                },
-                DebuggerCommand::Quit => {
-                    match self.inferior {
-                        Some(ref mut inf) => {
-                            inf.kill();
+                };
+                (CatchpointKind::Signal(signals), description)
+            }
+            Catch::Fork => (CatchpointKind::Fork, "fork".to_string()),
+            Catch::Vfork => (CatchpointKind::Vfork, "vfork".to_string()),
+            Catch::Exec => (CatchpointKind::Exec, "exec".to_string()),
+            Catch::Exit => (CatchpointKind::Exit, "exit".to_string()),
+        };
+        let number = self.session.catch(kind);
+        println!("Catchpoint {} ({})", number, description);
+    }
+
+    /// Parses a signal given by name (`SIGUSR1` or `USR1`) or number.
+    fn parse_signal(name: &str) -> Option<signal::Signal> {
+        if let Ok(number) = name.parse::<i32>() {
+            return signal::Signal::try_from(number).ok();
+        }
+        let name = name.to_uppercase();
+        if name.starts_with("SIG") {
+            name.parse().ok()
+        } else {
+            format!("SIG{}", name).parse().ok()
+        }
+    }
+
+    /// Starts a new inferior (killing the old one, if any) with the user's breakpoints inserted.
+    /// Arguments given to `run` or `start` replace the ones the program was run with before.
+    /// Returns false if it could not be started.
+    fn start_inferior(&mut self, args: Vec<String>) -> bool {
+        if !args.is_empty() {
+            self.args = args;
+        }
+        let (args, redirections) = match debugger_command::parse_redirections(&self.args) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                println!("{}", err);
+                return false;
+            }
+        };
+        self.session.set_launch(Launch {
+            redirections,
+            ..self.launch.clone()
+        });
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match self.session.launch(&args) {
+            Ok(()) => {
+                self.delete_checkpoints();
+                true
+            }
+            Err(err) => {
+                println!("{}", err);
+                false
+            }
+        }
+    }
+
+    fn set_environment(&mut self, name: String, value: String) {
+        if name.is_empty() {
+            println!("Argument required (environment variable and value).");
+            return;
+        }
+        if value.is_empty() {
+            println!("Setting environment variable \"{}\" to null value.", name);
+        }
+        let environment = self.launch.environment.get_or_insert_with(Vec::new);
+        environment.retain(|(existing, _)| *existing != name);
+        environment.push((name, value));
+    }
+
+    fn unset_environment(&mut self, name: Option<String>) {
+        let environment = self.launch.environment.get_or_insert_with(Vec::new);
+        match name {
+            Some(name) => environment.retain(|(existing, _)| *existing != name),
+            None => environment.clear(),
+        }
+    }
+
+    fn show_environment(&self, name: Option<String>) {
+        let environment = match &self.launch.environment {
+            Some(environment) => environment,
+            None => return,
+        };
+        match name {
+            Some(name) => match environment.iter().find(|(existing, _)| *existing == name) {
+                Some((_, value)) => println!("{} = {}", name, value),
+                None => println!("Environment variable \"{}\" not defined.", name),
+            },
+            None => {
+                for (name, value) in environment {
+                    println!("{}={}", name, value);
+                }
+            }
+        }
+    }
+
+    /// Sets the directory the program starts in.
+    fn set_cwd(&mut self, directory: Option<String>) {
+        self.launch.cwd = directory.map(|directory| expand_home(&directory));
+    }
+
+    /// Runs the commands in a file as if they had been typed, stopping at the first line that is
+    /// not a command. The lines of a `commands` list are taken from the file up to its `end`.
+    fn source(&mut self, path: &str) {
+        let contents = match fs::read_to_string(expand_home(path)) {
+            Ok(contents) => contents,
+            Err(err) => {
+                println!("{}: {}.", path, err);
+                return;
+            }
+        };
+        let mut lines = contents.lines().enumerate();
+        while let Some((index, line)) = lines.next() {
+            if self.quitting {
+                return;
+            }
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.is_empty() || tokens[0].starts_with('#') {
+                continue;
+            }
+            match DebuggerCommand::from_tokens(&tokens) {
+                Some(DebuggerCommand::Commands { number, .. }) => {
+                    let mut commands = Vec::new();
+                    for (_, line) in lines.by_ref() {
+                        if line.trim() == "end" {
+                            break;
                         }
-                        None => {}
+                        Debugger::add_to_command_list(&mut commands, line);
                     }
+                    self.execute(DebuggerCommand::Commands { number, commands });
+                }
+                Some(cmd) => self.execute(cmd),
+                None => {
+                    println!(
+                        "{}:{}: Error in sourced command file:\nUnrecognized command \"{}\".",
+                        path,
+                        index + 1,
+                        line.trim()
+                    );
                     return;
                 }
-                DebuggerCommand::Breakpoint(breakpoint) => {
-                    let addr;
-                    if breakpoint.starts_with('*') {
-                        addr = Debugger::parse_address(&breakpoint[1..]);
-                    }else {
-                        let linenum:usize;
-                        match breakpoint.parse::<usize>(){
-                            Ok(num)=> {
-                                linenum = num;
-                                addr = self.debug_data.get_addr_for_line(None,linenum);
+            }
+        }
+    }
 
-                            }
-                            Err(_)=>{
-                                addr = self.debug_data.get_addr_for_function(None,&breakpoint);
-                            }
-                        }
+    /// Runs ~/.deetinit and then ./.deetinit, if they exist.
+    fn source_init_files(&mut self) {
+        let home = expand_home("~/.deetinit");
+        let mut paths = vec![home.clone()];
+        // Started from the home directory, the same file would otherwise run twice
+        let local = fs::canonicalize(".deetinit").ok();
+        if local.is_some() && local != fs::canonicalize(&home).ok() {
+            paths.push(".deetinit".to_string());
+        }
+        for path in paths {
+            if Path::new(&path).is_file() {
+                self.source(&path);
+            }
+        }
+    }
+
+    /// Writes the commands that set the current breakpoints and catchpoints again to a file,
+    /// for `source` to read.
+    fn save_breakpoints(&self, path: &str) {
+        let mut saved: Vec<(usize, String)> = Vec::new();
+        for bp in self.session.breakpoints() {
+            let mut script = format!(
+                "{} {}",
+                if bp.temporary { "tbreak" } else { "break" },
+                bp.location
+            );
+            if let Some(condition) = &bp.condition {
+                script += &format!(" if {}", condition);
+            }
+            script.push('\n');
+            let commands = self.breakpoint_commands.get(&bp.number);
+            if let Some(commands) = commands.filter(|commands| !commands.is_empty()) {
+                script += "commands\n";
+                for command in commands {
+                    script += &format!("  {}\n", command);
+                }
+                script += "end\n";
+            }
+            saved.push((bp.number, script));
+        }
+        for catchpoint in self.session.catchpoints() {
+            let script = format!("catch {}\n", catchpoint.kind.arguments());
+            saved.push((catchpoint.number, script));
+        }
+        if saved.is_empty() {
+            println!("Nothing to save.");
+            return;
+        }
+        // Keep the order they were set in, so they are numbered the same way when read back
+        saved.sort_by_key(|(number, _)| *number);
+        let script: String = saved.into_iter().map(|(_, script)| script).collect();
+        match fs::write(expand_home(path), script) {
+            Ok(()) => println!("Saved to file '{}'.", path),
+            Err(err) => println!("{}: {}.", path, err),
+        }
+    }
+
+    /// Saves the state of the inferior in a forked copy of it.
+    fn checkpoint(&mut self) {
+        let inf = match self.session.inferior() {
+            Some(inf) => inf,
+            None => {
+                println!("The program is not being run.");
+                return;
+            }
+        };
+        match inf.fork() {
+            Ok(copy) => {
+                let number = self.next_checkpoint_number;
+                self.next_checkpoint_number += 1;
+                println!("checkpoint {}: fork returned pid {}.", number, copy.pid());
+                self.checkpoints.push(Checkpoint {
+                    number,
+                    inferior: copy,
+                });
+            }
+            Err(err) => println!("Error making checkpoint: {}", err),
+        }
+    }
+
+    /// Describes where a process is stopped, as in `info checkpoints`.
+    fn describe_process(&self, inf: &Inferior) -> String {
+        let rip = match inf.registers() {
+            Ok(regs) => regs.rip as usize,
+            Err(_) => return String::new(),
+        };
+        match self.session.debug_data().get_line_from_addr(rip) {
+            Some(line) => format!(" at {:#x}, file {}, line {}", rip, line.file, line.number),
+            None => format!(" at {:#x}", rip),
+        }
+    }
+
+    fn print_checkpoints(&self) {
+        if self.checkpoints.is_empty() {
+            println!("No checkpoints.");
+            return;
+        }
+        if let Some(inf) = self.session.inferior() {
+            println!(
+                "* 0 process {} (main process){}",
+                inf.pid(),
+                self.describe_process(inf)
+            );
+        }
+        for checkpoint in &self.checkpoints {
+            println!(
+                "  {} process {}{}",
+                checkpoint.number,
+                checkpoint.inferior.pid(),
+                self.describe_process(&checkpoint.inferior)
+            );
+        }
+    }
+
+    /// Replaces the inferior with a fresh copy of a checkpoint. The checkpoint itself stays as it
+    /// is, so that it can be restarted again.
+    fn restart(&mut self, number: usize) {
+        let checkpoint = match self.checkpoints.iter().find(|c| c.number == number) {
+            Some(checkpoint) => checkpoint,
+            None => {
+                println!("Invalid checkpoint number {}", number);
+                return;
+            }
+        };
+        let copy = match checkpoint.inferior.fork() {
+            Ok(copy) => copy,
+            Err(err) => {
+                println!("Error restarting checkpoint {}: {}", number, err);
+                return;
+            }
+        };
+        println!("Switching to process {}", copy.pid());
+        if let Err(err) = self.session.switch_to(copy) {
+            println!("Error inserting breakpoints: {}", err);
+        }
+        self.command_queue.clear();
+        self.select_frame(0);
+    }
+
+    fn delete_checkpoints(&mut self) {
+        for checkpoint in &mut self.checkpoints {
+            checkpoint.inferior.kill();
+        }
+        self.checkpoints.clear();
+        self.next_checkpoint_number = 1;
+    }
+
+    /// Starts or stops recording the instructions the inferior runs.
+    fn record(&mut self, start: bool) {
+        // Recording single-steps the process, which makes no system call stops
+        let catching_syscalls = self.session.wants_syscall_stops();
+        let inf = match self.session.inferior_mut() {
+            Some(inf) => inf,
+            None => {
+                println!("The program is not being run.");
+                return;
+            }
+        };
+        match (start, inf.recorded_instructions().is_some()) {
+            (true, true) => println!("The process is already being recorded."),
+            (true, false) if catching_syscalls => {
+                println!("Cannot record while syscall catchpoints are set.")
+            }
+            (true, false) => inf.start_recording(),
+            (false, true) => {
+                inf.stop_recording();
+                println!("Process record is stopped and all execution logs are deleted.");
+            }
+            (false, false) => println!("No recording is currently active."),
+        }
+    }
+
+    fn print_record_info(&self) {
+        match self
+            .session
+            .inferior()
+            .and_then(|inf| inf.recorded_instructions())
+        {
+            Some(count) => {
+                println!("Active record target: record-full");
+                println!("Log contains {} instructions.", count);
+            }
+            None => println!("No recording is currently active."),
+        }
+    }
+
+    /// Returns the inferior if it is being recorded, so that it can be run backwards.
+    fn recorded_inferior(&mut self) -> Option<&mut Inferior> {
+        match self.session.inferior_mut() {
+            Some(inf) if inf.recorded_instructions().is_some() => Some(inf),
+            Some(_) => {
+                println!("Target native does not support this command.");
+                None
+            }
+            None => {
+                println!("The program is not being run.");
+                None
+            }
+        }
+    }
+
+    /// Undoes one instruction. Returns false, after saying so, if there is nothing to undo.
+    fn undo_instruction(&mut self) -> bool {
+        match self.session.inferior_mut().unwrap().reverse_stepi() {
+            Ok(Some(_)) => true,
+            Ok(None) => {
+                println!("\nNo more reverse-execution history.");
+                false
+            }
+            Err(err) => {
+                println!("Error running backwards: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Shows where the inferior ended up after running backwards.
+    fn report_reverse_stop(&mut self) {
+        self.command_queue.clear();
+        self.select_frame(0);
+        self.do_displays();
+    }
+
+    fn reverse_stepi(&mut self) {
+        if self.recorded_inferior().is_none() {
+            return;
+        }
+        self.undo_instruction();
+        self.report_reverse_stop();
+    }
+
+    /// Runs backwards until a breakpoint whose condition holds is reached, or the start of the
+    /// recording.
+    fn reverse_continue(&mut self) {
+        if self.recorded_inferior().is_none() {
+            return;
+        }
+        while self.undo_instruction() {
+            let rip = match self.session.inferior().unwrap().registers() {
+                Ok(regs) => regs.rip as usize,
+                Err(err) => {
+                    println!("Error reading registers: {}", err);
+                    return;
+                }
+            };
+            let hit = self.session.breakpoints_hit(rip, false);
+            self.print_notices();
+            if !hit.is_empty() {
+                println!("Breakpoint {}", hit[0]);
+                break;
+            }
+        }
+        self.report_reverse_stop();
+    }
+
+    /// Returns the source line and canonical frame address of the innermost frame, or None if
+    /// the inferior is in code without debugging information.
+    fn position(&self) -> Option<(String, usize, usize)> {
+        let frames = self.frames_quietly();
+        let frame = frames.first()?;
+        let line = frame.line.as_ref()?;
+        Some((line.file.clone(), line.number, frame.cfa))
+    }
+
+    fn function_address(&self, addr: usize) -> Option<usize> {
+        self.session
+            .debug_data()
+            .get_function_containing(addr)
+            .map(|func| func.address)
+    }
+
+    fn frames_quietly(&self) -> Vec<Frame> {
+        match self.session.inferior() {
+            Some(inf) => inf.backtrace(self.session.debug_data()).unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Runs backwards to the start of the previous source line. `reverse-step` stops in
+    /// functions called on that line (at the start of their last line); `reverse-next` runs
+    /// backwards through them.
+    fn reverse_step(&mut self, over: bool) {
+        if self.recorded_inferior().is_none() {
+            return;
+        }
+        let (file, number, cfa) = match self.position() {
+            Some(position) => position,
+            None => {
+                println!("Cannot find bounds of current function");
+                return;
+            }
+        };
+        // Go back until some other line has run
+        let mut target = None;
+        while self.undo_instruction() {
+            match self.position() {
+                Some((_, _, other_cfa)) if over && other_cfa < cfa => continue,
+                Some(position) if position != (file.clone(), number, cfa) => {
+                    target = Some(position);
+                    break;
+                }
+                _ => continue,
+            }
+        }
+        if let Some(target) = target {
+            if let Err(err) = self.reverse_to_line_start(target, over) {
+                println!("Error reading registers: {}", err);
+                return;
+            }
+        }
+        self.report_reverse_stop();
+    }
+
+    /// Runs backwards to the start of the line at `target`: undoes instructions as long as the one
+    /// before is on that line, or in a function called from it. Without `over`, it stops in the
+    /// last function called on the line instead, at the start of its last line.
+    fn reverse_to_line_start(
+        &mut self,
+        target: (String, usize, usize),
+        over: bool,
+    ) -> Result<(), nix::Error> {
+        let (mut file, mut number, mut cfa) = target;
+        let start_pc = self.session.inferior().unwrap().registers()?.rip as usize;
+        let mut func = self.function_address(start_pc);
+        while let Some((prev_pc, prev_sp)) = self.session.inferior().unwrap().previous_instruction()
+        {
+            let prev_func = self.function_address(prev_pc);
+            let prev_line = self
+                .session
+                .debug_data()
+                .get_line_from_addr(prev_pc)
+                .map(|line| (line.file, line.number));
+            let same_line = prev_func == func && prev_line == Some((file.clone(), number));
+            // Callers run with %rsp at or above the frame's CFA
+            let deeper = prev_func != func && prev_sp < cfa - 8;
+            if !same_line && !deeper {
+                break;
+            }
+            if deeper {
+                // Returning into the target frame: the call belongs to the line of the call
+                // instruction, which ends right before the return address
+                let pc = self.session.inferior().unwrap().registers()?.rip as usize;
+                let call_line = self
+                    .session
+                    .debug_data()
+                    .get_line_from_addr(pc - 1)
+                    .map(|line| (line.file, line.number));
+                if self.function_address(pc) == func && call_line != Some((file.clone(), number)) {
+                    break;
+                }
+            }
+            if !self.undo_instruction() {
+                break;
+            }
+            if deeper && !over && prev_func.is_some() {
+                // Into a function called on the target line, at its last line
+                if let Some(position) = self.position() {
+                    file = position.0;
+                    number = position.1;
+                    cfa = position.2;
+                    func = prev_func;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs until the current function reaches `location` or returns, whichever comes first.
+    /// `until` keeps going when `location` is reached by a deeper recursive call; `advance` stops
+    /// there too.
+    fn run_to_location(&mut self, location: &str, stop_in_deeper_frames: bool) {
+        let stop = if stop_in_deeper_frames {
+            self.session.advance(location)
+        } else {
+            self.session.until(location)
+        };
+        match stop {
+            Err(Error::UnknownLocation(_)) => println!("No location \"{}\" found", location),
+            Err(Error::NotRunning) => println!("No child process now"),
+            stop => {
+                self.report_stop(stop);
+                self.run_queued_commands();
+            }
+        }
+    }
+
+    fn set_condition(&mut self, number: usize, condition: Option<String>) {
+        let unconditional = condition.is_none();
+        match self.session.set_condition(number, condition) {
+            Ok(()) if unconditional => println!("Breakpoint {} now unconditional.", number),
+            Ok(()) => {}
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    /// Shows memory starting at the address `expression` evaluates to, or where the last `x`
+    /// stopped.
+    fn examine(&mut self, format: Option<ExamineFormat>, expression: Option<&str>) {
+        if let Some(format) = format {
+            self.examine_format = format;
+        }
+        let format = self.examine_format.clone();
+        let mut addr = match expression {
+            Some(expression) => {
+                match self
+                    .evaluate(expression)
+                    .and_then(|value| Ok(expression::to_address(&value)?))
+                {
+                    Ok(addr) => addr,
+                    Err(err) => {
+                        println!("{}", err);
+                        return;
                     }
-                    match addr {
-                        Some(addr) => {
-                            self.insert_bp(addr);
-                        }
-                        None => {
-                            println!("Breakpoint on Invalid address");
-                        }
-                    }   
                 }
             }
+            None => match self.next_examine {
+                Some(addr) => addr,
+                None => {
+                    println!("Argument required (starting display address).");
+                    return;
+                }
+            },
+        };
+        let inf = match self.session.inferior() {
+            Some(inf) => inf,
+            None => {
+                println!("Cannot access memory at address {:#x}", addr);
+                return;
+            }
+        };
+        if format.format == 's' {
+            for _ in 0..format.count {
+                match format::read_c_string(inf, addr, 200) {
+                    Ok(string) => {
+                        println!("{:#x}:\t{}", addr, format::quote_c_string(&string));
+                        addr += string.len() + 1;
+                    }
+                    Err(_) => {
+                        println!("Cannot access memory at address {:#x}", addr);
+                        return;
+                    }
+                }
+            }
+            self.next_examine = Some(addr);
+            return;
+        }
+        let unit_size = match (format.format, format.unit_size) {
+            ('a', _) => 8,
+            ('c', _) => 1,
+            (_, Some(unit_size)) => unit_size,
+            ('f', None) => 8,
+            (_, None) => 4,
+        };
+        let per_line = match unit_size {
+            1 | 2 => 8,
+            4 => 4,
+            _ => 2,
+        };
+        let bytes = match inf.read_memory(addr, format.count * unit_size) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                println!("Cannot access memory at address {:#x}", addr);
+                return;
+            }
+        };
+        for line in bytes.chunks(unit_size * per_line) {
+            let units: Vec<String> = line
+                .chunks(unit_size)
+                .map(|unit| Debugger::format_unit(unit, format.format))
+                .collect();
+            println!("{:#x}:\t{}", addr, units.join("\t"));
+            addr += line.len();
+        }
+        self.next_examine = Some(addr);
+    }
+
+    /// Formats one unit of memory shown by `x`.
+    fn format_unit(unit: &[u8], format: char) -> String {
+        let mut raw = [0u8; 8];
+        raw[..unit.len()].copy_from_slice(unit);
+        let unsigned = u64::from_le_bytes(raw);
+        match format {
+            'd' => format::format_value(unit, Encoding::Signed),
+            'u' => format!("{}", unsigned),
+            'o' if unsigned == 0 => "0".to_string(),
+            'o' => format!("0{:o}", unsigned),
+            't' => format!("{:0width$b}", unsigned, width = unit.len() * 8),
+            'c' => format::format_value(unit, Encoding::SignedChar),
+            'a' => format!("{:#x}", unsigned),
+            'f' if unit.len() == 4 || unit.len() == 8 => {
+                format::format_value(unit, Encoding::Float)
+            }
+            _ => format!("0x{:0width$x}", unsigned, width = unit.len() * 2),
+        }
+    }
+
+    /// Attaches a command list to breakpoint `number`, or to the most recently set breakpoint.
+    fn set_commands(&mut self, number: Option<usize>, commands: Vec<String>) {
+        let breakpoints = self.session.breakpoints();
+        let bp = match number {
+            Some(number) => breakpoints.iter().find(|bp| bp.number == number),
+            None => breakpoints.iter().max_by_key(|bp| bp.number),
+        };
+        match bp.map(|bp| bp.number) {
+            Some(number) => {
+                self.breakpoint_commands.insert(number, commands);
+            }
+            None => match number {
+                Some(number) => println!("No breakpoint number {}.", number),
+                None => println!("No breakpoints specified."),
+            },
+        }
+    }
+
+    /// Reads the lines of a `commands` list up to `end`. Lines that are not valid commands are
+    /// rejected right away rather than when the breakpoint is hit.
+    fn read_command_list(&mut self) -> Vec<String> {
+        println!("Type commands for when the breakpoint is hit, one per line.");
+        println!("End with a line saying just \"end\".");
+        let mut commands = Vec::new();
+        loop {
+            let line = match self.readline.readline(">") {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => return Vec::new(),
+                Err(_) => return commands,
+            };
+            if line.trim() == "end" {
+                return commands;
+            }
+            Debugger::add_to_command_list(&mut commands, &line);
+        }
+    }
+
+    /// Adds a line to a breakpoint's command list, if it is a command that can go there.
+    fn add_to_command_list(commands: &mut Vec<String>, line: &str) {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            return;
+        }
+        match DebuggerCommand::from_tokens(&tokens) {
+            Some(DebuggerCommand::Commands { .. }) => {
+                println!("Breakpoint command lists cannot be nested.")
+            }
+            Some(_) => commands.push(tokens.join(" ")),
+            None if tokens[0] == "silent" && commands.is_empty() => {
+                commands.push(tokens[0].to_string())
+            }
+            None => println!("Unrecognized command."),
+        }
+    }
+
+    /// Shows the source, locals, registers and backtrace in panes above the command line.
+    pub fn set_tui(&mut self, tui: Tui) {
+        self.tui = Some(tui);
+    }
+
+    pub fn run(&mut self) {
+        self.source_init_files();
+        while !self.quitting {
+            self.refresh_tui();
+            let cmd = self.get_next_command();
+            self.execute(cmd);
+        }
+    }
+
+    fn execute(&mut self, cmd: DebuggerCommand) {
+        self.execute_command(cmd);
+        // A function called while evaluating an expression may have ended the process
+        self.session.forget_exited();
+    }
+
+    fn execute_command(&mut self, cmd: DebuggerCommand) {
+        match cmd {
+            DebuggerCommand::Run(args) => {
+                if self.start_inferior(args) {
+                    self.contin();
+                }
+            }
+            DebuggerCommand::Start(args) => {
+                if self.parse_location("main").is_none() {
+                    println!("No symbol \"main\" in the program");
+                    return;
+                }
+                if self.start_inferior(args) {
+                    let stop = self.session.run_to_main();
+                    self.report_stop(stop);
+                    self.run_queued_commands();
+                }
+            }
+            DebuggerCommand::Until(location) => self.run_to_location(&location, false),
+            DebuggerCommand::Advance(location) => self.run_to_location(&location, true),
+            DebuggerCommand::Contin => self.contin(),
+            DebuggerCommand::Step(over) => {
+                let stop = if over {
+                    self.session.step_over()
+                } else {
+                    self.session.step()
+                };
+                self.report_step(stop);
+            }
+            DebuggerCommand::Stepi => {
+                let stop = self.session.stepi();
+                self.report_step(stop);
+            }
+            DebuggerCommand::Backtrace { full, limit } => self.print_backtrace(full, limit),
+            DebuggerCommand::Frame(index) => {
+                self.select_frame(index.unwrap_or(self.selected_frame))
+            }
+            DebuggerCommand::Up(count) => self.select_frame(self.selected_frame + count),
+            DebuggerCommand::Down(count) => {
+                if count > self.selected_frame {
+                    println!("Bottom (innermost) frame selected; you cannot go down.");
+                } else {
+                    self.select_frame(self.selected_frame - count);
+                }
+            }
+            DebuggerCommand::InfoFrame => self.print_frame_info(),
+            DebuggerCommand::InfoFunctions(pattern) => self.info_functions(pattern),
+            DebuggerCommand::InfoVariables(pattern) => self.info_variables(pattern),
+            DebuggerCommand::InfoTypes(pattern) => self.info_types(pattern),
+            DebuggerCommand::InfoLine(location) => self.info_line(location),
+            DebuggerCommand::InfoAddress(symbol) => self.info_address(&symbol),
+            DebuggerCommand::InfoSource => self.info_source(),
+            DebuggerCommand::Condition(number, condition) => self.set_condition(number, condition),
+            DebuggerCommand::Examine { format, expression } => {
+                self.examine(format, expression.as_deref())
+            }
+            DebuggerCommand::Display(expression) => match expression {
+                Some(expression) => self.add_display(expression),
+                None => self.do_displays(),
+            },
+            DebuggerCommand::Undisplay(number) => self.undisplay(number),
+            DebuggerCommand::InfoDisplay => self.print_display_info(),
+            DebuggerCommand::Print(expression) => self.print_expression(&expression),
+            DebuggerCommand::Call(expression) => self.call_expression(&expression),
+            DebuggerCommand::List(line) => self.list_source(line),
+            DebuggerCommand::Finish => self.finish(),
+            DebuggerCommand::Return(value) => self.force_return(value),
+            DebuggerCommand::Checkpoint => self.checkpoint(),
+            DebuggerCommand::InfoCheckpoints => self.print_checkpoints(),
+            DebuggerCommand::Restart(number) => self.restart(number),
+            DebuggerCommand::Record(start) => self.record(start),
+            DebuggerCommand::InfoRecord => self.print_record_info(),
+            DebuggerCommand::ReverseStepi => self.reverse_stepi(),
+            DebuggerCommand::ReverseStep(over) => self.reverse_step(over),
+            DebuggerCommand::ReverseContinue => self.reverse_continue(),
+            DebuggerCommand::Catch(catch) => self.catch(catch),
+            DebuggerCommand::SetEnvironment(name, value) => self.set_environment(name, value),
+            DebuggerCommand::UnsetEnvironment(name) => self.unset_environment(name),
+            DebuggerCommand::ShowEnvironment(name) => self.show_environment(name),
+            DebuggerCommand::SetCwd(directory) => self.set_cwd(directory),
+            DebuggerCommand::ShowCwd => match &self.launch.cwd {
+                Some(cwd) => println!(
+                    "Current working directory that will be used when starting the inferior is \"{}\".",
+                    cwd
+                ),
+                None => println!(
+                    "You have not set the inferior's current working directory.\n\
+                     The inferior will inherit deet's cwd."
+                ),
+            },
+            DebuggerCommand::SetArgs(args) => self.args = args,
+            DebuggerCommand::ShowArgs => println!(
+                "Argument list to give program being debugged when it is started is \"{}\".",
+                self.args.join(" ")
+            ),
+            DebuggerCommand::Tty(tty) => self.launch.tty = tty,
+            DebuggerCommand::Source(path) => self.source(&path),
+            DebuggerCommand::SaveBreakpoints(path) => self.save_breakpoints(&path),
+            DebuggerCommand::PrettyPrinter(Some(definition)) => {
+                if let Err(err) = self.session.add_pretty_printer(&definition) {
+                    println!("{}", err);
+                }
+            }
+            DebuggerCommand::PrettyPrinter(None) => {
+                let definitions = self.session.pretty_printers();
+                if definitions.is_empty() {
+                    println!("No pretty-printers.");
+                }
+                for definition in definitions {
+                    println!("{}", definition);
+                }
+            }
+            DebuggerCommand::Help(name) => debugger_command::print_help(name.as_deref()),
+            DebuggerCommand::Quit => {
+                self.session.kill();
+                self.delete_checkpoints();
+                self.quitting = true;
+            }
+            DebuggerCommand::Commands { number, commands } => self.set_commands(number, commands),
+            DebuggerCommand::Breakpoint {
+                location,
+                temporary,
+                condition,
+            } => match self.session.set_breakpoint(&location, temporary, condition) {
+                Ok(bp) if bp.temporary => {
+                    println!("Temporary breakpoint {} at 0x{:x}", bp.number, bp.addr)
+                }
+                Ok(bp) => println!("Breakpoint {} at 0x{:x}", bp.number, bp.addr),
+                Err(Error::UnknownLocation(_))
+                    if location.starts_with('*') || location.parse::<usize>().is_ok() =>
+                {
+                    println!("Breakpoint on Invalid address");
+                }
+                Err(err) => println!("{}", err),
+            },
+        }
+    }
+
+    /// Lets tab completion offer the variables of the selected frame.
+    fn update_completion(&mut self) {
+        let frames = self.frames_quietly();
+        let locals: Vec<String> = if self.selected_frame < frames.len() {
+            match self.frame_function(&frames, self.selected_frame) {
+                Some(function) => function
+                    .variables
+                    .iter()
+                    .map(|var| var.name.clone())
+                    .collect(),
+                None => Vec::new(),
+            }
+        } else {
+            Vec::new()
+        };
+        if let Some(helper) = self.readline.helper_mut() {
+            helper.locals = locals;
         }
     }
 
     /// This function prompts the user to enter a command, and continues re-prompting until the user
     /// enters a valid command. It uses DebuggerCommand::from_tokens to do the command parsing.
     ///
-    /// You don't need to read, understand, or modify this function.
+    /// Tab completion is first pointed at the selected frame's variables. Each line entered is
+    /// saved to the history file. `commands` goes on to read the breakpoint's command list from
+    /// the lines up to `end`. ctrl+c prompts again and ctrl+d quits.
     fn get_next_command(&mut self) -> DebuggerCommand {
+        self.update_completion();
         loop {
             // Print prompt and get next line of user input
             match self.readline.readline("(deet) ") {
@@ -195,6 +2110,11 @@ impl Debugger {
                     }
                     let tokens: Vec<&str> = line.split_whitespace().collect();
                     if let Some(cmd) = DebuggerCommand::from_tokens(&tokens) {
+                        // `commands` switches to reading the breakpoint's command list
+                        if let DebuggerCommand::Commands { number, .. } = cmd {
+                            let commands = self.read_command_list();
+                            return DebuggerCommand::Commands { number, commands };
+                        }
                         return cmd;
                     } else {
                         println!("Unrecognized command.");
@@ -204,3 +2124,16 @@ impl Debugger {
         }
     }
 }
+
+/// Expands a leading `~` in a path to the home directory.
+fn expand_home(path: &str) -> String {
+    if path == "~" || path.starts_with("~/") {
+        format!(
+            "{}{}",
+            std::env::var("HOME").unwrap_or_default(),
+            &path[1..]
+        )
+    } else {
+        path.to_string()
+    }
+}
@@ -1,33 +1,993 @@
+use crate::inferior::Redirection;
+
 pub enum DebuggerCommand {
     Quit,
     Run(Vec<String>),
     Contin,
-    Backtrace,
-    Breakpoint(String),
+    Backtrace {
+        full: bool,
+        limit: Option<isize>,
+    },
+    Breakpoint {
+        location: String,
+        temporary: bool,
+        condition: Option<String>,
+    },
+    /// `condition N [expr]`
+    Condition(usize, Option<String>),
+    Start(Vec<String>),
+    Until(String),
+    Advance(String),
+    /// `commands [N]`; the list itself is read by the debugger after the command is parsed.
+    Commands {
+        number: Option<usize>,
+        commands: Vec<String>,
+    },
+    Frame(Option<usize>),
+    Up(usize),
+    Down(usize),
+    InfoFrame,
+    /// `info functions [regex]`, and likewise for variables and types
+    InfoFunctions(Option<String>),
+    InfoVariables(Option<String>),
+    InfoTypes(Option<String>),
+    /// `info line [location]`; no location means where the selected frame is
+    InfoLine(Option<String>),
+    InfoAddress(String),
+    InfoSource,
+    Display(Option<String>),
+    Undisplay(usize),
+    InfoDisplay,
+    Print(String),
+    /// `call expr`: like `print`, but prints nothing for void functions
+    Call(String),
+    Examine {
+        /// None if no `/FMT` was given, so that the last format is used again
+        format: Option<ExamineFormat>,
+        expression: Option<String>,
+    },
+    List(Option<usize>),
+    Finish,
+    Return(Option<String>),
+    Checkpoint,
+    InfoCheckpoints,
+    Restart(usize),
+    /// `record`, or `record stop` if false
+    Record(bool),
+    InfoRecord,
+    /// `step`, or `next` if true
+    Step(bool),
+    Stepi,
+    ReverseStepi,
+    /// `reverse-step`, or `reverse-next` if true
+    ReverseStep(bool),
+    ReverseContinue,
+    Catch(Catch),
+    /// `set env NAME=VALUE`
+    SetEnvironment(String, String),
+    /// `unset env [NAME]`; no name unsets every variable
+    UnsetEnvironment(Option<String>),
+    ShowEnvironment(Option<String>),
+    /// `set cwd [DIR]`; no directory goes back to starting the program in deet's
+    SetCwd(Option<String>),
+    ShowCwd,
+    SetArgs(Vec<String>),
+    ShowArgs,
+    /// `tty [TERMINAL]`; no terminal goes back to sharing deet's
+    Tty(Option<String>),
+    Source(String),
+    SaveBreakpoints(String),
+    /// `pretty-printer [TYPE = LAYOUT ARGS...]`; no definition lists the registered ones
+    PrettyPrinter(Option<String>),
+    Help(Option<String>),
 }
 
-impl DebuggerCommand {
-    pub fn from_tokens(tokens: &Vec<&str>) -> Option<DebuggerCommand> {
-        match tokens[0] {
-            "q" | "quit" => Some(DebuggerCommand::Quit),
-            "r" | "run" => {
-                let args = tokens[1..].to_vec();
-                Some(DebuggerCommand::Run(
-                    args.iter().map(|s| s.to_string()).collect(),
+/// What a `catch` command asks to stop at.
+pub enum Catch {
+    /// `catch syscall [name|number]...`; no names means any system call
+    Syscall(Vec<String>),
+    Fork,
+    Vfork,
+    Exec,
+    /// `catch signal [signal]...`; no signals means all but those deet uses itself
+    Signal(Vec<String>),
+    /// Stops a process that is about to exit, while it can still be examined
+    Exit,
+}
+
+/// The `/NFU` suffix of `x`: how many units to show, how to format them and how big they are.
+#[derive(Clone)]
+pub struct ExamineFormat {
+    pub count: usize,
+    pub format: char,
+    pub unit_size: Option<usize>,
+}
+
+impl Default for ExamineFormat {
+    fn default() -> Self {
+        ExamineFormat {
+            count: 1,
+            format: 'x',
+            unit_size: None,
+        }
+    }
+}
+
+impl ExamineFormat {
+    /// Parses the part of `x/4xw` after the slash.
+    fn parse(spec: &str) -> Option<ExamineFormat> {
+        let digits = spec.chars().take_while(|c| c.is_ascii_digit()).count();
+        let count = if digits == 0 {
+            1
+        } else {
+            spec[..digits].parse::<usize>().ok()?
+        };
+        let mut format = 'x';
+        let mut unit_size = None;
+        for letter in spec[digits..].chars() {
+            match letter {
+                'x' | 'd' | 'u' | 'o' | 't' | 'a' | 'c' | 'f' | 's' => format = letter,
+                'b' => unit_size = Some(1),
+                'h' => unit_size = Some(2),
+                'w' => unit_size = Some(4),
+                'g' => unit_size = Some(8),
+                _ => {
+                    println!("Invalid format letter '{}'.", letter);
+                    return None;
+                }
+            }
+        }
+        Some(ExamineFormat {
+            count,
+            format,
+            unit_size,
+        })
+    }
+}
+
+/// Parses the optional numeric argument of commands such as `up` and `frame`.
+fn parse_count(tokens: &Vec<&str>) -> Option<Option<usize>> {
+    match tokens.get(1) {
+        Some(count) => match count.parse::<usize>() {
+            Ok(count) => Some(Some(count)),
+            Err(_) => {
+                println!("Invalid number \"{}\"", count);
+                None
+            }
+        },
+        None => Some(None),
+    }
+}
+
+/// Splits the arguments of `run` into words the way the shell does: quotes and backslashes
+/// keep spaces and operators in a word. Returns each word and whether it starts with a quote or
+/// backslash, in which case it can't be a redirection. The arguments have already been split at
+/// whitespace, so a run of spaces in quotes becomes one.
+fn split_words(tokens: &[String]) -> Result<Vec<(String, bool)>, String> {
+    let line = tokens.join(" ");
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.peek().map_or(false, |c| c.is_whitespace()) {
+            chars.next();
+        }
+        let quoted = match chars.peek() {
+            Some(c) => *c == '\'' || *c == '"' || *c == '\\',
+            None => return Ok(words),
+        };
+        let mut word = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                c if c.is_whitespace() => break,
+                '\'' | '"' => loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some('\\') if c == '"' => word.extend(chars.next()),
+                        Some(other) => word.push(other),
+                        None => return Err(format!("Unterminated {} in arguments", c)),
+                    }
+                },
+                '\\' => word.extend(chars.next()),
+                c => word.push(c),
+            }
+        }
+        words.push((word, quoted));
+    }
+}
+
+/// Takes shell-style redirections (`< in`, `> out`, `>> out`, `2> err`, `2>&1`, `&> out`) out of
+/// the arguments of `run`. Returns the remaining arguments, with quotes removed, and where
+/// stdin, stdout and stderr should go.
+pub fn parse_redirections(
+    tokens: &[String],
+) -> Result<(Vec<String>, [Option<Redirection>; 3]), String> {
+    // Longer operators first, so that `>>` isn't taken for `>`
+    let operators = ["2>>", "2>", "&>", ">>", ">", "<"];
+    let operator_of = |word: &str, quoted: bool| {
+        if quoted {
+            None
+        } else {
+            operators.iter().find(|op| word.starts_with(*op)).cloned()
+        }
+    };
+    let mut args = Vec::new();
+    let mut redirections: [Option<Redirection>; 3] = Default::default();
+    let mut words = split_words(tokens)?.into_iter();
+    while let Some((word, quoted)) = words.next() {
+        if word == "2>&1" && !quoted {
+            redirections[2] = Some(Redirection::Stdout);
+            continue;
+        }
+        let operator = match operator_of(&word, quoted) {
+            Some(operator) => operator,
+            None => {
+                args.push(word);
+                continue;
+            }
+        };
+        let path = if word.len() > operator.len() {
+            word[operator.len()..].to_string()
+        } else {
+            match words.next() {
+                Some((path, quoted)) if operator_of(&path, quoted).is_none() => path,
+                _ => return Err(format!("Missing file name after {}", operator)),
+            }
+        };
+        match operator {
+            "<" => redirections[0] = Some(Redirection::Read(path)),
+            ">" => redirections[1] = Some(Redirection::Write(path)),
+            ">>" => redirections[1] = Some(Redirection::Append(path)),
+            "2>" => redirections[2] = Some(Redirection::Write(path)),
+            "2>>" => redirections[2] = Some(Redirection::Append(path)),
+            _ => {
+                redirections[1] = Some(Redirection::Write(path));
+                redirections[2] = Some(Redirection::Stdout);
+            }
+        }
+    }
+    Ok((args, redirections))
+}
+
+/// What the arguments of a command are, for tab completion.
+pub enum Arguments {
+    None,
+    /// A breakpoint location: a function, `file:line` or `file:function`
+    Location,
+    /// An expression, made of the variables in scope and functions
+    Expression,
+    /// A path on the file system
+    File,
+    /// Arguments for the program, where `< in`, `> out` and the like redirect to paths
+    Program,
+    /// One of these words, followed by what it takes
+    Subcommands(&'static [(&'static str, Arguments)]),
+    /// The name of another command
+    Command,
+}
+
+/// A command deet understands. `from_tokens` and `help` both work from the list of these.
+pub struct CommandInfo {
+    /// The name of the command followed by its abbreviations
+    pub names: &'static [&'static str],
+    pub usage: &'static str,
+    /// What the command does. The first sentence is the summary shown by `help` on its own.
+    pub help: &'static str,
+    pub arguments: Arguments,
+    parse: fn(&Vec<&str>) -> Option<DebuggerCommand>,
+}
+
+pub static COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        names: &["run", "r"],
+        usage: "run [ARGS...] [< IN] [> OUT] [2> ERR] [2>&1]",
+        help: "Start the program, with breakpoints in place. Arguments given replace the ones it \
+               was last run with. Its input and output can be redirected as in the shell.",
+        arguments: Arguments::Program,
+        parse: |tokens| {
+            let args = tokens[1..].to_vec();
+            Some(DebuggerCommand::Run(
+                args.iter().map(|s| s.to_string()).collect(),
+            ))
+        },
+    },
+    CommandInfo {
+        names: &["start"],
+        usage: "start [ARGS...]",
+        help: "Start the program and stop at the beginning of main. Takes the same arguments as \
+               run.",
+        arguments: Arguments::Program,
+        parse: |tokens| {
+            let args = tokens[1..].to_vec();
+            Some(DebuggerCommand::Start(
+                args.iter().map(|s| s.to_string()).collect(),
+            ))
+        },
+    },
+    CommandInfo {
+        names: &["continue", "c"],
+        usage: "continue",
+        help: "Continue running the stopped program.",
+        arguments: Arguments::None,
+        parse: |_| Some(DebuggerCommand::Contin),
+    },
+    CommandInfo {
+        names: &["step", "s"],
+        usage: "step",
+        help: "Run to the next source line, stepping into functions called on the way that \
+               have debugging information.",
+        arguments: Arguments::None,
+        parse: |_| Some(DebuggerCommand::Step(false)),
+    },
+    CommandInfo {
+        names: &["next", "n"],
+        usage: "next",
+        help: "Run to the next source line in this function, through the functions it calls.",
+        arguments: Arguments::None,
+        parse: |_| Some(DebuggerCommand::Step(true)),
+    },
+    CommandInfo {
+        names: &["stepi", "si"],
+        usage: "stepi",
+        help: "Run a single machine instruction.",
+        arguments: Arguments::None,
+        parse: |_| Some(DebuggerCommand::Stepi),
+    },
+    CommandInfo {
+        names: &["quit", "q"],
+        usage: "quit",
+        help: "Kill the program, if it is running, and exit deet.",
+        arguments: Arguments::None,
+        parse: |_| Some(DebuggerCommand::Quit),
+    },
+    CommandInfo {
+        names: &["backtrace", "bt", "back"],
+        usage: "backtrace [full] [N|-N]",
+        help: "Print the stack, innermost frame first. With full, the local variables of each \
+               frame are printed too. N shows only the innermost N frames and -N the outermost.",
+        arguments: Arguments::Subcommands(&[("full", Arguments::None)]),
+        parse: parse_backtrace,
+    },
+    CommandInfo {
+        names: &["break", "b"],
+        usage: "break <location> [if <condition>]",
+        help: "Set a breakpoint at a function, a line, file:line, file:function or *address. \
+               With a condition, the program only stops when it is true.",
+        arguments: Arguments::Location,
+        parse: parse_break,
+    },
+    CommandInfo {
+        names: &["tbreak"],
+        usage: "tbreak <location> [if <condition>]",
+        help: "Set a breakpoint that is deleted the first time it is hit. Locations are given as \
+               for break.",
+        arguments: Arguments::Location,
+        parse: parse_break,
+    },
+    CommandInfo {
+        names: &["condition"],
+        usage: "condition N [expression]",
+        help: "Make breakpoint N stop only when the expression is true, or always if none is \
+               given.",
+        arguments: Arguments::Expression,
+        parse: |tokens| match tokens.get(1).map(|number| number.parse::<usize>()) {
+            Some(Ok(number)) => {
+                let condition = tokens[2..].join(" ");
+                Some(DebuggerCommand::Condition(
+                    number,
+                    if condition.is_empty() {
+                        None
+                    } else {
+                        Some(condition)
+                    },
                 ))
             }
-            "c" | "continue" => Some(DebuggerCommand::Contin),
-            "bt" | "backtrace" | "back" => Some(DebuggerCommand::Backtrace),
-            "b" | "break" => {
-                if tokens.len() < 2 {
-                    println!("No breakpoint specified");
+            _ => {
+                println!("Usage: condition N [expression]");
+                None
+            }
+        },
+    },
+    CommandInfo {
+        names: &["commands"],
+        usage: "commands [N]",
+        help: "Give the commands to run when breakpoint N, or the last one set, is hit, one per \
+               line, ending with \"end\". A list starting with silent does not print where the \
+               program stopped.",
+        arguments: Arguments::None,
+        parse: |tokens| {
+            Some(DebuggerCommand::Commands {
+                number: parse_count(tokens)?,
+                commands: Vec::new(),
+            })
+        },
+    },
+    CommandInfo {
+        names: &["until", "u"],
+        usage: "until <location>",
+        help: "Continue until the location is reached or the current frame returns.",
+        arguments: Arguments::Location,
+        parse: parse_until,
+    },
+    CommandInfo {
+        names: &["advance"],
+        usage: "advance <location>",
+        help: "Continue until the location is reached, even in a frame further in or out.",
+        arguments: Arguments::Location,
+        parse: parse_until,
+    },
+    CommandInfo {
+        names: &["finish", "fin"],
+        usage: "finish",
+        help: "Continue until the selected frame returns, and print the value it returns.",
+        arguments: Arguments::None,
+        parse: |_| Some(DebuggerCommand::Finish),
+    },
+    CommandInfo {
+        names: &["return"],
+        usage: "return [expression]",
+        help: "Make the selected frame return to its caller at once, with the value given.",
+        arguments: Arguments::Expression,
+        parse: |tokens| {
+            let value = tokens[1..].join(" ");
+            Some(DebuggerCommand::Return(if value.is_empty() {
+                None
+            } else {
+                Some(value)
+            }))
+        },
+    },
+    CommandInfo {
+        names: &["frame", "f"],
+        usage: "frame [N]",
+        help: "Select frame N, or describe the selected frame if N is not given.",
+        arguments: Arguments::None,
+        parse: |tokens| Some(DebuggerCommand::Frame(parse_count(tokens)?)),
+    },
+    CommandInfo {
+        names: &["up"],
+        usage: "up [N]",
+        help: "Select the frame N (or 1) levels further out, towards main.",
+        arguments: Arguments::None,
+        parse: |tokens| Some(DebuggerCommand::Up(parse_count(tokens)?.unwrap_or(1))),
+    },
+    CommandInfo {
+        names: &["down"],
+        usage: "down [N]",
+        help: "Select the frame N (or 1) levels further in.",
+        arguments: Arguments::None,
+        parse: |tokens| Some(DebuggerCommand::Down(parse_count(tokens)?.unwrap_or(1))),
+    },
+    CommandInfo {
+        names: &["info", "i"],
+        usage: "info frame|display|checkpoints|record|functions [regex]|variables [regex]|\
+                types [regex]|line [location]|address <symbol>|source",
+        help: "Describe the selected frame, the expressions displayed at each stop, the \
+               checkpoints or the execution record. info functions, variables and types list \
+               the program's symbols whose names match the regular expression. info line tells \
+               where the code of a line is, info address where a symbol is stored, and info \
+               source which source file is being looked at.",
+        arguments: Arguments::Subcommands(&[
+            ("frame", Arguments::None),
+            ("display", Arguments::None),
+            ("checkpoints", Arguments::None),
+            ("record", Arguments::None),
+            ("functions", Arguments::None),
+            ("variables", Arguments::None),
+            ("types", Arguments::None),
+            ("line", Arguments::Location),
+            ("address", Arguments::Expression),
+            ("source", Arguments::None),
+        ]),
+        parse: parse_info,
+    },
+    CommandInfo {
+        names: &["print", "p"],
+        usage: "print <expression>",
+        help: "Print the value of an expression in the selected frame.",
+        arguments: Arguments::Expression,
+        parse: |tokens| {
+            if tokens.len() < 2 {
+                println!("No expression specified");
+                None
+            } else {
+                Some(DebuggerCommand::Print(tokens[1..].join(" ")))
+            }
+        },
+    },
+    CommandInfo {
+        names: &["call"],
+        usage: "call <expression>",
+        help: "Evaluate an expression, calling functions in the program, and print its value \
+               unless it is void.",
+        arguments: Arguments::Expression,
+        parse: |tokens| {
+            if tokens.len() < 2 {
+                println!("The history is empty.");
+                None
+            } else {
+                Some(DebuggerCommand::Call(tokens[1..].join(" ")))
+            }
+        },
+    },
+    CommandInfo {
+        names: &["x"],
+        usage: "x[/NFU] [address]",
+        help: "Examine memory: N units of size U (b, h, w or g) in format F (x, d, u, o, t, a, c, \
+               f or s). Without an address, x continues where the last one stopped.",
+        arguments: Arguments::Expression,
+        parse: |tokens| {
+            let format = if tokens[0].len() > 2 {
+                Some(ExamineFormat::parse(&tokens[0][2..])?)
+            } else {
+                None
+            };
+            let expression = tokens[1..].join(" ");
+            Some(DebuggerCommand::Examine {
+                format,
+                expression: if expression.is_empty() {
                     None
                 } else {
-                    Some(DebuggerCommand::Breakpoint(tokens[1].to_string()))
+                    Some(expression)
+                },
+            })
+        },
+    },
+    CommandInfo {
+        names: &["display"],
+        usage: "display [expression]",
+        help: "Print the expression each time the program stops. Without one, print all the \
+               displayed expressions now.",
+        arguments: Arguments::Expression,
+        parse: |tokens| {
+            let expression = tokens[1..].join(" ");
+            Some(DebuggerCommand::Display(if expression.is_empty() {
+                None
+            } else {
+                Some(expression)
+            }))
+        },
+    },
+    CommandInfo {
+        names: &["undisplay"],
+        usage: "undisplay N",
+        help: "Stop displaying expression N.",
+        arguments: Arguments::None,
+        parse: |tokens| match parse_count(tokens)? {
+            Some(number) => Some(DebuggerCommand::Undisplay(number)),
+            None => {
+                println!("Usage: undisplay N");
+                None
+            }
+        },
+    },
+    CommandInfo {
+        names: &["list", "l"],
+        usage: "list [LINE]",
+        help: "List source lines around LINE, or after the ones last listed.",
+        arguments: Arguments::None,
+        parse: |tokens| Some(DebuggerCommand::List(parse_count(tokens)?)),
+    },
+    CommandInfo {
+        names: &["checkpoint"],
+        usage: "checkpoint",
+        help: "Save a copy of the stopped program that restart can go back to.",
+        arguments: Arguments::None,
+        parse: |_| Some(DebuggerCommand::Checkpoint),
+    },
+    CommandInfo {
+        names: &["restart"],
+        usage: "restart N",
+        help: "Go back to checkpoint N.",
+        arguments: Arguments::None,
+        parse: |tokens| match parse_count(tokens)? {
+            Some(number) => Some(DebuggerCommand::Restart(number)),
+            None => {
+                println!("Usage: restart N");
+                None
+            }
+        },
+    },
+    CommandInfo {
+        names: &["record", "rec"],
+        usage: "record [full|stop]",
+        help: "Start recording the program's execution so that it can be run backwards, or stop \
+               recording.",
+        arguments: Arguments::Subcommands(&[("full", Arguments::None), ("stop", Arguments::None)]),
+        parse: |tokens| match tokens.get(1) {
+            None | Some(&"full") => Some(DebuggerCommand::Record(true)),
+            Some(&"stop") => Some(DebuggerCommand::Record(false)),
+            Some(_) => {
+                println!("Usage: record [full|stop]");
+                None
+            }
+        },
+    },
+    CommandInfo {
+        names: &["reverse-stepi", "rsi"],
+        usage: "reverse-stepi",
+        help: "Run backwards by one instruction.",
+        arguments: Arguments::None,
+        parse: |_| Some(DebuggerCommand::ReverseStepi),
+    },
+    CommandInfo {
+        names: &["reverse-step", "rs"],
+        usage: "reverse-step",
+        help: "Run backwards to the previous source line, into functions called on it.",
+        arguments: Arguments::None,
+        parse: |_| Some(DebuggerCommand::ReverseStep(false)),
+    },
+    CommandInfo {
+        names: &["reverse-next", "rn"],
+        usage: "reverse-next",
+        help: "Run backwards to the previous source line, through functions called on it.",
+        arguments: Arguments::None,
+        parse: |_| Some(DebuggerCommand::ReverseStep(true)),
+    },
+    CommandInfo {
+        names: &["reverse-continue", "rc"],
+        usage: "reverse-continue",
+        help: "Run backwards until a breakpoint or the start of the record.",
+        arguments: Arguments::None,
+        parse: |_| Some(DebuggerCommand::ReverseContinue),
+    },
+    CommandInfo {
+        names: &["catch"],
+        usage: "catch syscall [name|number]...|signal [signal]...|fork|vfork|exec|exit",
+        help: "Stop when the program makes one of the system calls, receives one of the \
+               signals, forks, calls exec or is about to exit.",
+        arguments: Arguments::Subcommands(&[
+            ("syscall", Arguments::None),
+            ("signal", Arguments::None),
+            ("fork", Arguments::None),
+            ("vfork", Arguments::None),
+            ("exec", Arguments::None),
+            ("exit", Arguments::None),
+        ]),
+        parse: |tokens| match tokens.get(1) {
+            Some(&"syscall") => Some(DebuggerCommand::Catch(Catch::Syscall(
+                tokens[2..].iter().map(|s| s.to_string()).collect(),
+            ))),
+            Some(&"signal") => Some(DebuggerCommand::Catch(Catch::Signal(
+                tokens[2..].iter().map(|s| s.to_string()).collect(),
+            ))),
+            Some(&"fork") if tokens.len() == 2 => Some(DebuggerCommand::Catch(Catch::Fork)),
+            Some(&"vfork") if tokens.len() == 2 => Some(DebuggerCommand::Catch(Catch::Vfork)),
+            Some(&"exec") if tokens.len() == 2 => Some(DebuggerCommand::Catch(Catch::Exec)),
+            Some(&"exit") if tokens.len() == 2 => Some(DebuggerCommand::Catch(Catch::Exit)),
+            _ => {
+                println!(
+                    "Usage: catch syscall [name|number]...|signal [signal]...|fork|vfork|exec|exit"
+                );
+                None
+            }
+        },
+    },
+    CommandInfo {
+        names: &["set"],
+        usage: "set env NAME=VALUE|cwd [DIR]|args [ARGS]",
+        help: "Set an environment variable, the working directory or the arguments the program \
+               is started with.",
+        arguments: Arguments::Subcommands(&[
+            ("env", Arguments::None),
+            ("cwd", Arguments::File),
+            ("args", Arguments::Program),
+        ]),
+        parse: parse_set,
+    },
+    CommandInfo {
+        names: &["unset"],
+        usage: "unset env [NAME]",
+        help: "Remove a variable, or all of them, from the program's environment.",
+        arguments: Arguments::Subcommands(&[("env", Arguments::None)]),
+        parse: |tokens| match tokens.get(1) {
+            Some(&"env") | Some(&"environment") => Some(DebuggerCommand::UnsetEnvironment(
+                tokens.get(2).map(|name| name.to_string()),
+            )),
+            _ => {
+                println!("Usage: unset env [NAME]");
+                None
+            }
+        },
+    },
+    CommandInfo {
+        names: &["show"],
+        usage: "show env [NAME]|cwd|args",
+        help: "Show the environment, working directory or arguments the program is started with.",
+        arguments: Arguments::Subcommands(&[
+            ("env", Arguments::None),
+            ("cwd", Arguments::None),
+            ("args", Arguments::None),
+        ]),
+        parse: |tokens| match tokens.get(1) {
+            Some(&"env") | Some(&"environment") => Some(DebuggerCommand::ShowEnvironment(
+                tokens.get(2).map(|name| name.to_string()),
+            )),
+            Some(&"cwd") => Some(DebuggerCommand::ShowCwd),
+            Some(&"args") => Some(DebuggerCommand::ShowArgs),
+            _ => {
+                println!("Usage: show env [NAME]|cwd|args");
+                None
+            }
+        },
+    },
+    CommandInfo {
+        names: &["tty"],
+        usage: "tty [TERMINAL]",
+        help: "Give the program its own terminal, such as /dev/pts/3, the next time it is run.",
+        arguments: Arguments::File,
+        parse: |tokens| {
+            Some(DebuggerCommand::Tty(
+                tokens.get(1).map(|tty| tty.to_string()),
+            ))
+        },
+    },
+    CommandInfo {
+        names: &["source"],
+        usage: "source <file>",
+        help: "Run the commands in a file, such as one written by save breakpoints.",
+        arguments: Arguments::File,
+        parse: |tokens| {
+            if tokens.len() > 1 {
+                Some(DebuggerCommand::Source(tokens[1..].join(" ")))
+            } else {
+                println!("source command requires file name of file to source.");
+                None
+            }
+        },
+    },
+    CommandInfo {
+        names: &["save"],
+        usage: "save breakpoints <file>",
+        help: "Write commands that set the current breakpoints and catchpoints again to a file.",
+        arguments: Arguments::Subcommands(&[("breakpoints", Arguments::File)]),
+        parse: |tokens| match tokens.get(1) {
+            Some(&"breakpoints") if tokens.len() > 2 => {
+                Some(DebuggerCommand::SaveBreakpoints(tokens[2..].join(" ")))
+            }
+            _ => {
+                println!("Usage: save breakpoints <file>");
+                None
+            }
+        },
+    },
+    CommandInfo {
+        names: &["pretty-printer"],
+        usage: "pretty-printer [TYPE|/REGEX/ = LAYOUT ARGS...]",
+        help: "Show values of a type, or of the types whose names match a regular expression, \
+               in a more readable form, or list the pretty-printers registered so far. The \
+               layouts are format TEMPLATE, where each {member} is replaced by its value; array \
+               DATA LENGTH; list HEAD NEXT [VALUE], for linked lists; ring BUFFER START COUNT \
+               CAPACITY, for circular buffers; and hash BUCKETS SIZE NEXT KEY [VALUE], for \
+               hash tables that chain their entries. Members are names separated by dots, which \
+               look through pointers, or . for the value itself; counts can also be numbers.",
+        arguments: Arguments::None,
+        parse: |tokens| {
+            let definition = tokens[1..].join(" ");
+            Some(DebuggerCommand::PrettyPrinter(if definition.is_empty() {
+                None
+            } else {
+                Some(definition)
+            }))
+        },
+    },
+    CommandInfo {
+        names: &["help", "h"],
+        usage: "help [command]",
+        help: "List the commands, or describe one of them.",
+        arguments: Arguments::Command,
+        parse: |tokens| Some(DebuggerCommand::Help(tokens.get(1).map(|s| s.to_string()))),
+    },
+];
+
+/// Finds a command by its name or one of its abbreviations.
+pub fn lookup(name: &str) -> Option<&'static CommandInfo> {
+    // `x` takes its format after a slash
+    let name = if name.starts_with("x/") { "x" } else { name };
+    COMMANDS
+        .iter()
+        .find(|command| command.names.contains(&name))
+}
+
+/// Prints the commands with a summary of each, or the full description of one.
+pub fn print_help(name: Option<&str>) {
+    match name {
+        None => {
+            println!("List of commands:\n");
+            for command in COMMANDS {
+                let summary = match command.help.find(". ") {
+                    Some(end) => &command.help[..=end],
+                    None => command.help,
+                };
+                println!("{:<16} -- {}", command.names[0], summary);
+            }
+            println!("\nType \"help\" followed by a command name for full documentation.");
+        }
+        Some(name) => match lookup(name) {
+            Some(command) => {
+                println!("Usage: {}", command.usage);
+                println!("{}", command.help);
+                if command.names.len() > 1 {
+                    println!("Aliases: {}", command.names[1..].join(", "));
                 }
             }
-            // Default case:
-            _ => None,
+            None => println!("Undefined command: \"{}\".  Try \"help\".", name),
+        },
+    }
+}
+
+fn parse_backtrace(tokens: &Vec<&str>) -> Option<DebuggerCommand> {
+    let mut full = false;
+    let mut limit = None;
+    for token in &tokens[1..] {
+        match (*token, token.parse::<isize>()) {
+            ("full", _) | ("-full", _) => full = true,
+            (_, Ok(count)) => limit = Some(count),
+            _ => {
+                println!("Usage: backtrace [full] [N|-N]");
+                return None;
+            }
+        }
+    }
+    Some(DebuggerCommand::Backtrace { full, limit })
+}
+
+fn parse_break(tokens: &Vec<&str>) -> Option<DebuggerCommand> {
+    if tokens.len() < 2 {
+        println!("No breakpoint specified");
+        None
+    } else {
+        // break <location> [if <condition>]
+        let condition = match tokens.get(2) {
+            Some(&"if") if tokens.len() > 3 => Some(tokens[3..].join(" ")),
+            Some(_) => {
+                println!("Usage: {} <location> [if <condition>]", tokens[0]);
+                return None;
+            }
+            None => None,
+        };
+        Some(DebuggerCommand::Breakpoint {
+            location: tokens[1].to_string(),
+            temporary: tokens[0] == "tbreak",
+            condition,
+        })
+    }
+}
+
+fn parse_info(tokens: &Vec<&str>) -> Option<DebuggerCommand> {
+    let pattern = if tokens.len() > 2 {
+        Some(tokens[2..].join(" "))
+    } else {
+        None
+    };
+    match tokens.get(1) {
+        Some(&"frame") | Some(&"f") => Some(DebuggerCommand::InfoFrame),
+        Some(&"display") => Some(DebuggerCommand::InfoDisplay),
+        Some(&"checkpoints") => Some(DebuggerCommand::InfoCheckpoints),
+        Some(&"record") => Some(DebuggerCommand::InfoRecord),
+        Some(&"functions") => Some(DebuggerCommand::InfoFunctions(pattern)),
+        Some(&"variables") => Some(DebuggerCommand::InfoVariables(pattern)),
+        Some(&"types") => Some(DebuggerCommand::InfoTypes(pattern)),
+        Some(&"line") => Some(DebuggerCommand::InfoLine(
+            tokens.get(2).map(|location| location.to_string()),
+        )),
+        Some(&"address") => match tokens.get(2) {
+            Some(symbol) => Some(DebuggerCommand::InfoAddress(symbol.to_string())),
+            None => {
+                println!("Argument required.");
+                None
+            }
+        },
+        Some(&"source") => Some(DebuggerCommand::InfoSource),
+        _ => {
+            println!(
+                "Usage: info frame|display|checkpoints|record|functions [regex]|\
+                 variables [regex]|types [regex]|line [location]|address <symbol>|source"
+            );
+            None
+        }
+    }
+}
+
+fn parse_until(tokens: &Vec<&str>) -> Option<DebuggerCommand> {
+    if tokens.len() < 2 {
+        println!("No location specified");
+        None
+    } else if tokens[0] == "advance" {
+        Some(DebuggerCommand::Advance(tokens[1].to_string()))
+    } else {
+        Some(DebuggerCommand::Until(tokens[1].to_string()))
+    }
+}
+
+fn parse_set(tokens: &Vec<&str>) -> Option<DebuggerCommand> {
+    match (tokens.get(1), tokens.get(2)) {
+        (Some(&"env"), Some(_)) | (Some(&"environment"), Some(_)) => {
+            // Both `set env NAME=VALUE` and `set env NAME VALUE` work
+            let setting = tokens[2..].join(" ");
+            let (name, value) = match setting.find('=') {
+                Some(equals) => (&setting[..equals], &setting[equals + 1..]),
+                None => match setting.find(' ') {
+                    Some(space) => (&setting[..space], &setting[space + 1..]),
+                    None => (&setting[..], ""),
+                },
+            };
+            Some(DebuggerCommand::SetEnvironment(
+                name.trim().to_string(),
+                value.trim().to_string(),
+            ))
+        }
+        (Some(&"cwd"), directory) => Some(DebuggerCommand::SetCwd(
+            directory.map(|dir| dir.to_string()),
+        )),
+        (Some(&"args"), _) => Some(DebuggerCommand::SetArgs(
+            tokens[2..].iter().map(|s| s.to_string()).collect(),
+        )),
+        _ => {
+            println!("Usage: set env NAME=VALUE|cwd [DIR]|args [ARGS]");
+            None
         }
     }
 }
+
+impl DebuggerCommand {
+    pub fn from_tokens(tokens: &Vec<&str>) -> Option<DebuggerCommand> {
+        (lookup(tokens[0])?.parse)(tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the arguments of `run` and shows each redirection as the shell would write it.
+    fn redirections(line: &str) -> Result<(Vec<String>, Vec<String>), String> {
+        let tokens: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+        let (args, redirections) = parse_redirections(&tokens)?;
+        let shown = redirections
+            .iter()
+            .zip(&["<", ">", "2>"])
+            .filter_map(|(redirection, operator)| match redirection.as_ref()? {
+                Redirection::Read(path) | Redirection::Write(path) => {
+                    Some(format!("{} {}", operator, path))
+                }
+                Redirection::Append(path) => Some(format!("{}> {}", operator, path)),
+                Redirection::Stdout => Some("2>&1".to_string()),
+            })
+            .collect();
+        Ok((args, shown))
+    }
+
+    fn check(line: &str, args: &[&str], shown: &[&str]) {
+        let (parsed_args, parsed) = redirections(line).unwrap();
+        assert_eq!(parsed_args, args, "{}", line);
+        assert_eq!(parsed, shown, "{}", line);
+    }
+
+    #[test]
+    fn operators() {
+        check("a b", &["a", "b"], &[]);
+        check("a < in > out", &["a"], &["< in", "> out"]);
+        check("<in >>out b", &["b"], &["< in", ">> out"]);
+        check("2> err a 2>>log", &["a"], &["2>> log"]);
+        check("> out 2>&1", &[], &["> out", "2>&1"]);
+        check("&> all", &[], &["> all", "2>&1"]);
+        // The last redirection of a stream wins
+        check("> first > second", &[], &["> second"]);
+    }
+
+    #[test]
+    fn quoting() {
+        check("> \"my out\"", &[], &["> my out"]);
+        check(">'a b' 'c d'", &["c d"], &["> a b"]);
+        check("'>' \\<x \"2>&1\"", &[">", "<x", "2>&1"], &[]);
+        check("\"say \\\"hi\\\"\" it\\'s", &["say \"hi\"", "it's"], &[]);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |line| redirections(line).unwrap_err();
+        assert_eq!(error("a >"), "Missing file name after >");
+        assert_eq!(error("2>> <"), "Missing file name after 2>>");
+        assert_eq!(error("> 'out"), "Unterminated ' in arguments");
+    }
+}
@@ -1,7 +1,12 @@
-use crate::gimli_wrapper;
+use crate::inferior::Frame;
+use crate::regex::Regex;
+use crate::{debug_file, gimli_wrapper};
 use addr2line::Context;
 use object::Object;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
+use std::path::{Path, PathBuf};
 use std::{fmt, fs};
 
 #[derive(Debug)]
@@ -13,6 +18,71 @@ pub enum Error {
 pub struct DwarfData {
     files: Vec<File>,
     addr2line: Context<addr2line::gimli::EndianRcSlice<addr2line::gimli::RunTimeEndian>>,
+    debug_file: Option<PathBuf>,
+    /// Every type in the debugging information, keyed by .debug_info offset
+    types: HashMap<usize, Type>,
+    symbols: SymbolIndex,
+}
+
+/// Lookup tables over the functions, global variables and types, built once when the debugging
+/// information is loaded. Functions and variables are referred to by their file's index in
+/// `files` and their index in that file's list.
+#[derive(Default)]
+struct SymbolIndex {
+    /// Functions that have code in this program, by name. Declarations of functions defined
+    /// elsewhere, such as printf's, are left out.
+    functions: BTreeMap<String, Vec<(usize, usize)>>,
+    /// The address ranges of those functions, sorted by start address
+    function_ranges: Vec<(usize, usize, (usize, usize))>,
+    global_variables: BTreeMap<String, Vec<(usize, usize)>>,
+    /// .debug_info offsets of the types with each name
+    types: BTreeMap<String, Vec<usize>>,
+    /// Every line table row as (address, file, index in that file's `lines`), sorted by address.
+    /// Rows with the same address keep their order in the line table.
+    line_rows: Vec<(usize, usize, usize)>,
+}
+
+impl SymbolIndex {
+    fn new(files: &[File], types: &HashMap<usize, Type>) -> SymbolIndex {
+        let mut index = SymbolIndex::default();
+        for (file_index, file) in files.iter().enumerate() {
+            for (func_index, func) in file.functions.iter().enumerate() {
+                if func.text_length == 0 {
+                    continue;
+                }
+                let symbol = (file_index, func_index);
+                index
+                    .functions
+                    .entry(func.name.clone())
+                    .or_default()
+                    .push(symbol);
+                let end = func.address + func.text_length;
+                index.function_ranges.push((func.address, end, symbol));
+            }
+            for (var_index, var) in file.global_variables.iter().enumerate() {
+                index
+                    .global_variables
+                    .entry(var.name.clone())
+                    .or_default()
+                    .push((file_index, var_index));
+            }
+            for (line_index, line) in file.lines.iter().enumerate() {
+                index.line_rows.push((line.address, file_index, line_index));
+            }
+        }
+        index.function_ranges.sort_by_key(|(start, _, _)| *start);
+        index.line_rows.sort_by_key(|(address, _, _)| *address);
+        let mut offsets: Vec<&usize> = types.keys().collect();
+        // Offsets in .debug_info order, so that the first type with a name is the one seen first
+        offsets.sort();
+        for offset in offsets {
+            let name = &types[offset].name;
+            if !name.is_empty() {
+                index.types.entry(name.clone()).or_default().push(*offset);
+            }
+        }
+        index
+    }
 }
 
 impl fmt::Debug for DwarfData {
@@ -28,22 +98,65 @@ impl From<gimli_wrapper::Error> for Error {
 }
 
 impl DwarfData {
-    pub fn from_file(path: &str) -> Result<DwarfData, Error> {
+    /// Loads the debugging information for the executable at `path`. If the executable was
+    /// stripped and its DWARF sections live in a separate debug file (found through its build ID
+    /// or `.gnu_debuglink` in `debug_file_directories`), the sections are read from there.
+    pub fn from_file_with_debug_dirs(
+        path: &str,
+        debug_file_directories: &[String],
+    ) -> Result<DwarfData, Error> {
         let file = fs::File::open(path).or(Err(Error::ErrorOpeningFile))?;
         let mmap = unsafe { memmap::Mmap::map(&file).or(Err(Error::ErrorOpeningFile))? };
         let object = object::File::parse(&*mmap)
             .or_else(|e| Err(gimli_wrapper::Error::ObjectError(e.to_string())))?;
-        let endian = if object.is_little_endian() {
-            gimli::RunTimeEndian::Little
+
+        let debug_file = debug_file::find_debug_file(path, &object, debug_file_directories);
+        let debug_mmap = match &debug_file {
+            Some(debug_path) => {
+                let file = fs::File::open(debug_path).or(Err(Error::ErrorOpeningFile))?;
+                Some(unsafe { memmap::Mmap::map(&file).or(Err(Error::ErrorOpeningFile))? })
+            }
+            None => None,
+        };
+        let debug_object = match &debug_mmap {
+            Some(mmap) => Some(
+                object::File::parse(&**mmap)
+                    .or_else(|e| Err(gimli_wrapper::Error::ObjectError(e.to_string())))?,
+            ),
+            None => None,
+        };
+        let mut objects = vec![&object];
+        if let Some(debug_object) = &debug_object {
+            objects.push(debug_object);
+        }
+
+        let (endian, addr2line_endian) = if object.is_little_endian() {
+            (
+                gimli::RunTimeEndian::Little,
+                addr2line::gimli::RunTimeEndian::Little,
+            )
         } else {
-            gimli::RunTimeEndian::Big
+            (
+                gimli::RunTimeEndian::Big,
+                addr2line::gimli::RunTimeEndian::Big,
+            )
         };
+        let (files, types) = gimli_wrapper::load_file(&objects, endian)?;
+        let symbols = SymbolIndex::new(&files, &types);
         Ok(DwarfData {
-            files: gimli_wrapper::load_file(&object, endian)?,
-            addr2line: Context::new(&object).or_else(|e| Err(gimli_wrapper::Error::from(e)))?,
+            files,
+            addr2line: gimli_wrapper::load_addr2line_context(&objects, addr2line_endian)?,
+            debug_file,
+            types,
+            symbols,
         })
     }
 
+    /// Returns the path of the separate debug file the symbols were read from, if any.
+    pub fn debug_file(&self) -> Option<&Path> {
+        self.debug_file.as_deref()
+    }
+
     #[allow(dead_code)]
     fn get_target_file(&self, file: &str) -> Option<&File> {
         self.files.iter().find(|f| {
@@ -51,38 +164,67 @@ impl DwarfData {
         })
     }
 
-    #[allow(dead_code)]
+    /// Returns the address where the code of a line starts, or of the next line that has code.
+    /// The rows of a line need not come first in address order: rustc puts the cleanup code of
+    /// later lines among them.
     pub fn get_addr_for_line(&self, file: Option<&str>, line_number: usize) -> Option<usize> {
         let target_file = match file {
             Some(filename) => self.get_target_file(filename)?,
             None => self.files.get(0)?,
         };
-        Some(
-            target_file
-                .lines
-                .iter()
-                .find(|line| line.number >= line_number)?
-                .address,
-        )
+        let number = target_file
+            .lines
+            .iter()
+            .map(|line| line.number)
+            .filter(|number| *number >= line_number)
+            .min()?;
+        target_file
+            .lines
+            .iter()
+            .filter(|line| line.number == number)
+            .map(|line| line.address)
+            .min()
     }
 
-    #[allow(dead_code)]
+    /// Returns the address of a function that has code in this program, in the given file if
+    /// there is one by that name in more than one.
     pub fn get_addr_for_function(&self, file: Option<&str>, func_name: &str) -> Option<usize> {
         match file {
             Some(filename) => Some(
                 self.get_target_file(filename)?
                     .functions
                     .iter()
-                    .find(|func| func.name == func_name)?
+                    .find(|func| {
+                        (func.name == func_name || is_qualified_name(&func.name, func_name))
+                            && func.text_length > 0
+                    })?
                     .address,
             ),
-            None => {
-                for file in &self.files {
-                    if let Some(func) = file.functions.iter().find(|func| func.name == func_name) {
-                        return Some(func.address);
-                    }
-                }
-                None
+            None => Some(self.get_function(func_name)?.address),
+        }
+    }
+
+    /// Resolves a location given as `*address`, a line number or a function name, optionally
+    /// preceded by the source file (`file:line`, `file:function`). Functions are entered past
+    /// their prologue.
+    pub fn get_addr_for_location(&self, location: &str) -> Option<usize> {
+        if location.starts_with('*') {
+            return parse_address(&location[1..]);
+        }
+        // A lone colon separates the file; `::` belongs to a C++ or Rust function name
+        let bytes = location.as_bytes();
+        let colon = (0..bytes.len()).find(|&i| {
+            bytes[i] == b':' && bytes.get(i + 1) != Some(&b':') && (i == 0 || bytes[i - 1] != b':')
+        });
+        let (file, location) = match colon {
+            Some(colon) => (Some(&location[..colon]), &location[colon + 1..]),
+            None => (None, location),
+        };
+        match location.parse::<usize>() {
+            Ok(linenum) => self.get_addr_for_line(file, linenum),
+            Err(_) => {
+                let addr = self.get_addr_for_function(file, location)?;
+                Some(self.get_addr_after_prologue(addr))
             }
         }
     }
@@ -108,43 +250,240 @@ impl DwarfData {
             .ok()?
             .next()
             .ok()??;
-        Some(frame.function?.raw_name().ok()?.to_string())
+        function_name(&frame.function?)
     }
 
-    #[allow(dead_code)]
-    pub fn print(&self) {
-        for file in &self.files {
-            println!("------");
-            println!("{}", file.name);
-            println!("------");
-
-            println!("Global variables:");
-            for var in &file.global_variables {
-                println!(
-                    "  * {} ({}, located at {}, declared at line {})",
-                    var.name, var.entity_type.name, var.location, var.line_number
-                );
+    /// Returns every function activation at `curr_addr`, innermost first. The first entry is the
+    /// function the address belongs to; if that function was inlined, the following entries are
+    /// the functions it was inlined into, located at the inlined call site.
+    pub fn get_frames_from_addr(&self, curr_addr: usize) -> Vec<InlineFrame> {
+        let mut frames = Vec::new();
+        let mut iter = match self.addr2line.find_frames(curr_addr.try_into().unwrap()) {
+            Ok(iter) => iter,
+            Err(_) => return frames,
+        };
+        while let Ok(Some(frame)) = iter.next() {
+            let function = frame.function.as_ref().and_then(function_name);
+            let line = frame.location.and_then(|location| {
+                Some(Line {
+                    file: location.file?.to_string(),
+                    number: location.line?.try_into().unwrap(),
+                    address: curr_addr,
+                })
+            });
+            frames.push(InlineFrame { function, line });
+        }
+        frames
+    }
+
+    /// Returns the address of the first line of a function's body, past the prologue that sets up
+    /// its frame and stores its arguments on the stack. This is where breakpoints on a function
+    /// go, so that its arguments can be read when they are hit.
+    pub fn get_addr_after_prologue(&self, func_addr: usize) -> usize {
+        let func = match self.get_function_containing(func_addr) {
+            Some(func) => func,
+            None => return func_addr,
+        };
+        let rows = &self.symbols.line_rows;
+        match rows.get(self.rows_up_to(func_addr)) {
+            Some((addr, _, _)) if *addr < func.address + func.text_length => *addr,
+            _ => func_addr,
+        }
+    }
+
+    /// Returns the number of line table rows at or below `addr`, which is the index of the first
+    /// row above it.
+    fn rows_up_to(&self, addr: usize) -> usize {
+        let rows = &self.symbols.line_rows;
+        match rows.binary_search_by(|(row_addr, _, _)| {
+            if *row_addr <= addr {
+                Ordering::Less
+            } else {
+                Ordering::Greater
             }
+        }) {
+            Ok(index) | Err(index) => index,
+        }
+    }
 
-            println!("Functions:");
-            for func in &file.functions {
-                println!(
-                    "  * {} (declared on line {}, located at {:#x}, {} bytes long)",
-                    func.name, func.line_number, func.address, func.text_length
-                );
-                for var in &func.variables {
-                    println!(
-                        "    * Variable: {} ({}, located at {}, declared at line {})",
-                        var.name, var.entity_type.name, var.location, var.line_number
-                    );
-                }
+    /// Returns the function whose code contains `curr_addr`.
+    pub fn get_function_containing(&self, curr_addr: usize) -> Option<&Function> {
+        let ranges = &self.symbols.function_ranges;
+        let index = match ranges.binary_search_by_key(&curr_addr, |(start, _, _)| *start) {
+            Ok(index) => index,
+            Err(0) => return None,
+            Err(next) => next - 1,
+        };
+        let (_, end, symbol) = ranges[index];
+        if curr_addr < end {
+            Some(self.function_at(symbol))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the function whose variables are visible in frame `index`. Inlined frames have no
+    /// variables of their own.
+    pub fn get_frame_function(&self, frames: &[Frame], index: usize) -> Option<&Function> {
+        let frame = &frames[index];
+        // Return addresses of callers may point past the function's last instruction
+        let lookup_addr = if frame.cfa == frames[0].cfa {
+            frame.pc
+        } else {
+            frame.pc - 1
+        };
+        self.get_function_containing(lookup_addr)
+            .filter(|func| !frame.inlined && frame.function.as_deref() == Some(&func.name))
+    }
+
+    /// Returns the function with the given name that has code in this program, passing over
+    /// declarations of functions defined elsewhere. A Rust or C++ function may be named without
+    /// its module or namespace, e.g. "main" for the `main` of a Rust program.
+    pub fn get_function(&self, name: &str) -> Option<&Function> {
+        let symbols = match self.symbols.functions.get(name) {
+            Some(symbols) => symbols,
+            None => {
+                self.symbols
+                    .functions
+                    .iter()
+                    .find(|(qualified, _)| is_qualified_name(qualified, name))?
+                    .1
             }
+        };
+        Some(self.function_at(*symbols.first()?))
+    }
 
-            println!("Line numbers:");
-            for line in &file.lines {
-                println!("  * {} (at {:#x})", line.number, line.address);
+    fn function_at(&self, (file, index): (usize, usize)) -> &Function {
+        &self.files[file].functions[index]
+    }
+
+    /// Returns the type at the given .debug_info offset, such as a struct member's.
+    pub fn get_type(&self, offset: usize) -> Option<&Type> {
+        self.types.get(&offset)
+    }
+
+    /// Returns the type with the given name, e.g. "int", "size_t" or "struct point". Structs,
+    /// unions and enums that are only declared somewhere are passed over for their definition.
+    pub fn get_type_by_name(&self, name: &str) -> Option<&Type> {
+        let mut found = None;
+        let offsets = self.symbols.types.get(name)?;
+        for entity_type in offsets.iter().map(|offset| &self.types[offset]) {
+            match entity_type.kind {
+                TypeKind::Struct(ref members) if members.is_empty() => found = Some(entity_type),
+                _ => return Some(entity_type),
             }
         }
+        found
+    }
+
+    /// Returns the global variable with the given name.
+    pub fn get_global_variable(&self, name: &str) -> Option<&Variable> {
+        let (file, index) = *self.symbols.global_variables.get(name)?.first()?;
+        Some(&self.files[file].global_variables[index])
+    }
+
+    /// Returns the names of the functions that have code in this program, in order.
+    pub fn function_names(&self) -> Vec<String> {
+        self.symbols.functions.keys().cloned().collect()
+    }
+
+    /// Returns the names of the source files, without their directories.
+    pub fn file_names(&self) -> Vec<String> {
+        self.files
+            .iter()
+            .map(|file| match file.name.rfind('/') {
+                Some(slash) => file.name[slash + 1..].to_string(),
+                None => file.name.clone(),
+            })
+            .collect()
+    }
+
+    /// Returns the names of the global variables, in order.
+    pub fn global_variable_names(&self) -> Vec<String> {
+        self.symbols.global_variables.keys().cloned().collect()
+    }
+
+    /// Returns the functions whose names `pattern` matches, grouped by the file they are in and
+    /// in order of name.
+    pub fn functions_matching(&self, pattern: &Regex) -> Vec<(&str, Vec<&Function>)> {
+        let symbols = self
+            .symbols
+            .functions
+            .iter()
+            .filter(|(name, _)| pattern.is_match(name))
+            .flat_map(|(_, symbols)| symbols.iter().cloned());
+        self.group_by_file(symbols, |file, index| &self.files[file].functions[index])
+    }
+
+    /// Returns the global variables whose names `pattern` matches, grouped by the file they are
+    /// in and in order of name.
+    pub fn global_variables_matching(&self, pattern: &Regex) -> Vec<(&str, Vec<&Variable>)> {
+        let symbols = self
+            .symbols
+            .global_variables
+            .iter()
+            .filter(|(name, _)| pattern.is_match(name))
+            .flat_map(|(_, symbols)| symbols.iter().cloned());
+        self.group_by_file(symbols, |file, index| {
+            &self.files[file].global_variables[index]
+        })
+    }
+
+    fn group_by_file<'a, T, I, F>(&'a self, symbols: I, get: F) -> Vec<(&'a str, Vec<&'a T>)>
+    where
+        I: Iterator<Item = (usize, usize)>,
+        F: Fn(usize, usize) -> &'a T,
+    {
+        let mut by_file: BTreeMap<usize, Vec<&T>> = BTreeMap::new();
+        for (file, index) in symbols {
+            by_file.entry(file).or_default().push(get(file, index));
+        }
+        by_file
+            .into_iter()
+            .map(|(file, found)| (self.files[file].name.as_str(), found))
+            .collect()
+    }
+
+    /// Returns the names of the types that `pattern` matches, in order, either as a whole or, for
+    /// `struct node`, by the tag `node` alone. Pointer and array types are left out, as they are
+    /// named after the types they are made from.
+    pub fn type_names_matching(&self, pattern: &Regex) -> Vec<&str> {
+        self.symbols
+            .types
+            .iter()
+            .filter(|(name, offsets)| {
+                let kind = &self.types[&offsets[0]].kind;
+                let derived = matches!(kind, TypeKind::Pointer(_) | TypeKind::Array(_));
+                let tag = ["struct ", "union ", "enum "]
+                    .iter()
+                    .find(|keyword| name.starts_with(*keyword))
+                    .map_or(name.as_str(), |keyword| &name[keyword.len()..]);
+                !derived && (pattern.is_match(name) || pattern.is_match(tag))
+            })
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Returns the line the instruction at `addr` was generated for, with the address where the
+    /// code of that line starts and the address where it ends.
+    pub fn get_line_range(&self, addr: usize) -> Option<(Line, usize)> {
+        let rows = &self.symbols.line_rows;
+        // The last row at or below `addr` is the one whose code it is in
+        let index = self.rows_up_to(addr).checked_sub(1)?;
+        let line_of =
+            |(_, file, line): (usize, usize, usize)| (file, self.files[file].lines[line].number);
+        let this_line = line_of(rows[index]);
+        // The line starts at the first of the consecutive rows for it
+        let mut first = index;
+        while first > 0 && line_of(rows[first - 1]) == this_line {
+            first -= 1;
+        }
+        let end = rows[index..]
+            .iter()
+            .find(|row| line_of(**row) != this_line)?
+            .0;
+        let (_, file, line) = rows[first];
+        Some((self.files[file].lines[line].clone(), end))
     }
 }
 
@@ -152,6 +491,10 @@ impl DwarfData {
 pub struct Type {
     pub name: String,
     pub size: usize,
+    pub kind: TypeKind,
+    /// The template type parameters of a C++ or Rust generic type, e.g. ("T", offset of i32)
+    /// for a `Vec<i32>`, with their types' .debug_info offsets
+    pub type_parameters: Vec<(String, usize)>,
 }
 
 impl Type {
@@ -159,8 +502,74 @@ impl Type {
         Type {
             name: name,
             size: size,
+            kind: TypeKind::Unknown,
+            type_parameters: Vec::new(),
         }
     }
+
+    pub fn with_kind(name: String, size: usize, kind: TypeKind) -> Self {
+        Type {
+            name,
+            size,
+            kind,
+            type_parameters: Vec::new(),
+        }
+    }
+}
+
+/// What a type is, with typedefs and qualifiers looked through. Struct and union members refer
+/// to their types by .debug_info offset (see `DwarfData::get_type`), since a struct may contain
+/// pointers to itself.
+#[derive(Debug, Clone)]
+pub enum TypeKind {
+    Base(Encoding),
+    /// A pointer, to `void` if the pointee is None
+    Pointer(Option<Box<Type>>),
+    Array(Box<Type>),
+    /// A struct or union (whose members all have offset 0)
+    Struct(Vec<Member>),
+    Enum(Vec<(String, i64)>),
+    /// A Rust enum, which holds the variant selected by the value of its discriminant
+    Variants {
+        discriminant: Option<Member>,
+        variants: Vec<Variant>,
+    },
+    /// Function types and anything else deet doesn't understand
+    Unknown,
+}
+
+impl Default for TypeKind {
+    fn default() -> Self {
+        TypeKind::Unknown
+    }
+}
+
+/// How the bits of a base type are interpreted (DW_AT_encoding).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Signed,
+    Unsigned,
+    SignedChar,
+    UnsignedChar,
+    Float,
+    Boolean,
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub name: String,
+    /// Byte offset within the struct
+    pub offset: usize,
+    pub type_offset: Option<usize>,
+}
+
+/// One variant of a Rust enum. Its member is a struct of the variant's fields.
+#[derive(Debug, Clone)]
+pub struct Variant {
+    /// The value of the discriminant that selects this variant, or None for the variant that
+    /// any other value selects
+    pub discr_value: Option<i64>,
+    pub member: Member,
 }
 
 #[derive(Clone)]
@@ -191,6 +600,23 @@ pub struct Variable {
     pub entity_type: Type,
     pub location: Location,
     pub line_number: usize, // Line number in source file
+    pub is_parameter: bool,
+}
+
+/// What a function's DW_AT_frame_base refers to. `Location::FramePointerOffset` values of its
+/// variables are offsets from this address.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameBase {
+    /// The canonical frame address, i.e. the value of %rsp before the call instruction
+    Cfa,
+    /// The value of %rbp once the function's prologue has run
+    Rbp,
+}
+
+impl Default for FrameBase {
+    fn default() -> Self {
+        FrameBase::Cfa
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -199,9 +625,20 @@ pub struct Function {
     pub address: usize,
     pub text_length: usize,
     pub line_number: usize, // Line number in source file
+    pub frame_base: FrameBase,
+    pub return_type: Option<Type>, // None for functions returning void
     pub variables: Vec<Variable>,
 }
 
+/// One level of function activation at an address. An address inside code that was inlined into
+/// its caller has one of these for the inlined function, followed by one for each caller it was
+/// inlined into.
+#[derive(Debug, Clone)]
+pub struct InlineFrame {
+    pub function: Option<String>,
+    pub line: Option<Line>,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct File {
     pub name: String,
@@ -223,4 +660,206 @@ impl fmt::Display for Line {
     }
 }
 
+/// Parses a hexadecimal address, with or without a 0x prefix.
+pub fn parse_address(addr: &str) -> Option<usize> {
+    let addr_without_0x = if addr.to_lowercase().starts_with("0x") {
+        &addr[2..]
+    } else {
+        &addr
+    };
+    usize::from_str_radix(addr_without_0x, 16).ok()
+}
+
+/// Demangles a function's linkage name with the scheme of its compilation unit's language: the
+/// legacy and v0 schemes for Rust and the Itanium scheme for C++. The hashes of Rust names and
+/// the parameter lists of C++ ones are left out, so that "_ZN2ns3addEii" becomes "ns::add".
+/// Returns None if the name isn't mangled.
+pub fn demangle(name: &str, language: Option<addr2line::gimli::DwLang>) -> Option<String> {
+    let demangled = addr2line::demangle(name, language?)?;
+    Some(strip_parameters(&demangled).to_string())
+}
 
+/// Removes the parameter list, and the qualifiers after it, from a demangled C++ function name.
+fn strip_parameters(name: &str) -> &str {
+    let end = match name.rfind(')') {
+        Some(end) => end,
+        None => return name,
+    };
+    // Such as " const" or " &&"; a ')' followed by anything else is inside a template argument
+    if !name[end + 1..]
+        .chars()
+        .all(|c| c == ' ' || c == '&' || c.is_ascii_alphabetic())
+    {
+        return name;
+    }
+    let mut depth = 0;
+    for (i, c) in name[..=end].char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &name[..i];
+                }
+            }
+            _ => {}
+        }
+    }
+    name
+}
+
+/// Returns whether `qualified` is `name` inside some module or namespace, as "ns::add" is "add".
+fn is_qualified_name(qualified: &str, name: &str) -> bool {
+    qualified.ends_with(name) && qualified[..qualified.len() - name.len()].ends_with("::")
+}
+
+/// Returns the demangled name of a function found by addr2line.
+fn function_name<R: addr2line::gimli::Reader>(
+    function: &addr2line::FunctionName<R>,
+) -> Option<String> {
+    let raw_name = function.raw_name().ok()?;
+    Some(demangle(&raw_name, function.language).unwrap_or_else(|| raw_name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use std::sync::atomic::{self, AtomicUsize};
+
+    /// Number of samples compiled so far, which keeps tests from writing the same executable.
+    static COMPILED: AtomicUsize = AtomicUsize::new(0);
+
+    /// Compiles samples/<name>.c with the given extra compiler flags and returns the path of the
+    /// resulting executable.
+    fn compile(name: &str, flags: &[&str]) -> String {
+        let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("samples")
+            .join(format!("{}.c", name));
+        let out_dir = std::env::temp_dir().join(format!("deet-test-{}", std::process::id()));
+        fs::create_dir_all(&out_dir).expect("Error creating output directory");
+        let count = COMPILED.fetch_add(1, atomic::Ordering::SeqCst);
+        let output = out_dir.join(format!("{}-{}", name, count));
+        let status = Command::new("cc")
+            .args(&["-O0", "-g", "-no-pie", "-fno-omit-frame-pointer"])
+            .args(flags)
+            .arg("-o")
+            .arg(&output)
+            .arg(&source)
+            .status()
+            .expect("Error running cc");
+        assert!(status.success(), "cc {:?} failed", flags);
+        output.to_str().unwrap().to_string()
+    }
+
+    fn check_function_calls(flags: &[&str]) {
+        let path = compile("function_calls", flags);
+        let data = DwarfData::from_file_with_debug_dirs(&path, &[]).unwrap();
+
+        let file = &data.files[0];
+        assert!(
+            file.name.ends_with("function_calls.c"),
+            "bad name {}",
+            file.name
+        );
+        assert!(!file.lines.is_empty());
+        assert_eq!(file.global_variables[0].name, "global");
+        let func2 = file.functions.iter().find(|f| f.name == "func2").unwrap();
+        assert_eq!(func2.line_number, 9);
+        assert!(func2.text_length > 0);
+        let variables: Vec<&str> = func2.variables.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(variables, vec!["a", "b", "sum"]);
+        assert_eq!(func2.variables[0].entity_type.name, "int");
+
+        let addr = data.get_addr_for_function(None, "func2").unwrap();
+        assert_eq!(addr, func2.address);
+        assert_eq!(data.get_function_from_addr(addr).as_deref(), Some("func2"));
+        let line = data.get_line_from_addr(addr).unwrap();
+        assert!(line.file.ends_with("function_calls.c"));
+        assert_eq!(line.number, 9);
+        assert!(data.get_addr_for_line(None, 11).is_some());
+    }
+
+    #[test]
+    fn symbol_index() {
+        let path = compile("function_calls", &[]);
+        let data = DwarfData::from_file_with_debug_dirs(&path, &[]).unwrap();
+
+        // printf is only declared, at address 0
+        assert_eq!(data.get_addr_for_function(None, "printf"), None);
+        assert!(data.get_function("printf").is_none());
+        let func2 = data.get_function("func2").unwrap();
+        let inside = func2.address + func2.text_length - 1;
+        assert_eq!(data.get_function_containing(inside).unwrap().name, "func2");
+        let after = data.get_function_containing(func2.address + func2.text_length);
+        assert!(after.map_or(true, |func| func.name != "func2"));
+        assert!(data.get_function_containing(0).is_none());
+
+        let pattern = Regex::new("^func[12]$").unwrap();
+        let found = data.functions_matching(&pattern);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].0.ends_with("function_calls.c"));
+        let names: Vec<&str> = found[0].1.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["func1", "func2"]);
+        assert_eq!(data.get_global_variable("global").unwrap().line_number, 3);
+        assert!(data.type_names_matching(&pattern).is_empty());
+
+        let (line, end) = data.get_line_range(func2.address).unwrap();
+        assert_eq!(line.number, 9);
+        assert_eq!(line.address, func2.address);
+        assert!(end > func2.address);
+        // Addresses inside a line belong to the row where the line starts
+        assert_eq!(data.get_line_range(end - 1).unwrap().0, line);
+        assert!(data.get_line_range(0).is_none());
+        // The body starts on the line after the one the function is declared on
+        let body = data.get_addr_after_prologue(func2.address);
+        assert_eq!(body, end);
+        assert_eq!(data.get_line_range(body).unwrap().0.number, 10);
+    }
+
+    #[test]
+    fn demangling() {
+        let rust = Some(addr2line::gimli::DW_LANG_Rust);
+        let cpp = Some(addr2line::gimli::DW_LANG_C_plus_plus);
+        let legacy = "_ZN9std_types4show17hed5e4e62b0e98b18E";
+        assert_eq!(demangle(legacy, rust).as_deref(), Some("std_types::show"));
+        let v0 = "_RNvCsfQNnHm6tKTs_9std_types4show";
+        assert_eq!(demangle(v0, rust).as_deref(), Some("std_types::show"));
+        assert_eq!(demangle("_ZN2ns3addEii", cpp).as_deref(), Some("ns::add"));
+        let member = "_ZNK2ns5Point3sumEv";
+        assert_eq!(demangle(member, cpp).as_deref(), Some("ns::Point::sum"));
+        assert_eq!(demangle("main", cpp), None);
+        assert_eq!(demangle(legacy, None), None);
+
+        assert_eq!(strip_parameters("F::operator()(int)"), "F::operator()");
+        assert_eq!(strip_parameters("f(int (*)(int)) &&"), "f");
+        assert_eq!(strip_parameters("drop<(i32, i32)>"), "drop<(i32, i32)>");
+        assert!(is_qualified_name("ns::add", "add"));
+        assert!(!is_qualified_name("ns::padd", "add"));
+    }
+
+    #[test]
+    fn dwarf4() {
+        check_function_calls(&["-gdwarf-4"]);
+    }
+
+    #[test]
+    fn dwarf5() {
+        check_function_calls(&["-gdwarf-5"]);
+    }
+
+    #[test]
+    fn dwarf5_compressed() {
+        check_function_calls(&["-gdwarf-5", "-gz"]);
+    }
+
+    #[test]
+    fn dwarf5_gnu_compressed() {
+        check_function_calls(&["-gdwarf-5", "-gz=zlib-gnu"]);
+    }
+
+    #[test]
+    fn dwarf4_compressed() {
+        check_function_calls(&["-gdwarf-4", "-gz"]);
+    }
+}
@@ -0,0 +1,1328 @@
+//! This file contains the parser and evaluator for the C expressions accepted by `print`,
+//! `display`, `x` and breakpoint conditions. Expressions are parsed into an `Expr` tree and then
+//! evaluated against a `Context`, which is how the evaluator finds variables, registers, types
+//! and the inferior's memory.
+
+use crate::dwarf_data::{Encoding, Type, TypeKind};
+use std::convert::TryInto;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A name that isn't a variable in the current context
+    NoSymbol(String),
+    /// The expression couldn't be parsed; holds the rest of the input from where it went wrong
+    Syntax(String),
+    /// The expression is well-formed but can't be evaluated, e.g. because of a bad pointer
+    Eval(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoSymbol(name) => write!(f, "No symbol \"{}\" in current context.", name),
+            Error::Syntax(rest) if rest.is_empty() => write!(f, "A syntax error in expression."),
+            Error::Syntax(rest) => write!(f, "A syntax error in expression, near `{}'.", rest),
+            Error::Eval(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+fn eval_error<T>(message: &str) -> Result<T, Error> {
+    Err(Error::Eval(message.to_string()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+    BitNot,
+    Deref,
+    AddressOf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    Ne,
+    BitAnd,
+    BitXor,
+    BitOr,
+    And,
+    Or,
+}
+
+/// Binary operators from loosest to tightest binding.
+const BINARY_OPS: &[&[(&str, BinaryOp)]] = &[
+    &[("||", BinaryOp::Or)],
+    &[("&&", BinaryOp::And)],
+    &[("|", BinaryOp::BitOr)],
+    &[("^", BinaryOp::BitXor)],
+    &[("&", BinaryOp::BitAnd)],
+    &[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne)],
+    &[
+        ("<=", BinaryOp::Le),
+        (">=", BinaryOp::Ge),
+        ("<", BinaryOp::Lt),
+        (">", BinaryOp::Gt),
+    ],
+    &[("<<", BinaryOp::Shl), (">>", BinaryOp::Shr)],
+    &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+    &[
+        ("*", BinaryOp::Mul),
+        ("/", BinaryOp::Div),
+        ("%", BinaryOp::Rem),
+    ],
+];
+
+/// A type named in a cast, e.g. `unsigned long` or `struct point *`.
+#[derive(Debug, Clone)]
+pub struct TypeName {
+    pub base: String,
+    pub pointers: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    /// A literal and the C type its value and suffix give it
+    Integer(u64, &'static str),
+    Float(f64),
+    Char(u8),
+    Variable(String),
+    /// `$rax`, `$pc`, ...
+    Register(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// `s.f`; `p->f` is parsed as `(*p).f`
+    Member(Box<Expr>, String),
+    /// `a[i]`
+    Index(Box<Expr>, Box<Expr>),
+    Cast(TypeName, Box<Expr>),
+    /// `f(a, b)`, which runs `f` in the inferior
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Integer(u64, &'static str),
+    Float(f64),
+    Char(u8),
+    Identifier(String),
+    Register(String),
+    Punct(&'static str),
+}
+
+/// Punctuation, longest first so that e.g. `->` isn't read as `-`.
+const PUNCTUATION: &[&str] = &[
+    "->", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "&",
+    "|", "^", "!", "~", "(", ")", "[", "]", ".", ",",
+];
+
+/// Words that always start a type name in a cast.
+const TYPE_KEYWORDS: &[&str] = &[
+    "void", "char", "short", "int", "long", "float", "double", "signed", "unsigned", "_Bool",
+    "struct", "union", "enum", "const", "volatile",
+];
+
+/// The type of an integer literal: the first of the types its suffix allows that can hold the
+/// value. Unlike decimal literals, hex and octal ones may become unsigned without a `u`.
+fn integer_type(value: u64, suffix: &str, decimal: bool) -> &'static str {
+    let unsigned = suffix.contains(&['u', 'U'][..]);
+    let long = suffix.contains(&['l', 'L'][..]);
+    if !long && !unsigned && value <= i32::MAX as u64 {
+        "int"
+    } else if !long && (unsigned || !decimal) && value <= u32::MAX as u64 {
+        "unsigned int"
+    } else if !unsigned && value <= i64::MAX as u64 {
+        "long"
+    } else {
+        "unsigned long"
+    }
+}
+
+/// Splits an expression into tokens. Each token is returned with its byte offset, which is used
+/// to point at the rest of the input in syntax errors.
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        let start = pos;
+        if c.is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        }
+        if c.is_ascii_digit() || (c == b'.' && bytes.get(pos + 1).map_or(false, u8::is_ascii_digit))
+        {
+            while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'.') {
+                pos += 1;
+            }
+            let text = input[start..pos].trim_end_matches(&['u', 'U', 'l', 'L'][..]);
+            let suffix = &input[start + text.len()..pos];
+            let integer = |value: Option<u64>, decimal| {
+                value.map(|value| Token::Integer(value, integer_type(value, suffix, decimal)))
+            };
+            let token = if text.len() > 2 && text[..2].eq_ignore_ascii_case("0x") {
+                integer(u64::from_str_radix(&text[2..], 16).ok(), false)
+            } else if text.contains('.') || text.contains('e') || text.contains('E') {
+                text.parse::<f64>().ok().map(Token::Float)
+            } else if text.len() > 1 && text.starts_with('0') {
+                integer(u64::from_str_radix(&text[1..], 8).ok(), false)
+            } else {
+                integer(text.parse::<u64>().ok(), true)
+            };
+            match token {
+                Some(token) => tokens.push((token, start)),
+                None => return Err(Error::Syntax(input[start..].to_string())),
+            }
+        } else if c.is_ascii_alphabetic() || c == b'_' || c == b'$' {
+            pos += 1;
+            while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_') {
+                pos += 1;
+            }
+            let token = if c == b'$' {
+                Token::Register(input[start + 1..pos].to_string())
+            } else {
+                Token::Identifier(input[start..pos].to_string())
+            };
+            tokens.push((token, start));
+        } else if c == b'\'' {
+            let (value, len) = match (bytes.get(pos + 1), bytes.get(pos + 2)) {
+                (Some(b'\\'), Some(escaped)) => {
+                    let value = match escaped {
+                        b'n' => b'\n',
+                        b't' => b'\t',
+                        b'r' => b'\r',
+                        b'0' => 0,
+                        other => *other,
+                    };
+                    (value, 3)
+                }
+                (Some(value), _) => (*value, 2),
+                _ => return Err(Error::Syntax(input[start..].to_string())),
+            };
+            if bytes.get(pos + len) != Some(&b'\'') {
+                return Err(Error::Syntax(input[start..].to_string()));
+            }
+            tokens.push((Token::Char(value), start));
+            pos += len + 1;
+        } else {
+            match PUNCTUATION
+                .iter()
+                .find(|punct| input[pos..].starts_with(*punct))
+            {
+                Some(punct) => {
+                    tokens.push((Token::Punct(punct), start));
+                    pos += punct.len();
+                }
+                None => return Err(Error::Syntax(input[start..].to_string())),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    /// Tells typedef names apart from variable names, so that `(size_t)x` is a cast
+    is_type_name: &'a dyn Fn(&str) -> bool,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset).map(|(token, _)| token)
+    }
+
+    fn syntax_error<T>(&self) -> Result<T, Error> {
+        let rest = match self.tokens.get(self.pos) {
+            Some((_, offset)) => &self.input[*offset..],
+            None => "",
+        };
+        Err(Error::Syntax(rest.to_string()))
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        if self.peek() == Some(&Token::Punct(punctuation(punct))) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), Error> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            self.syntax_error()
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Some(Token::Identifier(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => self.syntax_error(),
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, Error> {
+        if level == BINARY_OPS.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        'operators: loop {
+            for (punct, op) in BINARY_OPS[level] {
+                if self.eat(punct) {
+                    let right = self.binary(level + 1)?;
+                    left = Expr::Binary(*op, Box::new(left), Box::new(right));
+                    continue 'operators;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, Error> {
+        let op = match self.peek() {
+            Some(Token::Punct("-")) => Some(UnaryOp::Negate),
+            Some(Token::Punct("!")) => Some(UnaryOp::Not),
+            Some(Token::Punct("~")) => Some(UnaryOp::BitNot),
+            Some(Token::Punct("*")) => Some(UnaryOp::Deref),
+            Some(Token::Punct("&")) => Some(UnaryOp::AddressOf),
+            Some(Token::Punct("+")) => {
+                self.pos += 1;
+                return self.unary();
+            }
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.pos += 1;
+            return Ok(Expr::Unary(op, Box::new(self.unary()?)));
+        }
+        if self.is_cast() {
+            self.pos += 1;
+            let type_name = self.type_name()?;
+            self.expect(")")?;
+            return Ok(Expr::Cast(type_name, Box::new(self.unary()?)));
+        }
+        self.postfix()
+    }
+
+    /// Returns true if the next tokens are `(` followed by a type name.
+    fn is_cast(&self) -> bool {
+        match (self.peek(), self.peek_at(1)) {
+            (Some(Token::Punct("(")), Some(Token::Identifier(name))) => {
+                TYPE_KEYWORDS.contains(&name.as_str()) || (self.is_type_name)(name)
+            }
+            _ => false,
+        }
+    }
+
+    fn type_name(&mut self) -> Result<TypeName, Error> {
+        let mut words = Vec::new();
+        while let Some(Token::Identifier(word)) = self.peek() {
+            let word = word.clone();
+            self.pos += 1;
+            match word.as_str() {
+                "const" | "volatile" => continue,
+                "struct" | "union" | "enum" => {
+                    let tag = self.identifier()?;
+                    words.push(format!("{} {}", word, tag));
+                }
+                _ => words.push(word),
+            }
+        }
+        if words.is_empty() {
+            return self.syntax_error();
+        }
+        let mut pointers = 0;
+        while self.eat("*") {
+            pointers += 1;
+        }
+        Ok(TypeName {
+            base: words.join(" "),
+            pointers,
+        })
+    }
+
+    fn postfix(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat("[") {
+                let index = self.binary(0)?;
+                self.expect("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index));
+            } else if self.peek() == Some(&Token::Punct("(")) {
+                let name = match expr {
+                    Expr::Variable(name) => name,
+                    _ => return self.syntax_error(),
+                };
+                self.pos += 1;
+                let mut args = Vec::new();
+                if !self.eat(")") {
+                    loop {
+                        args.push(self.binary(0)?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                expr = Expr::Call(name, args);
+            } else if self.eat(".") {
+                expr = Expr::Member(Box::new(expr), self.identifier()?);
+            } else if self.eat("->") {
+                let pointee = Expr::Unary(UnaryOp::Deref, Box::new(expr));
+                expr = Expr::Member(Box::new(pointee), self.identifier()?);
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, Error> {
+        let expr = match self.peek() {
+            Some(Token::Integer(value, ty)) => Expr::Integer(*value, ty),
+            Some(Token::Float(value)) => Expr::Float(*value),
+            Some(Token::Char(value)) => Expr::Char(*value),
+            Some(Token::Identifier(name)) => Expr::Variable(name.clone()),
+            Some(Token::Register(name)) => Expr::Register(name.clone()),
+            Some(Token::Punct("(")) => {
+                self.pos += 1;
+                let expr = self.binary(0)?;
+                self.expect(")")?;
+                return Ok(expr);
+            }
+            _ => return self.syntax_error(),
+        };
+        self.pos += 1;
+        Ok(expr)
+    }
+}
+
+/// Returns the `&'static str` for a punctuation token, so that tokens can be compared.
+fn punctuation(punct: &str) -> &'static str {
+    PUNCTUATION.iter().find(|p| **p == punct).unwrap()
+}
+
+/// Parses an expression. `is_type_name` says whether an identifier names a type (such as a
+/// typedef), which decides whether `(name)` starts a cast.
+pub fn parse(input: &str, is_type_name: &dyn Fn(&str) -> bool) -> Result<Expr, Error> {
+    let mut parser = Parser {
+        input,
+        tokens: tokenize(input)?,
+        pos: 0,
+        is_type_name,
+    };
+    let expr = parser.binary(0)?;
+    if parser.pos < parser.tokens.len() {
+        return parser.syntax_error();
+    }
+    Ok(expr)
+}
+
+/// What the evaluator needs to know about the program being debugged.
+pub trait Context {
+    /// Returns the address and type of a variable visible in the selected frame.
+    fn variable(&self, name: &str) -> Option<(usize, Type)>;
+    fn register(&self, name: &str) -> Option<u64>;
+    fn read_memory(&self, addr: usize, len: usize) -> Result<Vec<u8>, Error>;
+    /// Returns the type at a .debug_info offset (for struct members).
+    fn get_type(&self, offset: usize) -> Option<Type>;
+    fn get_type_by_name(&self, name: &str) -> Option<Type>;
+    /// Calls a function in the inferior with arguments that have already been converted by
+    /// `argument`, and returns what it returned.
+    fn call_function(&self, name: &str, args: &[Value]) -> Result<Value, Error>;
+}
+
+/// The result of evaluating an expression.
+#[derive(Debug, Clone)]
+pub struct Value {
+    pub ty: Type,
+    pub bytes: Vec<u8>,
+    /// Where the value lives in the inferior's memory, if it does
+    pub address: Option<usize>,
+}
+
+/// A value converted for arithmetic.
+#[derive(Debug, Clone, Copy)]
+enum Scalar {
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+}
+
+impl Scalar {
+    fn as_i64(self) -> i64 {
+        match self {
+            Scalar::Signed(value) => value,
+            Scalar::Unsigned(value) => value as i64,
+            Scalar::Float(value) => value as i64,
+        }
+    }
+
+    fn as_u64(self) -> u64 {
+        match self {
+            Scalar::Signed(value) => value as u64,
+            Scalar::Unsigned(value) => value,
+            Scalar::Float(value) => value as u64,
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Scalar::Signed(value) => value as f64,
+            Scalar::Unsigned(value) => value as f64,
+            Scalar::Float(value) => value,
+        }
+    }
+
+    fn is_true(self) -> bool {
+        match self {
+            Scalar::Float(value) => value != 0.0,
+            other => other.as_u64() != 0,
+        }
+    }
+}
+
+/// Returns the C base types and `void`, which exist even if the program never uses them.
+pub fn builtin_type(name: &str) -> Option<Type> {
+    let (size, encoding) = match name {
+        "char" | "signed char" => (1, Encoding::SignedChar),
+        "unsigned char" => (1, Encoding::UnsignedChar),
+        "short" | "short int" | "signed short" => (2, Encoding::Signed),
+        "unsigned short" | "unsigned short int" | "short unsigned int" => (2, Encoding::Unsigned),
+        "int" | "signed" | "signed int" => (4, Encoding::Signed),
+        "unsigned" | "unsigned int" => (4, Encoding::Unsigned),
+        "long" | "long int" | "signed long" | "long long" | "long long int" => {
+            (8, Encoding::Signed)
+        }
+        "unsigned long"
+        | "unsigned long int"
+        | "long unsigned int"
+        | "unsigned long long"
+        | "long long unsigned int" => (8, Encoding::Unsigned),
+        "float" => (4, Encoding::Float),
+        "double" => (8, Encoding::Float),
+        "_Bool" | "bool" => (1, Encoding::Boolean),
+        "void" => return Some(Type::new("void".to_string(), 0)),
+        _ => return None,
+    };
+    Some(Type::with_kind(
+        name.to_string(),
+        size,
+        TypeKind::Base(encoding),
+    ))
+}
+
+fn builtin(name: &str) -> Type {
+    builtin_type(name).unwrap()
+}
+
+fn pointer_to(ty: Type) -> Type {
+    let name = if ty.name.ends_with('*') {
+        format!("{}*", ty.name)
+    } else {
+        format!("{} *", ty.name)
+    };
+    let pointee = match ty.kind {
+        TypeKind::Unknown if ty.size == 0 => None,
+        _ => Some(Box::new(ty)),
+    };
+    Type::with_kind(name, 8, TypeKind::Pointer(pointee))
+}
+
+/// Makes a value of a base or pointer type from a number.
+fn scalar_value(scalar: Scalar, ty: Type) -> Value {
+    let bytes = match (&ty.kind, scalar) {
+        (TypeKind::Base(Encoding::Float), _) if ty.size == 4 => {
+            (scalar.as_f64() as f32).to_le_bytes().to_vec()
+        }
+        (TypeKind::Base(Encoding::Float), _) => scalar.as_f64().to_le_bytes().to_vec(),
+        (TypeKind::Base(Encoding::Boolean), _) => vec![scalar.is_true() as u8],
+        (_, Scalar::Float(value)) => (value as i64).to_le_bytes()[..ty.size.min(8)].to_vec(),
+        (_, other) => other.as_u64().to_le_bytes()[..ty.size.min(8)].to_vec(),
+    };
+    Value {
+        ty,
+        bytes,
+        address: None,
+    }
+}
+
+fn int_value(value: i64) -> Value {
+    scalar_value(Scalar::Signed(value), builtin("int"))
+}
+
+/// Reads a value of type `ty` at `addr`.
+fn value_at(ctx: &dyn Context, addr: usize, ty: Type) -> Result<Value, Error> {
+    // Arrays of unknown size, like `extern int a[];`, can still be indexed
+    match ty.kind {
+        TypeKind::Array(_) => {}
+        _ if ty.size == 0 => return eval_error("Attempt to take contents of a non-pointer value."),
+        _ => {}
+    }
+    let bytes = ctx.read_memory(addr, ty.size)?;
+    Ok(Value {
+        ty,
+        bytes,
+        address: Some(addr),
+    })
+}
+
+/// Converts a value for arithmetic. Arrays aren't scalars; they are handled as pointers by the
+/// operators that accept them.
+fn scalar(value: &Value) -> Result<Scalar, Error> {
+    let mut raw = [0u8; 8];
+    let len = value.bytes.len().min(8);
+    raw[..len].copy_from_slice(&value.bytes[..len]);
+    let unsigned = u64::from_le_bytes(raw);
+    let bits = 8 * len as u32;
+    let signed = if bits == 0 || bits == 64 {
+        unsigned as i64
+    } else {
+        ((unsigned << (64 - bits)) as i64) >> (64 - bits)
+    };
+    match value.ty.kind {
+        TypeKind::Base(Encoding::Float) if len == 4 => Ok(Scalar::Float(f32::from_le_bytes(
+            value.bytes[..4].try_into().unwrap(),
+        ) as f64)),
+        TypeKind::Base(Encoding::Float) if len == 8 => Ok(Scalar::Float(f64::from_le_bytes(raw))),
+        TypeKind::Base(Encoding::Float) => eval_error("Unsupported floating point type."),
+        TypeKind::Base(Encoding::Signed)
+        | TypeKind::Base(Encoding::SignedChar)
+        | TypeKind::Enum(_) => Ok(Scalar::Signed(signed)),
+        TypeKind::Base(_) | TypeKind::Pointer(_) => Ok(Scalar::Unsigned(unsigned)),
+        _ => eval_error("Argument to arithmetic operation not a number or boolean."),
+    }
+}
+
+fn is_integer(ty: &Type) -> bool {
+    match ty.kind {
+        TypeKind::Base(Encoding::Float) => false,
+        TypeKind::Base(_) | TypeKind::Enum(_) => true,
+        _ => false,
+    }
+}
+
+fn is_unsigned(ty: &Type) -> bool {
+    match ty.kind {
+        TypeKind::Base(Encoding::Unsigned)
+        | TypeKind::Base(Encoding::UnsignedChar)
+        | TypeKind::Base(Encoding::Boolean)
+        | TypeKind::Pointer(_) => true,
+        _ => false,
+    }
+}
+
+/// Returns the type both operands of an arithmetic operator are converted to (C's "usual
+/// arithmetic conversions", with everything narrower than int promoted to int).
+fn common_type(left: &Type, right: &Type) -> Type {
+    let is_float = |ty: &Type| match ty.kind {
+        TypeKind::Base(Encoding::Float) => true,
+        _ => false,
+    };
+    if is_float(left) || is_float(right) {
+        return builtin("double");
+    }
+    let size = left.size.max(right.size).max(4);
+    let unsigned =
+        (is_unsigned(left) && left.size >= size) || (is_unsigned(right) && right.size >= size);
+    builtin(match (size, unsigned) {
+        (4, false) => "int",
+        (4, true) => "unsigned int",
+        (_, false) => "long",
+        (_, true) => "unsigned long",
+    })
+}
+
+/// Returns the pointer an array or pointer value stands for in arithmetic, along with the type
+/// it points to.
+fn as_pointer(value: &Value) -> Option<(u64, Option<Type>)> {
+    match &value.ty.kind {
+        TypeKind::Pointer(pointee) => Some((
+            scalar(value).ok()?.as_u64(),
+            pointee.as_ref().map(|pointee| (**pointee).clone()),
+        )),
+        TypeKind::Array(element) => Some((value.address? as u64, Some((**element).clone()))),
+        _ => None,
+    }
+}
+
+/// Size of what a pointer points to, for pointer arithmetic. Like gcc, `void *` steps by bytes.
+fn stride(pointee: &Option<Type>) -> u64 {
+    match pointee {
+        Some(pointee) if pointee.size > 0 => pointee.size as u64,
+        _ => 1,
+    }
+}
+
+fn pointer_value(addr: u64, pointee: Option<Type>) -> Value {
+    let ty = match pointee {
+        Some(pointee) => pointer_to(pointee),
+        None => Type::with_kind("void *".to_string(), 8, TypeKind::Pointer(None)),
+    };
+    scalar_value(Scalar::Unsigned(addr), ty)
+}
+
+/// Resolves a type named in a cast.
+fn resolve_type_name(ctx: &dyn Context, type_name: &TypeName) -> Result<Type, Error> {
+    let mut ty =
+        match builtin_type(&type_name.base).or_else(|| ctx.get_type_by_name(&type_name.base)) {
+            Some(ty) => ty,
+            None => return Err(Error::NoSymbol(type_name.base.clone())),
+        };
+    for _ in 0..type_name.pointers {
+        ty = pointer_to(ty);
+    }
+    Ok(ty)
+}
+
+fn cast(value: Value, ty: Type) -> Result<Value, Error> {
+    match ty.kind {
+        TypeKind::Base(_) | TypeKind::Enum(_) | TypeKind::Pointer(_) => {
+            let number = match as_pointer(&value) {
+                Some((addr, _)) => Scalar::Unsigned(addr),
+                None => scalar(&value)?,
+            };
+            Ok(scalar_value(number, ty))
+        }
+        _ => eval_error("Invalid cast."),
+    }
+}
+
+fn arithmetic(op: BinaryOp, left: &Value, right: &Value) -> Result<Value, Error> {
+    let ty = common_type(&left.ty, &right.ty);
+    let (a, b) = (scalar(left)?, scalar(right)?);
+    let is_float = !is_integer(&ty);
+    if !is_float && (op == BinaryOp::Div || op == BinaryOp::Rem) && b.as_u64() == 0 {
+        return eval_error("Division by zero");
+    }
+    if is_float {
+        let (a, b) = (a.as_f64(), b.as_f64());
+        let result = match op {
+            BinaryOp::Mul => a * b,
+            BinaryOp::Div => a / b,
+            BinaryOp::Add => a + b,
+            BinaryOp::Sub => a - b,
+            _ => return eval_error("Integer only operation."),
+        };
+        return Ok(scalar_value(Scalar::Float(result), ty));
+    }
+    let result = if is_unsigned(&ty) {
+        let (a, b) = (a.as_u64(), b.as_u64());
+        Scalar::Unsigned(match op {
+            BinaryOp::Mul => a.wrapping_mul(b),
+            BinaryOp::Div => a / b,
+            BinaryOp::Rem => a % b,
+            BinaryOp::Add => a.wrapping_add(b),
+            BinaryOp::Sub => a.wrapping_sub(b),
+            BinaryOp::Shl => a.wrapping_shl(b as u32),
+            BinaryOp::Shr => a.wrapping_shr(b as u32),
+            BinaryOp::BitAnd => a & b,
+            BinaryOp::BitXor => a ^ b,
+            BinaryOp::BitOr => a | b,
+            _ => unreachable!(),
+        })
+    } else {
+        let (a, b) = (a.as_i64(), b.as_i64());
+        Scalar::Signed(match op {
+            BinaryOp::Mul => a.wrapping_mul(b),
+            BinaryOp::Div => a.wrapping_div(b),
+            BinaryOp::Rem => a.wrapping_rem(b),
+            BinaryOp::Add => a.wrapping_add(b),
+            BinaryOp::Sub => a.wrapping_sub(b),
+            BinaryOp::Shl => a.wrapping_shl(b as u32),
+            BinaryOp::Shr => a.wrapping_shr(b as u32),
+            BinaryOp::BitAnd => a & b,
+            BinaryOp::BitXor => a ^ b,
+            BinaryOp::BitOr => a | b,
+            _ => unreachable!(),
+        })
+    };
+    Ok(scalar_value(result, ty))
+}
+
+fn compare(op: BinaryOp, left: &Value, right: &Value) -> Result<Value, Error> {
+    let ordering = match (as_pointer(left), as_pointer(right)) {
+        (Some((a, _)), Some((b, _))) => a.partial_cmp(&b),
+        (Some((a, _)), None) => a.partial_cmp(&scalar(right)?.as_u64()),
+        (None, Some((b, _))) => scalar(left)?.as_u64().partial_cmp(&b),
+        (None, None) => {
+            let ty = common_type(&left.ty, &right.ty);
+            let (a, b) = (scalar(left)?, scalar(right)?);
+            if !is_integer(&ty) {
+                a.as_f64().partial_cmp(&b.as_f64())
+            } else if is_unsigned(&ty) {
+                a.as_u64().partial_cmp(&b.as_u64())
+            } else {
+                a.as_i64().partial_cmp(&b.as_i64())
+            }
+        }
+    };
+    use std::cmp::Ordering::*;
+    let result = match (op, ordering) {
+        // Comparisons with NaN are false, except !=
+        (BinaryOp::Ne, None) => true,
+        (_, None) => false,
+        (BinaryOp::Lt, Some(ordering)) => ordering == Less,
+        (BinaryOp::Gt, Some(ordering)) => ordering == Greater,
+        (BinaryOp::Le, Some(ordering)) => ordering != Greater,
+        (BinaryOp::Ge, Some(ordering)) => ordering != Less,
+        (BinaryOp::Eq, Some(ordering)) => ordering == Equal,
+        (BinaryOp::Ne, Some(ordering)) => ordering != Equal,
+        _ => unreachable!(),
+    };
+    Ok(int_value(result as i64))
+}
+
+fn truth(ctx: &dyn Context, expr: &Expr) -> Result<bool, Error> {
+    is_true(&evaluate(ctx, expr)?)
+}
+
+fn binary(ctx: &dyn Context, op: BinaryOp, left: &Expr, right: &Expr) -> Result<Value, Error> {
+    // && and || don't evaluate their right operand if the left one decides the result
+    match op {
+        BinaryOp::And => {
+            let result = truth(ctx, left)? && truth(ctx, right)?;
+            return Ok(int_value(result as i64));
+        }
+        BinaryOp::Or => {
+            let result = truth(ctx, left)? || truth(ctx, right)?;
+            return Ok(int_value(result as i64));
+        }
+        _ => {}
+    }
+    let left = evaluate(ctx, left)?;
+    let right = evaluate(ctx, right)?;
+    match op {
+        BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Le | BinaryOp::Ge | BinaryOp::Eq | BinaryOp::Ne => {
+            return compare(op, &left, &right)
+        }
+        _ => {}
+    }
+    match (op, as_pointer(&left), as_pointer(&right)) {
+        (BinaryOp::Add, Some((addr, pointee)), None)
+        | (BinaryOp::Sub, Some((addr, pointee)), None) => {
+            if !is_integer(&right.ty) {
+                return eval_error("Argument to arithmetic operation not a number or boolean.");
+            }
+            let offset = scalar(&right)?
+                .as_i64()
+                .wrapping_mul(stride(&pointee) as i64);
+            let offset = if op == BinaryOp::Sub {
+                offset.wrapping_neg()
+            } else {
+                offset
+            };
+            Ok(pointer_value(addr.wrapping_add(offset as u64), pointee))
+        }
+        (BinaryOp::Add, None, Some((addr, pointee))) => {
+            if !is_integer(&left.ty) {
+                return eval_error("Argument to arithmetic operation not a number or boolean.");
+            }
+            let offset = scalar(&left)?
+                .as_i64()
+                .wrapping_mul(stride(&pointee) as i64);
+            Ok(pointer_value(addr.wrapping_add(offset as u64), pointee))
+        }
+        (BinaryOp::Sub, Some((a, pointee)), Some((b, _))) => {
+            let difference = (a.wrapping_sub(b) as i64) / stride(&pointee) as i64;
+            Ok(scalar_value(Scalar::Signed(difference), builtin("long")))
+        }
+        (_, None, None) => {
+            let integer_only = match op {
+                BinaryOp::Rem
+                | BinaryOp::Shl
+                | BinaryOp::Shr
+                | BinaryOp::BitAnd
+                | BinaryOp::BitXor
+                | BinaryOp::BitOr => true,
+                _ => false,
+            };
+            if integer_only && !(is_integer(&left.ty) && is_integer(&right.ty)) {
+                return eval_error("Integer only operation.");
+            }
+            arithmetic(op, &left, &right)
+        }
+        _ => eval_error("Argument to arithmetic operation not a number or boolean."),
+    }
+}
+
+fn member(ctx: &dyn Context, value: Value, name: &str) -> Result<Value, Error> {
+    let members = match &value.ty.kind {
+        TypeKind::Struct(members) => members,
+        TypeKind::Pointer(_) => {
+            return eval_error(&format!(
+                "The value of type `{}' is a pointer; use -> instead of .",
+                value.ty.name
+            ))
+        }
+        _ => {
+            return eval_error("Attempt to extract a component of a value that is not a structure.")
+        }
+    };
+    let member = match members.iter().find(|member| member.name == name) {
+        Some(member) => member,
+        None => return eval_error(&format!("There is no member named {}.", name)),
+    };
+    let ty = match member.type_offset.and_then(|offset| ctx.get_type(offset)) {
+        Some(ty) => ty,
+        None => return eval_error(&format!("Unknown type of member {}.", name)),
+    };
+    let start = member.offset;
+    let end = start + ty.size;
+    if end > value.bytes.len() {
+        return eval_error(&format!("Member {} lies outside of its struct.", name));
+    }
+    Ok(Value {
+        bytes: value.bytes[start..end].to_vec(),
+        address: value.address.map(|addr| addr + start),
+        ty,
+    })
+}
+
+fn register(ctx: &dyn Context, name: &str) -> Result<Value, Error> {
+    let value = match ctx.register(name) {
+        Some(value) => value,
+        None => return eval_error(&format!("Invalid register `${}'.", name)),
+    };
+    let ty = match name {
+        "pc" | "rip" | "sp" | "rsp" | "fp" | "rbp" => {
+            Type::with_kind("void *".to_string(), 8, TypeKind::Pointer(None))
+        }
+        _ => builtin("long"),
+    };
+    Ok(scalar_value(Scalar::Unsigned(value), ty))
+}
+
+/// Evaluates an expression.
+pub fn evaluate(ctx: &dyn Context, expr: &Expr) -> Result<Value, Error> {
+    match expr {
+        Expr::Integer(value, ty) => Ok(scalar_value(Scalar::Unsigned(*value), builtin(ty))),
+        Expr::Float(value) => Ok(scalar_value(Scalar::Float(*value), builtin("double"))),
+        Expr::Char(value) => Ok(scalar_value(
+            Scalar::Unsigned(*value as u64),
+            builtin("char"),
+        )),
+        Expr::Variable(name) => match ctx.variable(name) {
+            Some((addr, ty)) => value_at(ctx, addr, ty),
+            None => Err(Error::NoSymbol(name.clone())),
+        },
+        Expr::Register(name) => register(ctx, name),
+        Expr::Unary(UnaryOp::AddressOf, operand) => {
+            let value = evaluate(ctx, operand)?;
+            match value.address {
+                Some(addr) => Ok(pointer_value(addr as u64, Some(value.ty))),
+                None => eval_error("Attempt to take address of value not located in memory."),
+            }
+        }
+        Expr::Unary(UnaryOp::Deref, operand) => {
+            let value = evaluate(ctx, operand)?;
+            match as_pointer(&value) {
+                Some((addr, Some(pointee))) => value_at(ctx, addr as usize, pointee),
+                Some((_, None)) => eval_error("Attempt to take contents of a non-pointer value."),
+                None if is_integer(&value.ty) => {
+                    // Like gdb, `*0x1234` reads a long at that address
+                    value_at(ctx, scalar(&value)?.as_u64() as usize, builtin("long"))
+                }
+                None => eval_error("Attempt to take contents of a non-pointer value."),
+            }
+        }
+        Expr::Unary(op, operand) => {
+            let value = evaluate(ctx, operand)?;
+            if *op == UnaryOp::Not {
+                return Ok(int_value(!is_true(&value)? as i64));
+            }
+            let zero = int_value(0);
+            match op {
+                UnaryOp::Negate => arithmetic(BinaryOp::Sub, &zero, &value),
+                _ if !is_integer(&value.ty) => {
+                    eval_error("Argument to complement operation not an integer.")
+                }
+                _ => {
+                    let ty = common_type(&value.ty, &zero.ty);
+                    Ok(scalar_value(
+                        Scalar::Unsigned(!scalar(&value)?.as_u64()),
+                        ty,
+                    ))
+                }
+            }
+        }
+        Expr::Binary(op, left, right) => binary(ctx, *op, left, right),
+        Expr::Member(operand, name) => member(ctx, evaluate(ctx, operand)?, name),
+        Expr::Index(array, index) => {
+            let value = evaluate(ctx, array)?;
+            if as_pointer(&value).is_none() {
+                return eval_error(&format!(
+                    "cannot subscript something of type `{}'",
+                    value.ty.name
+                ));
+            }
+            let sum = Expr::Binary(BinaryOp::Add, array.clone(), index.clone());
+            evaluate(ctx, &Expr::Unary(UnaryOp::Deref, Box::new(sum)))
+        }
+        Expr::Cast(type_name, operand) => {
+            let ty = resolve_type_name(ctx, type_name)?;
+            cast(evaluate(ctx, operand)?, ty)
+        }
+        Expr::Call(name, args) => {
+            let args = args
+                .iter()
+                .map(|arg| evaluate(ctx, arg))
+                .collect::<Result<Vec<Value>, Error>>()?;
+            ctx.call_function(name, &args)
+        }
+    }
+}
+
+/// Converts an argument of a function call to the type of its parameter, or applies C's default
+/// argument promotions if the parameter's type isn't known (e.g. for the `...` of `printf`).
+/// Arrays are passed as pointers to their first element.
+pub fn argument(value: Value, param: Option<&Type>) -> Result<Value, Error> {
+    if let Some(param) = param {
+        return match param.kind {
+            TypeKind::Struct(_) | TypeKind::Variants { .. } => {
+                eval_error("Passing structures by value is not supported.")
+            }
+            _ => cast(value, param.clone()),
+        };
+    }
+    match &value.ty.kind {
+        TypeKind::Array(element) => Ok(pointer_value(
+            to_address(&value)? as u64,
+            Some((**element).clone()),
+        )),
+        TypeKind::Base(Encoding::Float) => cast(value, builtin("double")),
+        TypeKind::Base(_) | TypeKind::Enum(_) if value.ty.size < 4 => cast(value, builtin("int")),
+        TypeKind::Base(_) | TypeKind::Enum(_) | TypeKind::Pointer(_) => Ok(value),
+        TypeKind::Struct(_) | TypeKind::Variants { .. } => {
+            eval_error("Passing structures by value is not supported.")
+        }
+        TypeKind::Unknown => eval_error(&format!(
+            "Cannot pass a value of type `{}' to a function.",
+            value.ty.name
+        )),
+    }
+}
+
+/// Returns the integer or address a value stands for, e.g. as the start address for `x`.
+pub fn to_address(value: &Value) -> Result<usize, Error> {
+    match as_pointer(value) {
+        Some((addr, _)) => Ok(addr as usize),
+        None if is_integer(&value.ty) => Ok(scalar(value)?.as_u64() as usize),
+        None => match value.address {
+            Some(addr) => Ok(addr),
+            None => eval_error("Attempt to take address of value not located in memory."),
+        },
+    }
+}
+
+/// Returns true if a value is nonzero, as for a breakpoint condition.
+pub fn is_true(value: &Value) -> Result<bool, Error> {
+    match as_pointer(value) {
+        Some((addr, _)) => Ok(addr != 0),
+        None => Ok(scalar(value)?.is_true()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dwarf_data::Member;
+
+    /// Where the mock program's memory starts.
+    const BASE: usize = 0x1000;
+    /// The .debug_info offset `get_type` knows `int` by, for struct members.
+    const INT_OFFSET: usize = 1;
+
+    /// A program with these variables:
+    ///
+    ///     int x = -3;                  unsigned u = 2;
+    ///     int arr[4] = {10, 20, 30, 40};
+    ///     int *p = &arr[1];            char c = 'A';
+    ///     struct point pt = {5, 7};    struct point *pp = &pt;
+    ///     typedef unsigned long size_t;
+    struct MockContext {
+        memory: Vec<u8>,
+        variables: Vec<(&'static str, usize, Type)>,
+    }
+
+    fn point() -> Type {
+        let member = |name: &str, offset| Member {
+            name: name.to_string(),
+            offset,
+            type_offset: Some(INT_OFFSET),
+        };
+        Type::with_kind(
+            "struct point".to_string(),
+            8,
+            TypeKind::Struct(vec![member("x", 0), member("y", 4)]),
+        )
+    }
+
+    impl MockContext {
+        fn new() -> MockContext {
+            let mut ctx = MockContext {
+                memory: vec![0; 0x100],
+                variables: Vec::new(),
+            };
+            let int = builtin("int");
+            let array = Type::with_kind(
+                "int [4]".to_string(),
+                16,
+                TypeKind::Array(Box::new(int.clone())),
+            );
+            ctx.store("x", 0x00, int.clone(), &(-3i32).to_le_bytes());
+            ctx.store("u", 0x04, builtin("unsigned int"), &2u32.to_le_bytes());
+            let elements: Vec<u8> = [10i32, 20, 30, 40]
+                .iter()
+                .flat_map(|element| element.to_le_bytes().to_vec())
+                .collect();
+            ctx.store("arr", 0x10, array, &elements);
+            let p = (BASE as u64 + 0x14).to_le_bytes();
+            ctx.store("p", 0x20, pointer_to(int), &p);
+            ctx.store("c", 0x28, builtin("char"), b"A");
+            let pt: Vec<u8> = [5i32, 7]
+                .iter()
+                .flat_map(|member| member.to_le_bytes().to_vec())
+                .collect();
+            ctx.store("pt", 0x30, point(), &pt);
+            let pp = (BASE as u64 + 0x30).to_le_bytes();
+            ctx.store("pp", 0x38, pointer_to(point()), &pp);
+            ctx
+        }
+
+        fn store(&mut self, name: &'static str, offset: usize, ty: Type, bytes: &[u8]) {
+            self.memory[offset..offset + bytes.len()].copy_from_slice(bytes);
+            self.variables.push((name, BASE + offset, ty));
+        }
+    }
+
+    impl Context for MockContext {
+        fn variable(&self, name: &str) -> Option<(usize, Type)> {
+            self.variables
+                .iter()
+                .find(|(variable, _, _)| *variable == name)
+                .map(|(_, addr, ty)| (*addr, ty.clone()))
+        }
+
+        fn register(&self, name: &str) -> Option<u64> {
+            match name {
+                "rip" | "pc" => Some(0x401000),
+                "rax" => Some(42),
+                _ => None,
+            }
+        }
+
+        fn read_memory(&self, addr: usize, len: usize) -> Result<Vec<u8>, Error> {
+            match addr.checked_sub(BASE) {
+                Some(offset) if offset + len <= self.memory.len() => {
+                    Ok(self.memory[offset..offset + len].to_vec())
+                }
+                _ => eval_error(&format!("Cannot access memory at address {:#x}", addr)),
+            }
+        }
+
+        fn get_type(&self, offset: usize) -> Option<Type> {
+            if offset == INT_OFFSET {
+                Some(builtin("int"))
+            } else {
+                None
+            }
+        }
+
+        fn get_type_by_name(&self, name: &str) -> Option<Type> {
+            match name {
+                "size_t" => Some(Type::with_kind(
+                    "size_t".to_string(),
+                    8,
+                    TypeKind::Base(Encoding::Unsigned),
+                )),
+                "struct point" => Some(point()),
+                _ => None,
+            }
+        }
+
+        fn call_function(&self, name: &str, _args: &[Value]) -> Result<Value, Error> {
+            eval_error(&format!("Cannot call {} here.", name))
+        }
+    }
+
+    fn eval(input: &str) -> Result<Value, Error> {
+        let expr = parse(input, &|name| name == "size_t")?;
+        evaluate(&MockContext::new(), &expr)
+    }
+
+    /// Evaluates an expression to its value as a string, and the name of its type.
+    fn show(input: &str) -> (String, String) {
+        let value = eval(input).unwrap_or_else(|err| panic!("{}: {}", input, err));
+        let text = match as_pointer(&value) {
+            Some((addr, _)) => format!("{:#x}", addr),
+            None => match scalar(&value).unwrap() {
+                Scalar::Signed(value) => value.to_string(),
+                Scalar::Unsigned(value) => value.to_string(),
+                Scalar::Float(value) => value.to_string(),
+            },
+        };
+        (text, value.ty.name)
+    }
+
+    fn check(cases: &[(&str, &str, &str)]) {
+        for (input, value, ty) in cases {
+            assert_eq!(
+                show(input),
+                (value.to_string(), ty.to_string()),
+                "{}",
+                input
+            );
+        }
+    }
+
+    fn error(input: &str) -> String {
+        match eval(input) {
+            Ok(value) => panic!("{} evaluated to {:?}", input, value),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        check(&[
+            ("1-2-3", "-4", "int"),
+            ("3<<1+1", "12", "int"),
+            ("1+2*3", "7", "int"),
+            ("2*3%4", "2", "int"),
+            ("24/4/2", "3", "int"),
+            ("(1+2)*3", "9", "int"),
+            ("1 < 2 == 1", "1", "int"),
+            ("1 | 2 ^ 3 & 6", "1", "int"),
+            ("-x*2", "6", "int"),
+            ("!0 + ~0", "0", "int"),
+            ("0 && 1/0", "0", "int"),
+            ("1 || 1/0", "1", "int"),
+        ]);
+    }
+
+    #[test]
+    fn arithmetic_conversions() {
+        check(&[
+            // -3 converted to unsigned int is larger than 2
+            ("x < u", "0", "int"),
+            ("x < 2", "1", "int"),
+            ("x < (long)u", "1", "int"),
+            ("u - 3", "4294967295", "unsigned int"),
+            ("x + 1L", "-2", "long"),
+            ("u + 1L", "3", "long"),
+            ("x / 2", "-1", "int"),
+            ("x % 2", "-1", "int"),
+            ("7 / 2.0", "3.5", "double"),
+            ("c + 1", "66", "int"),
+            ("2147483648", "2147483648", "long"),
+        ]);
+    }
+
+    #[test]
+    fn pointers() {
+        check(&[
+            ("p", "0x1014", "int *"),
+            ("*p", "20", "int"),
+            ("*(p + 1)", "30", "int"),
+            ("p[2]", "40", "int"),
+            ("*(arr + 1)", "20", "int"),
+            ("p - 1", "0x1010", "int *"),
+            ("&arr[3] - p", "2", "long"),
+            ("p == &arr[1]", "1", "int"),
+            ("p > arr", "1", "int"),
+            ("(char *)p + 1", "0x1015", "char *"),
+            ("$pc", "0x401000", "void *"),
+            ("$rax * 2", "84", "long"),
+        ]);
+    }
+
+    #[test]
+    fn casts_members_and_literals() {
+        check(&[
+            ("(size_t)x", "18446744073709551613", "size_t"),
+            ("(x) - 1", "-4", "int"),
+            ("(char)321", "65", "char"),
+            ("(unsigned char)-1", "255", "unsigned char"),
+            ("(int)3.9", "3", "int"),
+            ("(double)x / 2", "-1.5", "double"),
+            ("((struct point *)0x1030)->y", "7", "int"),
+            ("pp->x", "5", "int"),
+            ("(*pp).y", "7", "int"),
+            ("pt.y", "7", "int"),
+            ("&pt.y", "0x1034", "int *"),
+            ("'\\n'", "10", "char"),
+            ("'\\''", "39", "char"),
+            ("'\\0'", "0", "char"),
+            ("c == 'A'", "1", "int"),
+            ("010", "8", "int"),
+            ("0x1F", "31", "int"),
+            ("0x10UL", "16", "unsigned long"),
+            ("0xffffffff", "4294967295", "unsigned int"),
+            ("4294967295", "4294967295", "long"),
+            ("1.5e1", "15", "double"),
+        ]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error("x / 0"), "Division by zero");
+        assert_eq!(error("x % (u - 2)"), "Division by zero");
+        assert_eq!(
+            error("pp.x"),
+            "The value of type `struct point *' is a pointer; use -> instead of ."
+        );
+        assert_eq!(error("pt.z"), "There is no member named z.");
+        assert_eq!(
+            error("x.y"),
+            "Attempt to extract a component of a value that is not a structure."
+        );
+        assert_eq!(
+            error("nothing + 1"),
+            "No symbol \"nothing\" in current context."
+        );
+        assert_eq!(error("(foo_t)x"), "A syntax error in expression, near `x'.");
+        assert_eq!(error("1 +"), "A syntax error in expression.");
+        assert_eq!(
+            error("1 + ) 2"),
+            "A syntax error in expression, near `) 2'."
+        );
+        assert_eq!(error("'ab'"), "A syntax error in expression, near `'ab''.");
+        assert_eq!(error("1.5 % 2"), "Integer only operation.");
+        assert_eq!(
+            error("*x"),
+            "Cannot access memory at address 0xfffffffffffffffd"
+        );
+        assert_eq!(error("$xyz"), "Invalid register `$xyz'.");
+        assert_eq!(
+            error("pt + 1"),
+            "Argument to arithmetic operation not a number or boolean."
+        );
+        assert_eq!(error("f(1)"), "Cannot call f here.");
+    }
+}
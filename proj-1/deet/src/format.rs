@@ -0,0 +1,308 @@
+//! Formatting of values read from the inferior, the way `print` shows them. Shared by the
+//! debugger's commands and by `Session`, which hands out values rather than printing them.
+
+use crate::dwarf_data::{DwarfData, Encoding, Type, TypeKind, Variant};
+use crate::inferior::Inferior;
+use crate::pretty_printers::PrettyPrinters;
+use std::convert::TryInto;
+
+/// Number of array elements `print` shows before eliding the rest.
+pub const MAX_ELEMENTS: usize = 200;
+
+/// Formats the bytes of a value of a base type.
+pub fn format_value(bytes: &[u8], encoding: Encoding) -> String {
+    let mut raw = [0u8; 8];
+    raw[..bytes.len().min(8)].copy_from_slice(&bytes[..bytes.len().min(8)]);
+    let unsigned = u64::from_le_bytes(raw);
+    let bits = 8 * bytes.len().min(8) as u32;
+    let signed = if bits == 0 || bits == 64 {
+        unsigned as i64
+    } else {
+        ((unsigned << (64 - bits)) as i64) >> (64 - bits)
+    };
+    match encoding {
+        Encoding::Float if bytes.len() == 4 => {
+            format_float(f32::from_le_bytes(bytes.try_into().unwrap()) as f64)
+        }
+        Encoding::Float if bytes.len() == 8 => {
+            format_float(f64::from_le_bytes(bytes.try_into().unwrap()))
+        }
+        Encoding::SignedChar | Encoding::UnsignedChar if bytes.len() == 1 => {
+            let value = if encoding == Encoding::UnsignedChar {
+                unsigned as i64
+            } else {
+                signed
+            };
+            format!("{} {}", value, quote_c_char(bytes[0]))
+        }
+        Encoding::Boolean if unsigned <= 1 => format!("{}", unsigned == 1),
+        Encoding::Unsigned | Encoding::UnsignedChar | Encoding::Boolean => {
+            format!("{}", unsigned)
+        }
+        _ => format!("{}", signed),
+    }
+}
+
+/// Formats a floating point number, in scientific notation if it is very large or small.
+pub fn format_float(value: f64) -> String {
+    let magnitude = value.abs();
+    if value == 0.0 || !value.is_finite() || (1e-5..1e16).contains(&magnitude) {
+        format!("{}", value)
+    } else {
+        format!("{:e}", value)
+    }
+}
+
+/// Quotes a character the way it would be written in C source.
+pub fn quote_c_char(byte: u8) -> String {
+    match byte {
+        b'\'' => "'\\''".to_string(),
+        b'"' => "'\"'".to_string(),
+        _ => {
+            let quoted = quote_c_string(&(byte as char).to_string());
+            format!("'{}'", &quoted[1..quoted.len() - 1])
+        }
+    }
+}
+
+/// Reads a NUL-terminated string of at most `max_len` bytes from the inferior.
+pub fn read_c_string(inf: &Inferior, addr: usize, max_len: usize) -> Result<String, nix::Error> {
+    let mut bytes = Vec::new();
+    while bytes.len() < max_len {
+        let chunk = inf.read_memory(addr + bytes.len(), 8)?;
+        match chunk.iter().position(|&b| b == 0) {
+            Some(end) => {
+                bytes.extend_from_slice(&chunk[..end]);
+                break;
+            }
+            None => bytes.extend_from_slice(&chunk),
+        }
+    }
+    bytes.truncate(max_len);
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// Quotes a string the way it would be written in C source.
+pub fn quote_c_string(string: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => {
+                quoted.push_str(&format!("\\{:03o}", c as u32))
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Formats the bytes of a value of the given type. Pointers are shown as addresses, followed
+/// by the string they point to if they are C strings; structs and arrays show their members
+/// and elements. Types that have a pretty-printer, such as Rust standard library types, are
+/// shown the way it shows them.
+pub fn format_typed_value(
+    debug_data: &DwarfData,
+    inferior: Option<&Inferior>,
+    printers: &PrettyPrinters,
+    bytes: &[u8],
+    entity_type: &Type,
+) -> String {
+    match printers.format(debug_data, inferior, bytes, entity_type) {
+        Some(value) => value,
+        None => format_members(debug_data, inferior, printers, bytes, entity_type),
+    }
+}
+
+/// Formats the bytes of a value as its type lays them out, without a pretty-printer for the
+/// value itself. Its members and elements still get theirs.
+pub fn format_members(
+    debug_data: &DwarfData,
+    inferior: Option<&Inferior>,
+    printers: &PrettyPrinters,
+    bytes: &[u8],
+    entity_type: &Type,
+) -> String {
+    match &entity_type.kind {
+        TypeKind::Base(encoding) => format_value(bytes, *encoding),
+        TypeKind::Pointer(pointee) => {
+            let pointer = u64::from_le_bytes(bytes[..].try_into().unwrap_or([0; 8])) as usize;
+            let is_string = match pointee.as_ref().map(|pointee| &pointee.kind) {
+                Some(TypeKind::Base(Encoding::SignedChar))
+                | Some(TypeKind::Base(Encoding::UnsignedChar)) => true,
+                _ => false,
+            };
+            match inferior {
+                Some(inf) if is_string && pointer != 0 => match read_c_string(inf, pointer, 200) {
+                    Ok(string) => format!("{:#x} {}", pointer, quote_c_string(&string)),
+                    Err(_) => format!("{:#x} <error: Cannot access memory>", pointer),
+                },
+                _ => format!("{:#x}", pointer),
+            }
+        }
+        TypeKind::Array(element) => {
+            if let TypeKind::Base(Encoding::SignedChar) | TypeKind::Base(Encoding::UnsignedChar) =
+                element.kind
+            {
+                let end = bytes
+                    .iter()
+                    .position(|&b| b == 0)
+                    .unwrap_or_else(|| bytes.len());
+                let string = String::from_utf8_lossy(&bytes[..end]);
+                return quote_c_string(&string);
+            }
+            if element.size == 0 {
+                return "{}".to_string();
+            }
+            let mut elements: Vec<String> = bytes
+                .chunks(element.size)
+                .take(MAX_ELEMENTS)
+                .map(|chunk| format_typed_value(debug_data, inferior, printers, chunk, element))
+                .collect();
+            if bytes.len() / element.size > MAX_ELEMENTS {
+                elements.push("...".to_string());
+            }
+            format!("{{{}}}", elements.join(", "))
+        }
+        TypeKind::Struct(members) => {
+            let members: Vec<String> = members
+                .iter()
+                .map(|member| {
+                    let value = match member
+                        .type_offset
+                        .and_then(|offset| debug_data.get_type(offset))
+                    {
+                        Some(ty) if member.offset + ty.size <= bytes.len() => format_typed_value(
+                            debug_data,
+                            inferior,
+                            printers,
+                            &bytes[member.offset..member.offset + ty.size],
+                            ty,
+                        ),
+                        _ => "?".to_string(),
+                    };
+                    format!("{} = {}", member.name, value)
+                })
+                .collect();
+            format!("{{{}}}", members.join(", "))
+        }
+        TypeKind::Enum(enumerators) => {
+            let value = format_value(bytes, Encoding::Signed);
+            match enumerators
+                .iter()
+                .find(|(_, number)| number.to_string() == value)
+            {
+                Some((name, _)) => name.clone(),
+                None => value,
+            }
+        }
+        TypeKind::Variants {
+            discriminant,
+            variants,
+        } => {
+            // Without a discriminant, the enum has a single variant
+            let discr_value = discriminant.as_ref().and_then(|discriminant| {
+                let ty = debug_data.get_type(discriminant.type_offset?)?;
+                let bytes = bytes.get(discriminant.offset..discriminant.offset + ty.size)?;
+                Some((read_unsigned(bytes), ty.size))
+            });
+            let variant = variants
+                .iter()
+                .find(|variant| match (variant.discr_value, discr_value) {
+                    (Some(expected), Some((value, size))) => {
+                        let mask = if size >= 8 {
+                            !0
+                        } else {
+                            (1u64 << (8 * size)) - 1
+                        };
+                        expected as u64 & mask == value
+                    }
+                    _ => false,
+                })
+                .or_else(|| {
+                    variants
+                        .iter()
+                        .find(|variant| variant.discr_value.is_none())
+                });
+            match variant {
+                Some(variant) => format_variant(debug_data, inferior, printers, bytes, variant),
+                None => "{...}".to_string(),
+            }
+        }
+        TypeKind::Unknown => "{...}".to_string(),
+    }
+}
+
+/// Formats the active variant of a Rust enum like Rust's `Debug` does: `None`, `Some(7)` or
+/// `Move {x = 1, y = 2}`.
+fn format_variant(
+    debug_data: &DwarfData,
+    inferior: Option<&Inferior>,
+    printers: &PrettyPrinters,
+    bytes: &[u8],
+    variant: &Variant,
+) -> String {
+    let name = &variant.member.name;
+    let ty = match variant
+        .member
+        .type_offset
+        .and_then(|offset| debug_data.get_type(offset))
+    {
+        Some(ty) => ty,
+        None => return name.clone(),
+    };
+    let fields = match &ty.kind {
+        TypeKind::Struct(fields) => fields,
+        _ => return name.clone(),
+    };
+    let offset = variant.member.offset;
+    // The fields of tuple variants are named __0, __1, ...
+    if fields.is_empty() {
+        name.clone()
+    } else if fields.iter().all(|field| field.name.starts_with("__")) {
+        let values: Vec<String> = fields
+            .iter()
+            .map(|field| {
+                match field
+                    .type_offset
+                    .and_then(|offset| debug_data.get_type(offset))
+                {
+                    Some(ty) if offset + field.offset + ty.size <= bytes.len() => {
+                        let start = offset + field.offset;
+                        format_typed_value(
+                            debug_data,
+                            inferior,
+                            printers,
+                            &bytes[start..start + ty.size],
+                            ty,
+                        )
+                    }
+                    _ => "?".to_string(),
+                }
+            })
+            .collect();
+        format!("{}({})", name, values.join(", "))
+    } else {
+        let end = (offset + ty.size).min(bytes.len());
+        let value = format_typed_value(
+            debug_data,
+            inferior,
+            printers,
+            &bytes[offset.min(end)..end],
+            ty,
+        );
+        format!("{} {}", name, value)
+    }
+}
+
+/// Reads a little-endian unsigned integer of up to 8 bytes.
+pub fn read_unsigned(bytes: &[u8]) -> u64 {
+    let mut raw = [0u8; 8];
+    let len = bytes.len().min(8);
+    raw[..len].copy_from_slice(&bytes[..len]);
+    u64::from_le_bytes(raw)
+}
@@ -9,19 +9,85 @@ use gimli;
 use gimli::{UnitOffset, UnitSectionOffset};
 use object::Object;
 use std::borrow;
+use std::rc::Rc;
 //use std::io::{BufWriter, Write};
-use crate::dwarf_data::{File, Function, Line, Location, Type, Variable};
+use crate::dwarf_data::{
+    self, Encoding, File, FrameBase, Function, Line, Location, Member, Type, TypeKind, Variable,
+    Variant,
+};
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt::Write;
 use std::{io, path};
 
-pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<Vec<File>, Error> {
+/// Returns the data of the named section from the first object that has a non-empty copy of it.
+/// A stripped executable and its separate debug file are merged this way: the executable keeps
+/// none of its `.debug_*` sections, so they are all read from the debug file.
+///
+/// Compressed sections (`SHF_COMPRESSED`, or GNU-style `.zdebug_*` sections starting with a
+/// "ZLIB" header) are decompressed by `object`'s `compression` feature.
+pub fn section_data<'data>(
+    objects: &[&object::File<'data>],
+    name: &str,
+) -> borrow::Cow<'data, [u8]> {
+    let zdebug_name = if name.starts_with(".debug_") {
+        Some(format!(".zdebug_{}", &name[".debug_".len()..]))
+    } else {
+        None
+    };
+    objects
+        .iter()
+        .filter_map(|object| {
+            object
+                .section_data_by_name(name)
+                .or_else(|| object.section_data_by_name(zdebug_name.as_ref()?))
+        })
+        .find(|data| !data.is_empty())
+        .unwrap_or(borrow::Cow::Borrowed(&[][..]))
+}
+
+/// Builds the addr2line context from the DWARF sections of the given objects.
+pub fn load_addr2line_context(
+    objects: &[&object::File],
+    endian: addr2line::gimli::RunTimeEndian,
+) -> Result<
+    addr2line::Context<addr2line::gimli::EndianRcSlice<addr2line::gimli::RunTimeEndian>>,
+    Error,
+> {
+    type RcReader = addr2line::gimli::EndianRcSlice<addr2line::gimli::RunTimeEndian>;
+    fn load<S: addr2line::gimli::Section<RcReader>>(
+        objects: &[&object::File],
+        endian: addr2line::gimli::RunTimeEndian,
+    ) -> S {
+        let data = section_data(objects, S::section_name());
+        S::from(addr2line::gimli::EndianRcSlice::new(
+            Rc::from(&*data),
+            endian,
+        ))
+    }
+    Ok(addr2line::Context::from_sections(
+        load(objects, endian),
+        load(objects, endian),
+        load(objects, endian),
+        load(objects, endian),
+        load(objects, endian),
+        load(objects, endian),
+        load(objects, endian),
+        load(objects, endian),
+        load(objects, endian),
+        addr2line::gimli::EndianRcSlice::new(Rc::from(&[][..]), endian),
+    )?)
+}
+
+/// Loads the files (compilation units) in the debugging information, along with every type,
+/// keyed by .debug_info offset.
+pub fn load_file(
+    objects: &[&object::File],
+    endian: gimli::RunTimeEndian,
+) -> Result<(Vec<File>, HashMap<usize, Type>), Error> {
     // Load a section and return as `Cow<[u8]>`.
     let load_section = |id: gimli::SectionId| -> Result<borrow::Cow<[u8]>, gimli::Error> {
-        Ok(object
-            .section_data_by_name(id.name())
-            .unwrap_or(borrow::Cow::Borrowed(&[][..])))
+        Ok(section_data(objects, id.name()))
     };
     // Load a supplementary section. We don't have a supplementary object file,
     // so always return an empty slice.
@@ -40,7 +106,7 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
     let dwarf = dwarf_cow.borrow(&borrow_section);
 
     // Define a mapping from type offsets to type structs
-    let mut offset_to_type: HashMap<usize, Type> = HashMap::new();
+    let offset_to_type = load_types(&dwarf)?;
 
     let mut compilation_units: Vec<File> = Vec::new();
 
@@ -48,6 +114,8 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
     let mut iter = dwarf.units();
     while let Some(header) = iter.next()? {
         let unit = dwarf.unit(header)?;
+        // The language of the unit, which says how its functions' linkage names are mangled
+        let mut language = None;
 
         // Iterate over the Debugging Information Entries (DIEs) in the unit.
         let mut depth = 0;
@@ -67,6 +135,16 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
                     } else {
                         "<unknown>".to_string()
                     };
+                    // rustc names units "<source file>/@/<codegen unit>"
+                    let name = match name.find("/@/") {
+                        Some(index) => name[..index].to_string(),
+                        None => name,
+                    };
+                    if let Some(gimli::AttributeValue::Language(lang)) =
+                        entry.attr_value(gimli::DW_AT_language)?
+                    {
+                        language = Some(addr2line::gimli::DwLang(lang.0));
+                    }
                     compilation_units.push(File {
                         name,
                         global_variables: Vec::new(),
@@ -74,35 +152,9 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
                         lines: Vec::new(),
                     });
                 }
-                gimli::DW_TAG_base_type => {
-                    let name = if let Ok(Some(attr)) = entry.attr(gimli::DW_AT_name) {
-                        if let Ok(DebugValue::Str(name)) = get_attr_value(&attr, &unit, &dwarf) {
-                            name
-                        } else {
-                            "<unknown>".to_string()
-                        }
-                    } else {
-                        "<unknown>".to_string()
-                    };
-                    let byte_size = if let Ok(Some(attr)) = entry.attr(gimli::DW_AT_byte_size) {
-                        if let Ok(DebugValue::Uint(byte_size)) =
-                            get_attr_value(&attr, &unit, &dwarf)
-                        {
-                            byte_size
-                        } else {
-                            // TODO: report error?
-                            0
-                        }
-                    } else {
-                        // TODO: report error?
-                        0
-                    };
-                    let type_offset = entry.offset().0;
-                    offset_to_type
-                        .insert(type_offset, Type::new(name, byte_size.try_into().unwrap()));
-                }
                 gimli::DW_TAG_subprogram => {
                     let mut func: Function = Default::default();
+                    let mut linkage_name = get_linkage_name(entry, &unit, &dwarf)?;
                     let mut attrs = entry.attrs();
                     while let Some(attr) = attrs.next()? {
                         let val = get_attr_value(&attr, &unit, &dwarf);
@@ -129,9 +181,55 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
                                     func.line_number = line_number.try_into().unwrap();
                                 }
                             }
+                            gimli::DW_AT_frame_base => {
+                                if let Some(frame_base) = get_frame_base(&attr, &unit) {
+                                    func.frame_base = frame_base;
+                                }
+                            }
+                            gimli::DW_AT_type => {
+                                if let Ok(DebugValue::Size(offset)) = val {
+                                    func.return_type = offset_to_type.get(&offset).cloned();
+                                }
+                            }
+                            // The definition of a C++ function declared in a class or
+                            // namespace refers to its declaration instead of repeating it
+                            gimli::DW_AT_specification | gimli::DW_AT_abstract_origin => {
+                                if let gimli::AttributeValue::UnitRef(offset) = attr.value() {
+                                    let declaration = unit.entry(offset)?;
+                                    let (name, return_type, _) =
+                                        get_name_and_type(&declaration, &unit, &dwarf)?;
+                                    if func.name.is_empty() {
+                                        func.name = name.unwrap_or_default();
+                                    }
+                                    if func.return_type.is_none() {
+                                        func.return_type = return_type
+                                            .and_then(|offset| offset_to_type.get(&offset))
+                                            .cloned();
+                                    }
+                                    let line_number = get_constant(
+                                        &declaration,
+                                        gimli::DW_AT_decl_line,
+                                        &unit,
+                                        &dwarf,
+                                    )?;
+                                    if func.line_number == 0 {
+                                        func.line_number = line_number.unwrap_or(0) as usize;
+                                    }
+                                    if linkage_name.is_none() {
+                                        linkage_name =
+                                            get_linkage_name(&declaration, &unit, &dwarf)?;
+                                    }
+                                }
+                            }
                             _ => {}
                         }
                     }
+                    // DW_AT_name leaves out the module or namespace, e.g. "add" for "ns::add"
+                    if let Some(name) = linkage_name
+                        .and_then(|linkage_name| dwarf_data::demangle(&linkage_name, language))
+                    {
+                        func.name = name;
+                    }
                     compilation_units.last_mut().unwrap().functions.push(func);
                 }
                 gimli::DW_TAG_formal_parameter | gimli::DW_TAG_variable => {
@@ -175,6 +273,7 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
                             entity_type: entity_type.unwrap(),
                             location: location.unwrap(),
                             line_number: line_number.try_into().unwrap(),
+                            is_parameter: entry.tag() == gimli::DW_TAG_formal_parameter,
                         };
                         if depth == 1 {
                             compilation_units
@@ -202,6 +301,15 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
 
         // Get line numbers
         if let Some(program) = unit.line_program.clone() {
+            // Relative paths (in DW_AT_name or the line program) are relative to the compilation
+            // directory. Compare absolute paths, since DWARF 5 line programs spell out the
+            // compilation directory while the unit's name may not.
+            let comp_dir = match unit.comp_dir {
+                Some(ref comp_dir) => {
+                    path::PathBuf::from(gimli::Reader::to_string_lossy(comp_dir)?.as_ref())
+                }
+                None => path::PathBuf::new(),
+            };
             // Iterate over the line program rows.
             let mut rows = program.rows();
             while let Some((header, row)) = rows.next_row()? {
@@ -221,9 +329,10 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
                     }
 
                     // Get the File
+                    let path = comp_dir.join(path);
                     let file = compilation_units
                         .iter_mut()
-                        .find(|f| f.name == path.as_os_str().to_str().unwrap());
+                        .find(|f| comp_dir.join(&f.name) == path);
 
                     // Determine line/column. DWARF line/column is never 0, so we use that
                     // but other applications may want to display this differently.
@@ -240,7 +349,417 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
             }
         }
     }
-    Ok(compilation_units)
+    Ok((compilation_units, offset_to_type))
+}
+
+/// A type DIE as read from the debugging information, before the types it refers to have been
+/// looked up.
+enum RawType {
+    Base {
+        name: String,
+        size: usize,
+        encoding: Encoding,
+    },
+    /// Rust names its reference and `Box` types; C pointer types have no name
+    Pointer {
+        name: Option<String>,
+        pointee: Option<usize>,
+        size: usize,
+    },
+    Qualified {
+        qualifier: &'static str,
+        target: Option<usize>,
+    },
+    Typedef {
+        name: String,
+        target: Option<usize>,
+    },
+    /// The element counts of each dimension are read from the DW_TAG_subrange_type children
+    Array {
+        element: Option<usize>,
+        counts: Vec<Option<usize>>,
+    },
+    /// Members, enumerators, template type parameters and the variants of Rust enums are read
+    /// from the children
+    Aggregate {
+        name: String,
+        size: usize,
+        members: Vec<Member>,
+        enumerators: Vec<(String, i64)>,
+        type_parameters: Vec<(String, usize)>,
+        discriminant: Option<Member>,
+        variants: Vec<Variant>,
+    },
+}
+
+/// Reads the name of a DIE and the offset of the type it refers to.
+fn get_name_and_type<R: Reader>(
+    entry: &gimli::DebuggingInformationEntry<R>,
+    unit: &gimli::Unit<R>,
+    dwarf: &gimli::Dwarf<R>,
+) -> Result<(Option<String>, Option<usize>, Option<usize>), Error> {
+    let mut name = None;
+    let mut target = None;
+    let mut size = None;
+    let mut attrs = entry.attrs();
+    while let Some(attr) = attrs.next()? {
+        match (attr.name(), get_attr_value(&attr, unit, dwarf)) {
+            (gimli::DW_AT_name, Ok(DebugValue::Str(attr_name))) => name = Some(attr_name),
+            (gimli::DW_AT_type, Ok(DebugValue::Size(offset))) => target = Some(offset),
+            (gimli::DW_AT_byte_size, Ok(DebugValue::Uint(byte_size))) => {
+                size = Some(byte_size.try_into().unwrap())
+            }
+            _ => {}
+        }
+    }
+    Ok((name, target, size))
+}
+
+/// Reads the mangled name of a function DIE (DW_AT_linkage_name, or DW_AT_MIPS_linkage_name
+/// from before DWARF 4).
+fn get_linkage_name<R: Reader>(
+    entry: &gimli::DebuggingInformationEntry<R>,
+    unit: &gimli::Unit<R>,
+    dwarf: &gimli::Dwarf<R>,
+) -> Result<Option<String>, Error> {
+    for name in &[gimli::DW_AT_linkage_name, gimli::DW_AT_MIPS_linkage_name] {
+        if let Some(attr) = entry.attr(*name)? {
+            if let Ok(DebugValue::Str(linkage_name)) = get_attr_value(&attr, unit, dwarf) {
+                return Ok(Some(linkage_name));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Reads a constant-valued attribute of a DIE, such as DW_AT_encoding or DW_AT_upper_bound.
+fn get_constant<R: Reader>(
+    entry: &gimli::DebuggingInformationEntry<R>,
+    name: gimli::DwAt,
+    unit: &gimli::Unit<R>,
+    dwarf: &gimli::Dwarf<R>,
+) -> Result<Option<i64>, Error> {
+    Ok(match entry.attr(name)? {
+        Some(attr) => match get_attr_value(&attr, unit, dwarf) {
+            Ok(DebugValue::Uint(value)) => Some(value as i64),
+            Ok(DebugValue::Int(value)) => Some(value),
+            _ => None,
+        },
+        None => None,
+    })
+}
+
+/// Collects every type in the debugging information, keyed by .debug_info offset. This is done
+/// before reading variables because a variable's type may be declared after it.
+fn load_types<R: Reader>(dwarf: &gimli::Dwarf<R>) -> Result<HashMap<usize, Type>, Error> {
+    let mut raw_types: HashMap<usize, RawType> = HashMap::new();
+    let mut iter = dwarf.units();
+    while let Some(header) = iter.next()? {
+        let unit = dwarf.unit(header)?;
+        let pointer_size = unit.encoding().address_size as usize;
+        let mut entries = unit.entries();
+        // Offsets of the enclosing DIEs, used to attach members, enumerators and array bounds
+        // to their type, and the names of those that are namespaces (or Rust modules)
+        let mut parents: Vec<usize> = Vec::new();
+        let mut namespaces: Vec<Option<String>> = Vec::new();
+        // The struct of each DW_TAG_variant_part, and the struct and discriminant value of each
+        // DW_TAG_variant in it
+        let mut variant_parts: HashMap<usize, usize> = HashMap::new();
+        let mut variants: HashMap<usize, (usize, Option<i64>)> = HashMap::new();
+        while let Some((delta_depth, entry)) = entries.next_dfs()? {
+            let offset = match entry.offset().to_unit_section_offset(&unit) {
+                UnitSectionOffset::DebugInfoOffset(goff) => goff.0,
+                UnitSectionOffset::DebugTypesOffset(goff) => goff.0,
+            };
+            // A delta of 1 means this entry is the previous one's first child
+            for _ in delta_depth..1 {
+                parents.pop();
+                namespaces.pop();
+            }
+            let parent = parents.last().cloned();
+            parents.push(offset);
+            namespaces.push(if entry.tag() == gimli::DW_TAG_namespace {
+                get_name_and_type(entry, &unit, dwarf)?.0
+            } else {
+                None
+            });
+            let raw_type = match entry.tag() {
+                gimli::DW_TAG_base_type => {
+                    let (name, _, size) = get_name_and_type(entry, &unit, dwarf)?;
+                    let encoding = match entry.attr_value(gimli::DW_AT_encoding)? {
+                        Some(gimli::AttributeValue::Encoding(encoding)) => encoding,
+                        _ => gimli::DW_ATE_signed,
+                    };
+                    RawType::Base {
+                        name: name.unwrap_or_else(|| "<unknown>".to_string()),
+                        size: size.unwrap_or(0),
+                        encoding: match encoding {
+                            gimli::DW_ATE_float => Encoding::Float,
+                            gimli::DW_ATE_boolean => Encoding::Boolean,
+                            gimli::DW_ATE_signed_char => Encoding::SignedChar,
+                            gimli::DW_ATE_unsigned_char => Encoding::UnsignedChar,
+                            gimli::DW_ATE_unsigned | gimli::DW_ATE_UTF => Encoding::Unsigned,
+                            _ => Encoding::Signed,
+                        },
+                    }
+                }
+                gimli::DW_TAG_pointer_type => {
+                    let (name, pointee, size) = get_name_and_type(entry, &unit, dwarf)?;
+                    RawType::Pointer {
+                        name,
+                        pointee,
+                        size: size.unwrap_or(pointer_size),
+                    }
+                }
+                gimli::DW_TAG_const_type | gimli::DW_TAG_volatile_type => {
+                    let (_, target, _) = get_name_and_type(entry, &unit, dwarf)?;
+                    let qualifier = if entry.tag() == gimli::DW_TAG_const_type {
+                        "const"
+                    } else {
+                        "volatile"
+                    };
+                    RawType::Qualified { qualifier, target }
+                }
+                gimli::DW_TAG_typedef => {
+                    let (name, target, _) = get_name_and_type(entry, &unit, dwarf)?;
+                    RawType::Typedef {
+                        name: name.unwrap_or_else(|| "<unknown>".to_string()),
+                        target,
+                    }
+                }
+                gimli::DW_TAG_array_type => {
+                    let (_, element, _) = get_name_and_type(entry, &unit, dwarf)?;
+                    RawType::Array {
+                        element,
+                        counts: Vec::new(),
+                    }
+                }
+                gimli::DW_TAG_structure_type
+                | gimli::DW_TAG_union_type
+                | gimli::DW_TAG_enumeration_type => {
+                    let (name, _, size) = get_name_and_type(entry, &unit, dwarf)?;
+                    let keyword = match entry.tag() {
+                        gimli::DW_TAG_structure_type => "struct",
+                        gimli::DW_TAG_union_type => "union",
+                        _ => "enum",
+                    };
+                    let scope: Vec<&str> = namespaces
+                        .iter()
+                        .filter_map(|name| name.as_deref())
+                        .collect();
+                    let name = match name {
+                        Some(name) if !scope.is_empty() => {
+                            format!("{}::{}", scope.join("::"), name)
+                        }
+                        Some(name) => name,
+                        None => "{...}".to_string(),
+                    };
+                    RawType::Aggregate {
+                        name: format!("{} {}", keyword, name),
+                        size: size.unwrap_or(0),
+                        members: Vec::new(),
+                        enumerators: Vec::new(),
+                        type_parameters: Vec::new(),
+                        discriminant: None,
+                        variants: Vec::new(),
+                    }
+                }
+                gimli::DW_TAG_subrange_type => {
+                    let count = match get_constant(entry, gimli::DW_AT_count, &unit, dwarf)? {
+                        Some(count) => Some(count as usize),
+                        None => get_constant(entry, gimli::DW_AT_upper_bound, &unit, dwarf)?
+                            .map(|upper_bound| (upper_bound + 1) as usize),
+                    };
+                    if let Some(RawType::Array { counts, .. }) =
+                        parent.and_then(|parent| raw_types.get_mut(&parent))
+                    {
+                        counts.push(count);
+                    }
+                    continue;
+                }
+                gimli::DW_TAG_member => {
+                    let (name, type_offset, _) = get_name_and_type(entry, &unit, dwarf)?;
+                    let offset =
+                        get_constant(entry, gimli::DW_AT_data_member_location, &unit, dwarf)?;
+                    let member = Member {
+                        name: name.unwrap_or_default(),
+                        offset: offset.unwrap_or(0) as usize,
+                        type_offset,
+                    };
+                    let parent = match parent {
+                        Some(parent) => parent,
+                        None => continue,
+                    };
+                    // A member of a variant part is the discriminant; a member of a variant
+                    // holds that variant's fields
+                    if let Some(aggregate) = variant_parts.get(&parent) {
+                        if let Some(RawType::Aggregate { discriminant, .. }) =
+                            raw_types.get_mut(aggregate)
+                        {
+                            *discriminant = Some(member);
+                        }
+                    } else if let Some((aggregate, discr_value)) = variants.get(&parent) {
+                        if let Some(RawType::Aggregate { variants, .. }) =
+                            raw_types.get_mut(aggregate)
+                        {
+                            variants.push(Variant {
+                                discr_value: *discr_value,
+                                member,
+                            });
+                        }
+                    } else if let Some(RawType::Aggregate { members, .. }) =
+                        raw_types.get_mut(&parent)
+                    {
+                        members.push(member);
+                    }
+                    continue;
+                }
+                gimli::DW_TAG_variant_part => {
+                    if let Some(parent) = parent {
+                        variant_parts.insert(offset, parent);
+                    }
+                    continue;
+                }
+                gimli::DW_TAG_variant => {
+                    if let Some(aggregate) = parent.and_then(|parent| variant_parts.get(&parent)) {
+                        let discr_value =
+                            get_constant(entry, gimli::DW_AT_discr_value, &unit, dwarf)?;
+                        variants.insert(offset, (*aggregate, discr_value));
+                    }
+                    continue;
+                }
+                gimli::DW_TAG_template_type_parameter => {
+                    let (name, type_offset, _) = get_name_and_type(entry, &unit, dwarf)?;
+                    if let Some(RawType::Aggregate {
+                        type_parameters, ..
+                    }) = parent.and_then(|parent| raw_types.get_mut(&parent))
+                    {
+                        if let Some(type_offset) = type_offset {
+                            type_parameters.push((name.unwrap_or_default(), type_offset));
+                        }
+                    }
+                    continue;
+                }
+                gimli::DW_TAG_enumerator => {
+                    let (name, _, _) = get_name_and_type(entry, &unit, dwarf)?;
+                    let value = get_constant(entry, gimli::DW_AT_const_value, &unit, dwarf)?;
+                    if let Some(RawType::Aggregate { enumerators, .. }) =
+                        parent.and_then(|parent| raw_types.get_mut(&parent))
+                    {
+                        enumerators.push((name.unwrap_or_default(), value.unwrap_or(0)));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            raw_types.insert(offset, raw_type);
+        }
+    }
+
+    fn resolve(
+        offset: Option<usize>,
+        raw_types: &HashMap<usize, RawType>,
+        depth: usize,
+    ) -> Option<Type> {
+        // "void" has no type DIE; give up on anything that refers to itself
+        let offset = match offset {
+            Some(offset) if depth < 16 => offset,
+            _ => return None,
+        };
+        Some(match raw_types.get(&offset)? {
+            RawType::Base {
+                name,
+                size,
+                encoding,
+            } => Type::with_kind(name.clone(), *size, TypeKind::Base(*encoding)),
+            RawType::Pointer {
+                name,
+                pointee,
+                size,
+            } => {
+                let pointee = resolve(*pointee, raw_types, depth + 1);
+                let name = match (name, &pointee) {
+                    (Some(name), _) => name.clone(),
+                    (None, Some(pointee)) if pointee.name.ends_with('*') => {
+                        format!("{}*", pointee.name)
+                    }
+                    (None, Some(pointee)) => format!("{} *", pointee.name),
+                    (None, None) => "void *".to_string(),
+                };
+                Type::with_kind(name, *size, TypeKind::Pointer(pointee.map(Box::new)))
+            }
+            RawType::Qualified { qualifier, target } => {
+                match resolve(*target, raw_types, depth + 1) {
+                    Some(target) if target.name.ends_with('*') => Type::with_kind(
+                        format!("{} {}", target.name, qualifier),
+                        target.size,
+                        target.kind,
+                    ),
+                    Some(target) => Type::with_kind(
+                        format!("{} {}", qualifier, target.name),
+                        target.size,
+                        target.kind,
+                    ),
+                    None => Type::new(format!("{} void", qualifier), 0),
+                }
+            }
+            RawType::Typedef { name, target } => match resolve(*target, raw_types, depth + 1) {
+                Some(target) => Type::with_kind(name.clone(), target.size, target.kind),
+                None => Type::new(name.clone(), 0),
+            },
+            RawType::Array { element, counts } => {
+                let mut array = resolve(*element, raw_types, depth + 1)?;
+                // int a[2][3] is an array of 2 arrays of 3 ints
+                let mut suffix = String::new();
+                for count in counts.iter().rev() {
+                    let dimension = match count {
+                        Some(count) => format!("[{}]", count),
+                        None => "[]".to_string(),
+                    };
+                    suffix = format!("{}{}", dimension, suffix);
+                    let element_name = array.name.clone();
+                    let base_name = match element_name.find(" [") {
+                        Some(index) => element_name[..index].to_string(),
+                        None => element_name,
+                    };
+                    array = Type::with_kind(
+                        format!("{} {}", base_name, suffix),
+                        array.size * count.unwrap_or(0),
+                        TypeKind::Array(Box::new(array)),
+                    );
+                }
+                array
+            }
+            RawType::Aggregate {
+                name,
+                size,
+                members,
+                enumerators,
+                type_parameters,
+                discriminant,
+                variants,
+            } => {
+                let kind = if name.starts_with("enum ") {
+                    TypeKind::Enum(enumerators.clone())
+                } else if !variants.is_empty() {
+                    TypeKind::Variants {
+                        discriminant: discriminant.clone(),
+                        variants: variants.clone(),
+                    }
+                } else {
+                    TypeKind::Struct(members.clone())
+                };
+                let mut aggregate = Type::with_kind(name.clone(), *size, kind);
+                aggregate.type_parameters = type_parameters.clone();
+                aggregate
+            }
+        })
+    }
+
+    Ok(raw_types
+        .keys()
+        .filter_map(|offset| Some((*offset, resolve(Some(*offset), &raw_types, 0)?)))
+        .collect())
 }
 
 #[derive(Debug, Clone)]
@@ -312,6 +831,25 @@ fn get_location<R: Reader>(attr: &gimli::Attribute<R>, unit: &gimli::Unit<R>) ->
     None
 }
 
+fn get_frame_base<R: Reader>(
+    attr: &gimli::Attribute<R>,
+    unit: &gimli::Unit<R>,
+) -> Option<FrameBase> {
+    if let gimli::AttributeValue::Exprloc(ref data) = attr.value() {
+        let mut pc = data.0.clone();
+        match gimli::Operation::parse(&mut pc, unit.encoding()) {
+            // gcc uses the canonical frame address
+            Ok(gimli::Operation::CallFrameCFA) => return Some(FrameBase::Cfa),
+            // clang uses DW_OP_reg6 (%rbp)
+            Ok(gimli::Operation::Register { register }) if register.0 == 6 => {
+                return Some(FrameBase::Rbp)
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
 // based on dwarf_dump.rs
 fn get_attr_value<R: Reader>(
     attr: &gimli::Attribute<R>,
@@ -327,16 +865,10 @@ fn get_attr_value<R: Reader>(
             dump_exprloc(w, unit.encoding(), data)?;
             Ok(DebugValue::Str(w.to_string()))
         }
-        gimli::AttributeValue::UnitRef(offset) => {
-            match offset.to_unit_section_offset(unit) {
-                UnitSectionOffset::DebugInfoOffset(goff) => {
-                    Ok(DebugValue::Size(goff.0))
-                }
-                UnitSectionOffset::DebugTypesOffset(goff) => {
-                    Ok(DebugValue::Size(goff.0))
-                }
-            }
-        }
+        gimli::AttributeValue::UnitRef(offset) => match offset.to_unit_section_offset(unit) {
+            UnitSectionOffset::DebugInfoOffset(goff) => Ok(DebugValue::Size(goff.0)),
+            UnitSectionOffset::DebugTypesOffset(goff) => Ok(DebugValue::Size(goff.0)),
+        },
         gimli::AttributeValue::DebugStrRef(offset) => {
             if let Ok(s) = dwarf.debug_str.get_str(offset) {
                 Ok(DebugValue::Str(format!("{}", s.to_string_lossy()?)))
@@ -344,9 +876,25 @@ fn get_attr_value<R: Reader>(
                 Ok(DebugValue::Str(format!("<.debug_str+0x{:08x}>", offset.0)))
             }
         }
+        // DWARF 5 string forms: DW_FORM_line_strp, DW_FORM_strx{,1,2,3,4} and DW_FORM_strp_sup
+        gimli::AttributeValue::DebugLineStrRef(_)
+        | gimli::AttributeValue::DebugStrOffsetsIndex(_)
+        | gimli::AttributeValue::DebugStrRefSup(_) => {
+            let s = dwarf.attr_string(unit, value)?;
+            Ok(DebugValue::Str(format!("{}", s.to_string_lossy()?)))
+        }
         gimli::AttributeValue::Sdata(data) => Ok(DebugValue::Int(data)),
         gimli::AttributeValue::Addr(data) => Ok(DebugValue::Uint(data)),
+        // DW_FORM_addrx{,1,2,3,4}: an index into .debug_addr
+        gimli::AttributeValue::DebugAddrIndex(index) => {
+            Ok(DebugValue::Uint(dwarf.address(unit, index)?))
+        }
         gimli::AttributeValue::Udata(data) => Ok(DebugValue::Uint(data)),
+        gimli::AttributeValue::Data1(data) => Ok(DebugValue::Uint(data.into())),
+        gimli::AttributeValue::Data2(data) => Ok(DebugValue::Uint(data.into())),
+        gimli::AttributeValue::Data4(data) => Ok(DebugValue::Uint(data.into())),
+        gimli::AttributeValue::Data8(data) => Ok(DebugValue::Uint(data)),
+        gimli::AttributeValue::Flag(flag) => Ok(DebugValue::Uint(flag as u64)),
 
         gimli::AttributeValue::String(s) => {
             Ok(DebugValue::Str(format!("{}", s.to_string_lossy()?)))
@@ -356,9 +904,7 @@ fn get_attr_value<R: Reader>(
             dump_file_index(w, value, unit, dwarf)?;
             Ok(DebugValue::Str(w.to_string()))
         }
-        _ => {
-            Ok(DebugValue::NoVal)
-        }
+        _ => Ok(DebugValue::NoVal),
     }
 }
 
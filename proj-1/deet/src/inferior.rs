@@ -1,12 +1,18 @@
-use crate::dwarf_data::DwarfData;
+use crate::dwarf_data::{DwarfData, Encoding, FrameBase, Line, Location, Type, TypeKind, Variable};
+use crate::record::{self, Recorder, Step};
+use nix::errno::Errno;
 use nix::sys::ptrace;
 use nix::sys::signal;
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
 use nix::unistd::Pid;
+use std::cell::Cell;
 use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs::{File, OpenOptions};
 use std::mem::size_of;
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::os::unix::process::CommandExt;
-use std::process::Child;
+use std::path::Path;
 use std::process::Command;
 
 pub enum Status {
@@ -20,6 +26,46 @@ pub enum Status {
     /// Indicates the inferior exited due to a signal. Contains the signal that killed the
     /// process.
     Signaled(signal::Signal),
+
+    /// Indicates inferior stopped on entry to (`true`) or return from (`false`) a system call,
+    /// which only happens while system call stops are enabled. Contains the system call number.
+    Syscall(bool, usize),
+
+    /// Indicates inferior stopped at a ptrace event.
+    Event(Event),
+}
+
+/// Something the inferior did that ptrace stops it for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    /// The process forked a child with this pid. The child is not debugged.
+    Fork(Pid),
+    Vfork(Pid),
+    /// The process replaced its program with another one. Breakpoints in the old program are
+    /// gone.
+    Exec,
+    /// The process is about to exit. Contains the wait status it will exit with.
+    Exit(i32),
+}
+
+/// Signals that programs routinely get, which are passed on without stopping unless caught.
+pub const QUIET_SIGNALS: &[signal::Signal] = &[
+    signal::SIGCHLD,
+    signal::SIGWINCH,
+    signal::SIGURG,
+    signal::SIGALRM,
+    signal::SIGPROF,
+];
+
+/// The ptrace options every inferior is traced with. PTRACE_O_TRACESYSGOOD sets bit 7 of the
+/// signal of system call stops, so that they can't be mistaken for breakpoint traps. Forked
+/// children are traced just long enough to take our breakpoints out of them.
+fn trace_options() -> ptrace::Options {
+    ptrace::Options::PTRACE_O_TRACESYSGOOD
+        | ptrace::Options::PTRACE_O_TRACEFORK
+        | ptrace::Options::PTRACE_O_TRACEVFORK
+        | ptrace::Options::PTRACE_O_TRACEEXEC
+        | ptrace::Options::PTRACE_O_TRACEEXIT
 }
 
 /// This function calls ptrace with PTRACE_TRACEME to enable debugging on a process. You should use
@@ -31,158 +77,1397 @@ fn child_traceme() -> Result<(), std::io::Error> {
     )))
 }
 
+/// This function makes `tty` the controlling terminal of the child process, in a new session of
+/// its own, so that the terminal's ctrl+c and job control go to the inferior rather than deet.
+fn child_take_terminal(tty: RawFd) -> Result<(), std::io::Error> {
+    unsafe {
+        if libc::setsid() < 0 || libc::ioctl(tty, libc::TIOCSCTTY, 0) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Where one of the inferior's standard streams goes, as given to `run` with `<`, `>` or `>>`.
+#[derive(Clone)]
+pub enum Redirection {
+    Read(String),
+    Write(String),
+    Append(String),
+    /// The same place as stdout (`2>&1`)
+    Stdout,
+}
+
+/// How the inferior's process is started, apart from its arguments.
+#[derive(Clone, Default)]
+pub struct Launch {
+    /// The inferior's whole environment, or None to give it deet's
+    pub environment: Option<Vec<(String, String)>>,
+    /// Directory to start the inferior in, or None for deet's
+    pub cwd: Option<String>,
+    /// Terminal the inferior's standard streams are connected to (`tty`), or None for deet's
+    pub tty: Option<String>,
+    /// Redirections of stdin, stdout and stderr, which take precedence over `tty`
+    pub redirections: [Option<Redirection>; 3],
+    /// Trace the inferior with PTRACE_SEIZE rather than PTRACE_TRACEME, so that `interrupt` can
+    /// stop it while it runs
+    pub interruptible: bool,
+}
+
+impl Launch {
+    /// Opens `path` the way `open` says, relative to the inferior's working directory.
+    fn open(&self, path: &str, options: &mut OpenOptions) -> Result<File, String> {
+        let full_path = match &self.cwd {
+            Some(cwd) => Path::new(cwd).join(path),
+            None => Path::new(path).to_path_buf(),
+        };
+        options
+            .open(full_path)
+            .map_err(|err| format!("{}: {}", path, err))
+    }
+
+    /// Opens the files for the inferior's stdin, stdout and stderr. Streams that are neither
+    /// redirected nor sent to a terminal are None, and are shared with deet.
+    fn open_streams(&self, tty: Option<&File>) -> Result<Vec<Option<File>>, String> {
+        let mut streams: Vec<Option<File>> = Vec::new();
+        for redirection in &self.redirections {
+            let file = match redirection {
+                Some(Redirection::Read(path)) => {
+                    Some(self.open(path, OpenOptions::new().read(true))?)
+                }
+                Some(Redirection::Write(path)) => Some(self.open(
+                    path,
+                    OpenOptions::new().write(true).create(true).truncate(true),
+                )?),
+                Some(Redirection::Append(path)) => {
+                    Some(self.open(path, OpenOptions::new().append(true).create(true))?)
+                }
+                Some(Redirection::Stdout) => match streams.get(1) {
+                    Some(Some(stdout)) => Some(stdout.try_clone().map_err(|err| err.to_string())?),
+                    _ => None,
+                },
+                None => match tty {
+                    Some(tty) => Some(tty.try_clone().map_err(|err| err.to_string())?),
+                    None => None,
+                },
+            };
+            streams.push(file);
+        }
+        Ok(streams)
+    }
+}
+
 /// This function is used to wirte memory in the breakpoint command
 fn align_addr_to_word(addr: usize) -> usize {
     addr & (-(size_of::<usize>() as isize) as usize)
 }
 
+/// Writes a byte in the memory of process `pid` and returns the byte that was there.
+fn write_byte_in(pid: Pid, addr: usize, val: u8) -> Result<u8, nix::Error> {
+    let aligned_addr = align_addr_to_word(addr);
+    let byte_offset = addr - aligned_addr;
+    let word = ptrace::read(pid, aligned_addr as ptrace::AddressType)? as u64;
+    let orig_byte = (word >> 8 * byte_offset) & 0xff;
+    let masked_word = word & !(0xff << 8 * byte_offset);
+    let updated_word = masked_word | ((val as u64) << 8 * byte_offset);
+    ptrace::write(
+        pid,
+        aligned_addr as ptrace::AddressType,
+        updated_word as *mut std::ffi::c_void,
+    )?;
+    Ok(orig_byte as u8)
+}
+
 #[derive(Clone)]
 struct Breakpoint {
     addr: usize,
     orig_byte: u8,
+    /// Set while the user has a breakpoint at this address.
+    user: bool,
+    /// Set while deet needs a one-shot breakpoint here for its own purposes (e.g. `finish`).
+    /// These are removed the next time the inferior stops.
+    internal: bool,
+}
+
+/// A frame of the inferior's call stack. Functions inlined into their caller get a frame of their
+/// own, which shares its registers with the frame it was inlined into.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Address of the next instruction to execute in this frame. For callers of the innermost
+    /// frame this is the return address.
+    pub pc: usize,
+    /// The canonical frame address: the value of %rsp before the call into this frame.
+    pub cfa: usize,
+    /// The value of %rbp for this frame once its prologue has run.
+    pub base_ptr: usize,
+    /// The address this frame will return to.
+    pub return_addr: usize,
+    /// The value of %rbp to restore when this frame returns.
+    pub caller_base_ptr: usize,
+    pub function: Option<String>,
+    pub line: Option<Line>,
+    /// True if this frame is for a function that was inlined into the next frame.
+    pub inlined: bool,
+}
+
+impl Frame {
+    /// Returns the address of a variable as seen from this frame.
+    pub fn variable_address(&self, var: &Variable, frame_base: FrameBase) -> usize {
+        match var.location {
+            Location::Address(addr) => addr,
+            Location::FramePointerOffset(offset) => {
+                let base = match frame_base {
+                    FrameBase::Cfa => self.cfa,
+                    FrameBase::Rbp => self.base_ptr,
+                };
+                (base as isize + offset) as usize
+            }
+        }
+    }
+}
+
+/// An argument of a function called in the inferior, by the register class it is passed in.
+pub enum CallArgument {
+    Integer(u64),
+    /// The bytes of a float or double, passed in an %xmm register
+    Float(Vec<u8>),
+}
+
+/// How a function called in the inferior ended.
+pub enum CallOutcome {
+    /// The function returned. Holds the registers at that point and, for functions that return
+    /// their value in memory, the bytes of that value.
+    Returned {
+        regs: libc::user_regs_struct,
+        fpregs: libc::user_fpregs_struct,
+        memory: Vec<u8>,
+    },
+    /// Something else stopped the inferior first, such as a breakpoint or a signal.
+    Interrupted(Status),
+}
+
+/// Where a function returns a value of some type, following the System V AMD64 calling
+/// convention.
+#[derive(Debug, PartialEq)]
+pub enum ReturnLocation {
+    /// In memory provided by the caller, whose address is passed in %rdi and returned in %rax
+    Memory,
+    /// In registers, one per eightbyte of the value: INTEGER eightbytes in %rax and then %rdx,
+    /// SSE ones in %xmm0 and then %xmm1
+    Registers(Vec<EightbyteClass>),
+    /// In %st0 (long double), which deet does not read
+    X87,
+}
+
+/// The class of an eightbyte of a value returned in registers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EightbyteClass {
+    Integer,
+    Sse,
+}
+
+impl ReturnLocation {
+    /// Classifies a return type the way the calling convention does: values over 16 bytes and
+    /// values with misaligned members go in memory; otherwise each eightbyte is SSE if it holds
+    /// only floats and doubles, and INTEGER if it holds anything else.
+    pub fn of(debug_data: &DwarfData, ty: &Type) -> ReturnLocation {
+        if ty.size > 16 {
+            return ReturnLocation::Memory;
+        }
+        let mut classes = vec![None; if ty.size > 8 { 2 } else { 1 }];
+        match classify(debug_data, ty, 0, &mut classes) {
+            Ok(()) => ReturnLocation::Registers(
+                classes
+                    .into_iter()
+                    .map(|class| class.unwrap_or(EightbyteClass::Integer))
+                    .collect(),
+            ),
+            Err(location) => location,
+        }
+    }
+
+    /// Assembles a value of `size` bytes returned in registers from the registers it is in.
+    pub fn read_registers(
+        classes: &[EightbyteClass],
+        size: usize,
+        regs: &libc::user_regs_struct,
+        fpregs: &libc::user_fpregs_struct,
+    ) -> Vec<u8> {
+        let integers = [regs.rax, regs.rdx];
+        let mut integers = integers.iter();
+        let mut xmm = 0;
+        let mut bytes = Vec::new();
+        for class in classes {
+            match class {
+                EightbyteClass::Integer => {
+                    let value = integers.next().copied().unwrap_or_default();
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                EightbyteClass::Sse => {
+                    // The low eightbyte of %xmm0 or %xmm1
+                    for word in &fpregs.xmm_space[4 * xmm..4 * xmm + 2] {
+                        bytes.extend_from_slice(&word.to_le_bytes());
+                    }
+                    xmm += 1;
+                }
+            }
+        }
+        bytes.truncate(size);
+        bytes
+    }
+}
+
+/// Merges the classes of the scalars of a value of type `ty` at `offset` into `classes`, one per
+/// eightbyte. Fails with where the value goes instead if it can't go in general purpose or SSE
+/// registers.
+fn classify(
+    debug_data: &DwarfData,
+    ty: &Type,
+    offset: usize,
+    classes: &mut [Option<EightbyteClass>],
+) -> Result<(), ReturnLocation> {
+    let class = match &ty.kind {
+        TypeKind::Base(Encoding::Float) if ty.size > 8 => return Err(ReturnLocation::X87),
+        TypeKind::Base(Encoding::Float) => EightbyteClass::Sse,
+        TypeKind::Array(element) if element.size > 0 => {
+            for index in 0..ty.size / element.size {
+                classify(debug_data, element, offset + index * element.size, classes)?;
+            }
+            return Ok(());
+        }
+        TypeKind::Struct(members) => {
+            for member in members {
+                let member_type = member
+                    .type_offset
+                    .and_then(|type_offset| debug_data.get_type(type_offset));
+                match member_type {
+                    Some(member_type) => {
+                        classify(debug_data, member_type, offset + member.offset, classes)?
+                    }
+                    None => return Err(ReturnLocation::Memory),
+                }
+            }
+            return Ok(());
+        }
+        _ => EightbyteClass::Integer,
+    };
+    if ty.size == 0 {
+        return Ok(());
+    }
+    if let TypeKind::Base(_) = ty.kind {
+        // The sizes of base types are powers of two
+        if offset & (ty.size - 1) != 0 {
+            // A misaligned member of a packed struct
+            return Err(ReturnLocation::Memory);
+        }
+    }
+    let last = (offset + ty.size - 1) / 8;
+    for eightbyte in classes.iter_mut().take(last + 1).skip(offset / 8) {
+        *eightbyte = match (*eightbyte, class) {
+            (Some(EightbyteClass::Integer), _) => Some(EightbyteClass::Integer),
+            _ => Some(class),
+        };
+    }
+    Ok(())
+}
+
+/// Longest possible x86-64 instruction.
+const MAX_INSTRUCTION_LENGTH: usize = 15;
+
+/// Bytes below %rsp that a function may use without moving %rsp, which a call must leave alone.
+const RED_ZONE: usize = 128;
+
+const INTEGER_ARGUMENT_REGISTERS: usize = 6;
+const FLOAT_ARGUMENT_REGISTERS: usize = 8;
+
+/// PTRACE_GET_SYSCALL_INFO (Linux 5.3), which libc 0.2.68 doesn't have, and the value of the
+/// `op` field that starts the struct it fills in for a stop on entry to a system call.
+const PTRACE_GET_SYSCALL_INFO: libc::c_uint = 0x420e;
+const PTRACE_SYSCALL_INFO_ENTRY: u8 = 1;
+
+/// How far a function's `push %rbp; mov %rsp,%rbp` prologue has run at some address.
+enum PrologueState {
+    /// Nothing has been pushed yet, or it has been popped again; the return address is at %rsp.
+    Entry,
+    /// %rbp has been pushed but not yet updated; the return address is at %rsp + 8.
+    RbpPushed,
+    /// %rbp points at the saved %rbp, followed by the return address.
+    FrameSetUp,
 }
 
 pub struct Inferior {
-    child: Child,
+    /// The traced process. Only the first inferior is deet's own child; checkpoints are forked
+    /// from it and only traced.
+    pid: Pid,
     breakpoints: HashMap<usize, Breakpoint>,
+    /// Set if the process ended during a function call, which only has `&self`
+    exited: Cell<bool>,
+    /// The instruction log, while recording
+    recorder: Option<Recorder>,
+    /// The signal the inferior last stopped for, which it is given when it continues
+    pending_signal: Option<signal::Signal>,
+    /// Set to stop at every system call (`catch syscall`)
+    syscall_stops: bool,
+    /// Set to keep tracing forked children rather than letting them go
+    follow_forks: bool,
+}
+
+/// Waits for any of our traced processes to change state, for tracing several at once.
+pub fn wait_any() -> Result<WaitStatus, nix::Error> {
+    waitpid(Pid::from_raw(-1), Some(WaitPidFlag::__WALL))
 }
 
 impl Inferior {
     /// This function can wirte a byte in the memory of the inferior process
-    fn write_byte(&mut self, addr: usize, val: u8) -> Result<u8, nix::Error> {
-        let aligned_addr = align_addr_to_word(addr);
-        let byte_offset = addr - aligned_addr;
-        let word = ptrace::read(self.pid(), aligned_addr as ptrace::AddressType)? as u64;
-        let orig_byte = (word >> 8 * byte_offset) & 0xff;
-        let masked_word = word & !(0xff << 8 * byte_offset);
-        let updated_word = masked_word | ((val as u64) << 8 * byte_offset);
-        ptrace::write(
-            self.pid(),
-            aligned_addr as ptrace::AddressType,
-            updated_word as *mut std::ffi::c_void,
-        )?;
-        Ok(orig_byte as u8)
+    fn write_byte(&self, addr: usize, val: u8) -> Result<u8, nix::Error> {
+        write_byte_in(self.pid(), addr, val)
     }
 
-    /// Attempts to start a new inferior process. Returns Some(Inferior) if successful, or None if
-    /// an error is encountered.
-    pub fn new(target: &str, args: &Vec<String>, breakpoints: &Vec<usize>) -> Option<Inferior> {
+    /// Attempts to start a new inferior process. Returns the Inferior if successful, or a
+    /// message saying what went wrong.
+    pub fn new(
+        target: &str,
+        args: &Vec<String>,
+        breakpoints: &Vec<usize>,
+        launch: &Launch,
+    ) -> Result<Inferior, String> {
         let mut binding = Command::new(target);
         let cmd = binding.args(args);
+        if let Some(environment) = &launch.environment {
+            cmd.env_clear();
+            cmd.envs(environment.iter().map(|(name, value)| (name, value)));
+        }
+        if let Some(cwd) = &launch.cwd {
+            cmd.current_dir(cwd);
+        }
+        let tty = match &launch.tty {
+            Some(path) => Some(
+                OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(path)
+                    .map_err(|err| format!("{}: {}", path, err))?,
+            ),
+            None => None,
+        };
+        let mut streams = launch.open_streams(tty.as_ref())?.into_iter();
+        if let Some(stdin) = streams.next().unwrap() {
+            cmd.stdin(stdin);
+        }
+        if let Some(stdout) = streams.next().unwrap() {
+            cmd.stdout(stdout);
+        }
+        if let Some(stderr) = streams.next().unwrap() {
+            cmd.stderr(stderr);
+        }
+        if let Some(tty) = &tty {
+            let fd = tty.as_raw_fd();
+            unsafe {
+                cmd.pre_exec(move || child_take_terminal(fd));
+            }
+        }
         unsafe {
             cmd.pre_exec(child_traceme);
         }
-        let child = cmd.spawn().ok()?;
+        let child = cmd
+            .spawn()
+            .map_err(|err| format!("Error starting subprocess: {}", err))?;
         let mut inferior = Inferior {
-            child,
+            pid: Pid::from_raw(child.id() as i32),
             breakpoints: HashMap::new(),
+            exited: Cell::new(false),
+            recorder: None,
+            pending_signal: None,
+            syscall_stops: false,
+            follow_forks: false,
         };
-        let result = inferior.wait(None).ok()?;
-        match result {
-            Status::Stopped(signal, _) => match signal {
-                signal::SIGTRAP => {
-                    for bp in breakpoints {
-                        inferior.insert_breakpoint(*bp).ok()?
-                    }
-                    Some(inferior)
-                }
-                _ => None,
-            },
-            _ => None,
+        let failed = |err: nix::Error| format!("Error starting subprocess: {}", err);
+        match inferior.wait(None).map_err(failed)? {
+            Status::Stopped(signal::SIGTRAP, _) if launch.interruptible => {
+                inferior.seize().map_err(failed)?
+            }
+            Status::Stopped(signal::SIGTRAP, _) => {
+                ptrace::setoptions(inferior.pid(), trace_options()).map_err(failed)?
+            }
+            _ => return Err("Error starting subprocess".to_string()),
+        }
+        for bp in breakpoints {
+            inferior.insert_breakpoint(*bp).map_err(failed)?;
+        }
+        Ok(inferior)
+    }
+
+    /// Traces the inferior, stopped at the start of the program under PTRACE_TRACEME, with
+    /// PTRACE_SEIZE instead. It is let go stopped by SIGSTOP, taken on again and left stopped.
+    fn seize(&self) -> Result<(), nix::Error> {
+        ptrace::detach(self.pid(), Some(signal::SIGSTOP))?;
+        waitpid(self.pid(), Some(WaitPidFlag::WUNTRACED))?;
+        ptrace::seize(self.pid(), trace_options())?;
+        // Taking on a stopped process reports its stop again
+        waitpid(self.pid(), None)?;
+        Ok(())
+    }
+
+    /// Stops an inferior that was started interruptible while it runs. The stop is reported to
+    /// waitpid as a PTRACE_EVENT_STOP, which `status` doesn't know about.
+    pub fn interrupt(&self) -> Result<(), nix::Error> {
+        let ret = unsafe {
+            libc::ptrace(
+                libc::PTRACE_INTERRUPT,
+                self.pid().as_raw(),
+                std::ptr::null_mut::<libc::c_void>(),
+                std::ptr::null_mut::<libc::c_void>(),
+            )
+        };
+        Errno::result(ret)?;
+        Ok(())
+    }
+
+    /// Lets the inferior run on, giving it `signal`, without waiting for it to stop again.
+    pub fn resume(&self, signal: Option<signal::Signal>) -> Result<(), nix::Error> {
+        ptrace::cont(self.pid(), signal)
+    }
+
+    /// Writes an int3 at `addr` unless there already is one, and returns its breakpoint.
+    fn install_breakpoint(&mut self, addr: usize) -> Result<&mut Breakpoint, nix::Error> {
+        if !self.breakpoints.contains_key(&addr) {
+            let orig_byte = self.write_byte(addr, 0xcc)?;
+            let bp = Breakpoint {
+                addr,
+                orig_byte,
+                user: false,
+                internal: false,
+            };
+            self.breakpoints.insert(addr, bp);
+        }
+        Ok(self.breakpoints.get_mut(&addr).unwrap())
+    }
+
+    /// Takes the int3 at `addr` out again if nothing needs it anymore.
+    fn uninstall_breakpoint_if_unused(&mut self, addr: usize) -> Result<(), nix::Error> {
+        let unused = match self.breakpoints.get(&addr) {
+            Some(bp) => !bp.user && !bp.internal,
+            None => false,
+        };
+        if unused {
+            let bp = self.breakpoints.remove(&addr).unwrap();
+            self.write_byte(bp.addr, bp.orig_byte)?;
         }
+        Ok(())
     }
 
     /// Insert breakpoint
     pub fn insert_breakpoint(&mut self, addr: usize) -> Result<(), nix::Error> {
-        if self.breakpoints.contains_key(&addr) {
-            return Ok(());
+        self.install_breakpoint(addr)?.user = true;
+        Ok(())
+    }
+
+    /// Remove the user breakpoint at `addr`.
+    pub fn remove_breakpoint(&mut self, addr: usize) -> Result<(), nix::Error> {
+        if let Some(bp) = self.breakpoints.get_mut(&addr) {
+            bp.user = false;
+        }
+        self.uninstall_breakpoint_if_unused(addr)
+    }
+
+    /// Returns true if the user has a breakpoint at `addr`.
+    pub fn is_user_breakpoint(&self, addr: usize) -> bool {
+        self.breakpoints.get(&addr).map_or(false, |bp| bp.user)
+    }
+
+    /// Inserts a one-shot breakpoint that only lasts until the inferior next stops. These never
+    /// show up among the user's breakpoints.
+    pub fn insert_internal_breakpoint(&mut self, addr: usize) -> Result<(), nix::Error> {
+        self.install_breakpoint(addr)?.internal = true;
+        Ok(())
+    }
+
+    fn remove_internal_breakpoints(&mut self) -> Result<(), nix::Error> {
+        let addrs: Vec<usize> = self
+            .breakpoints
+            .values()
+            .filter(|bp| bp.internal)
+            .map(|bp| bp.addr)
+            .collect();
+        for addr in addrs {
+            self.breakpoints.get_mut(&addr).unwrap().internal = false;
+            self.uninstall_breakpoint_if_unused(addr)?;
         }
-        let mut bp = Breakpoint { addr, orig_byte: 0 };
-        bp.orig_byte = self.write_byte(addr, 0xcc)?;
-        self.breakpoints.insert(addr, bp);
         Ok(())
     }
 
     /// Kill the inferior process.
     pub fn kill(&mut self) {
-        self.child.kill().expect("Error killing inferior");
-        self.child.wait().expect("Error waiting for inferior");
+        // The process may be gone already, e.g. if it was killed by someone else
+        let _ = signal::kill(self.pid, signal::SIGKILL);
+        // SIGKILL doesn't end the stop at an exit event that has already been reported; the
+        // process only goes on when let go
+        let _ = ptrace::cont(self.pid, None);
+        // It may still report its exit event before it is gone
+        loop {
+            match waitpid(self.pid, None) {
+                Ok(WaitStatus::Exited(_, _)) | Ok(WaitStatus::Signaled(_, _, _)) | Err(_) => break,
+                Ok(_) => {
+                    let _ = ptrace::cont(self.pid, None);
+                }
+            }
+        }
+    }
+
+    /// Returns true if the process ended while deet was calling one of its functions.
+    pub fn has_exited(&self) -> bool {
+        self.exited.get()
+    }
+
+    /// Returns the path of the program the inferior is running.
+    pub fn executable(&self) -> String {
+        match std::fs::read_link(format!("/proc/{}/exe", self.pid())) {
+            Ok(path) => path.display().to_string(),
+            Err(_) => "(unknown)".to_string(),
+        }
     }
 
     /// Returns the pid of this inferior.
     pub fn pid(&self) -> Pid {
-        nix::unistd::Pid::from_raw(self.child.id() as i32)
+        self.pid
+    }
+
+    /// If the inferior is stopped at a breakpoint, executes the original instruction there and
+    /// puts the breakpoint back. Returns the status if something other than the end of the step
+    /// stopped the process, such as an event of the instruction or the process ending.
+    fn step_over_breakpoint(&mut self) -> Result<Option<Status>, nix::Error> {
+        let rip = ptrace::getregs(self.pid())?.rip as usize;
+        let orig_byte = match self.breakpoints.get(&rip) {
+            Some(bp) => bp.orig_byte,
+            None => return Ok(None),
+        };
+        self.write_byte(rip, orig_byte)?;
+        ptrace::step(self.pid(), None)?;
+        match self.wait_status(None)? {
+            Status::Stopped(_, _) => {
+                self.write_byte(rip, 0xcc)?;
+                Ok(None)
+            }
+            Status::Event(event) => {
+                self.write_byte(rip, 0xcc)?;
+                Ok(Some(Status::Event(event)))
+            }
+            other => Ok(Some(other)),
+        }
     }
 
-    /// Make process continue
+    /// Make process continue. Internal breakpoints are removed once it stops.
+    /// While recording, every instruction is single-stepped and logged instead.
     pub fn cont(&mut self) -> Result<Status, nix::Error> {
-        let mut regs = ptrace::getregs(self.pid())?;
-        let rip = regs.rip as usize;
-        if self.breakpoints.contains_key(&(rip - 1)) {
-            self.write_byte(
-                self.breakpoints.get(&(rip - 1)).unwrap().addr,
-                self.breakpoints.get(&(rip - 1)).unwrap().orig_byte,
-            )?;
-            regs.rip -= 1;
-            ptrace::setregs(self.pid(), regs)?;
-            ptrace::step(self.pid(), None)?;
-            match self.wait(None)? {
-                Status::Stopped(signal, _) => {
-                    if signal == signal::SIGTRAP {
-                        self.write_byte(
-                            self.breakpoints.get(&(rip - 1)).unwrap().addr,
-                            0xcc,
-                        )?;
+        let status = if self.recorder.is_some() {
+            // The signal frame the kernel would write can't be undone, so signals are dropped
+            self.pending_signal = None;
+            self.record_until_breakpoint()?
+        } else {
+            match self.step_over_breakpoint()? {
+                Some(Status::Event(event)) => Status::Event(event),
+                Some(status) => return Ok(status),
+                None => {
+                    let signal = self.pending_signal.take();
+                    if self.syscall_stops {
+                        ptrace::syscall(self.pid(), signal)?;
+                    } else {
+                        ptrace::cont(self.pid(), signal)?;
                     }
+                    self.wait(None)?
                 }
+            }
+        };
+        match status {
+            Status::Exited(_) | Status::Signaled(_) => return Ok(status),
+            // Traps are ours; SIGINT is the user interrupting the program with ctrl+c
+            Status::Stopped(signal::SIGTRAP, _) | Status::Stopped(signal::SIGINT, _) => {}
+            Status::Stopped(signal, _) => self.pending_signal = Some(signal),
+            Status::Event(Event::Exec) => {
+                self.breakpoints.clear();
+                self.recorder = None;
+            }
+            _ => {}
+        }
+        self.remove_internal_breakpoints()?;
+        Ok(status)
+    }
+
+    /// Executes a single instruction, stepping over any breakpoint the inferior is stopped at.
+    pub fn step_instruction(&mut self) -> Result<Status, nix::Error> {
+        let rip = self.registers()?.rip as usize;
+        let status = if self.recorder.is_some() {
+            self.pending_signal = None;
+            self.write_breakpoint_bytes(false)?;
+            let status = self.record_step()?;
+            if let Status::Stopped(_, _) = status {
+                self.write_breakpoint_bytes(true)?;
+            }
+            status
+        } else if self.breakpoints.contains_key(&rip) {
+            match self.step_over_breakpoint()? {
+                Some(status) => status,
+                None => Status::Stopped(signal::SIGTRAP, self.registers()?.rip as usize),
+            }
+        } else {
+            ptrace::step(self.pid(), self.pending_signal.take())?;
+            // Not `wait`: the step may end right after a breakpoint without having hit it
+            self.wait_status(None)?
+        };
+        match status {
+            Status::Stopped(signal::SIGTRAP, _) | Status::Stopped(signal::SIGINT, _) => {}
+            Status::Stopped(signal, _) => self.pending_signal = Some(signal),
+            Status::Event(Event::Exec) => {
+                self.breakpoints.clear();
+                self.recorder = None;
+            }
+            _ => {}
+        }
+        Ok(status)
+    }
+
+    /// Makes `cont` stop on entry to and return from every system call, or stop doing so.
+    pub fn set_syscall_stops(&mut self, enabled: bool) {
+        self.syscall_stops = enabled;
+    }
+
+    /// Keeps tracing the children the inferior forks. Each child starts out stopped, and is taken
+    /// on with `traced_child`.
+    pub fn set_follow_forks(&mut self) {
+        self.follow_forks = true;
+    }
+
+    /// Returns an inferior for a child this inferior forked while followed. The child inherits
+    /// our breakpoints and ptrace options.
+    pub fn traced_child(&self, pid: Pid) -> Inferior {
+        Inferior {
+            pid,
+            breakpoints: self.breakpoints.clone(),
+            exited: Cell::new(false),
+            recorder: None,
+            pending_signal: None,
+            syscall_stops: self.syscall_stops,
+            follow_forks: true,
+        }
+    }
+
+    /// Lets the inferior run to its next system call stop, giving it `signal`, without waiting
+    /// for it. The stop is picked up with `wait_any` and `status`.
+    pub fn resume_to_syscall(&self, signal: Option<signal::Signal>) -> Result<(), nix::Error> {
+        ptrace::syscall(self.pid(), signal)
+    }
+
+    /// Starts logging the instructions the inferior runs, so that they can be undone.
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(Recorder::new());
+    }
+
+    /// Stops recording and forgets the log.
+    pub fn stop_recording(&mut self) {
+        self.recorder = None;
+    }
+
+    /// Returns the number of instructions that can be undone, or None if not recording.
+    pub fn recorded_instructions(&self) -> Option<usize> {
+        self.recorder
+            .as_ref()
+            .map(|recorder| recorder.instruction_count())
+    }
+
+    /// Returns the address of an instruction that recording stopped at because it couldn't be
+    /// decoded, once.
+    pub fn take_unsupported_instruction(&mut self) -> Option<usize> {
+        self.recorder.as_mut()?.unsupported.take()
+    }
+
+    /// Records instructions one at a time until the inferior reaches a breakpoint's address or
+    /// something else stops it. Breakpoints are taken out of memory meanwhile, so that the
+    /// original instructions are run and logged.
+    fn record_until_breakpoint(&mut self) -> Result<Status, nix::Error> {
+        self.write_breakpoint_bytes(false)?;
+        let status = loop {
+            let status = self.record_step()?;
+            match status {
+                Status::Stopped(signal::SIGTRAP, rip)
+                    if !self.breakpoints.contains_key(&rip)
+                        && self.recorder.as_ref().unwrap().unsupported.is_none() => {}
+                other => break other,
+            }
+        };
+        if let Status::Stopped(_, _) = status {
+            self.write_breakpoint_bytes(true)?;
+        }
+        Ok(status)
+    }
+
+    /// Single-steps the instruction at %rip after saving what it may change. Breakpoints must be
+    /// out of memory.
+    fn record_step(&mut self) -> Result<Status, nix::Error> {
+        let regs = self.registers()?;
+        let rip = regs.rip as usize;
+        let code = self.read_accessible_memory(rip, MAX_INSTRUCTION_LENGTH);
+        let regions = match record::memory_writes(&code, &regs) {
+            Some(regions) => regions,
+            None => {
+                self.recorder.as_mut().unwrap().unsupported = Some(rip);
+                return Ok(Status::Stopped(signal::SIGTRAP, rip));
+            }
+        };
+        let memory = regions
+            .iter()
+            .map(|(addr, len)| (*addr, self.read_accessible_memory(*addr, *len)))
+            .collect();
+        let fpregs_before = match self.recorder.as_mut().unwrap().fpregs.take() {
+            Some(fpregs) => fpregs,
+            None => self.fp_registers()?,
+        };
+
+        ptrace::step(self.pid(), None)?;
+        let status = self.wait_status(None)?;
+
+        let mut changed_fpregs = None;
+        if let Status::Stopped(_, _) = status {
+            let fpregs = self.fp_registers()?;
+            if !record::same_fpregs(&fpregs_before, &fpregs) {
+                changed_fpregs = Some(fpregs_before);
+            }
+            self.recorder.as_mut().unwrap().fpregs = Some(fpregs);
+        }
+        self.recorder.as_mut().unwrap().push(Step {
+            regs,
+            fpregs: changed_fpregs,
+            memory,
+        });
+        Ok(status)
+    }
+
+    /// Returns the address of the last recorded instruction and %rsp when it ran.
+    pub fn previous_instruction(&self) -> Option<(usize, usize)> {
+        let regs = self.recorder.as_ref()?.last()?;
+        Some((regs.rip as usize, regs.rsp as usize))
+    }
+
+    /// Undoes the last recorded instruction. Returns the address of that instruction, which is
+    /// where the inferior now is, or None if there is nothing left to undo.
+    pub fn reverse_stepi(&mut self) -> Result<Option<usize>, nix::Error> {
+        let step = match self.recorder.as_mut().and_then(|recorder| recorder.pop()) {
+            Some(step) => step,
+            None => return Ok(None),
+        };
+        for (addr, bytes) in step.memory.iter().rev() {
+            self.write_memory(*addr, bytes)?;
+        }
+        self.set_registers(step.regs)?;
+        if let Some(fpregs) = step.fpregs {
+            self.set_fp_registers(fpregs)?;
+        }
+        self.recorder.as_mut().unwrap().fpregs = None;
+        Ok(Some(step.regs.rip as usize))
+    }
+
+    /// Puts the int3 of every breakpoint into memory, or takes them all out.
+    fn write_breakpoint_bytes(&self, installed: bool) -> Result<(), nix::Error> {
+        for bp in self.breakpoints.values() {
+            self.write_byte(bp.addr, if installed { 0xcc } else { bp.orig_byte })?;
+        }
+        Ok(())
+    }
+
+    /// Writes `bytes` to the inferior's memory at `addr`. Bytes under breakpoints are saved as
+    /// the breakpoints' original bytes, so that the breakpoints stay in place.
+    fn write_memory(&mut self, addr: usize, bytes: &[u8]) -> Result<(), nix::Error> {
+        let end = addr + bytes.len();
+        let mut word_addr = align_addr_to_word(addr);
+        while word_addr < end {
+            let mut word =
+                (ptrace::read(self.pid(), word_addr as ptrace::AddressType)? as u64).to_le_bytes();
+            for (i, byte) in word.iter_mut().enumerate() {
+                let byte_addr = word_addr + i;
+                if byte_addr < addr || byte_addr >= end {
+                    continue;
+                }
+                match self.breakpoints.get_mut(&byte_addr) {
+                    Some(bp) => bp.orig_byte = bytes[byte_addr - addr],
+                    None => *byte = bytes[byte_addr - addr],
+                }
+            }
+            self.write_word(word_addr, u64::from_le_bytes(word))?;
+            word_addr += size_of::<usize>();
+        }
+        Ok(())
+    }
+
+    /// Reads up to `len` bytes at `addr`, stopping early where memory can't be read.
+    fn read_accessible_memory(&self, addr: usize, len: usize) -> Vec<u8> {
+        if let Ok(bytes) = self.read_memory(addr, len) {
+            return bytes;
+        }
+        let mut bytes = Vec::new();
+        while bytes.len() < len {
+            let next = addr + bytes.len();
+            let chunk = (len - bytes.len()).min(size_of::<usize>() - next % size_of::<usize>());
+            match self.read_memory(next, chunk) {
+                Ok(chunk) => bytes.extend(chunk),
+                Err(_) => break,
+            }
+        }
+        bytes
+    }
+
+    /// Continues until the inferior reaches one of `addrs`, or something else stops it first.
+    pub fn run_to(&mut self, addrs: &[usize]) -> Result<Status, nix::Error> {
+        for addr in addrs {
+            self.insert_internal_breakpoint(*addr)?;
+        }
+        self.cont()
+    }
+
+    /// Continues until the frame whose canonical frame address is `cfa` returns to `return_addr`.
+    /// Anything else that stops the inferior first (a breakpoint, a signal or exiting) ends the
+    /// wait early.
+    pub fn finish_frame(&mut self, return_addr: usize, cfa: usize) -> Result<Status, nix::Error> {
+        loop {
+            let status = self.run_to(&[return_addr])?;
+            match status {
+                // Deeper recursive calls of the same function return here too; keep going until
+                // the stack has unwound past the frame being finished
+                Status::Stopped(signal::SIGTRAP, rip)
+                    if rip == return_addr
+                        && !self.is_user_breakpoint(rip)
+                        && (self.registers()?.rsp as usize) < cfa => {}
                 other => return Ok(other),
             }
         }
+    }
+
+    /// Calls the function at `func` with the given arguments, following the System V AMD64 calling
+    /// convention, and returns once it has returned. The call returns to the program's entry
+    /// point, where a trap is planted for the duration of the call. If `memory_return_size` is
+    /// nonzero, the function returns its value in memory, so room for it is made on the stack.
+    /// All registers are restored afterwards, also if the call is interrupted.
+    pub fn call_function(
+        &self,
+        func: usize,
+        args: &[CallArgument],
+        memory_return_size: usize,
+    ) -> Result<CallOutcome, nix::Error> {
+        let saved_regs = self.registers()?;
+        let saved_fpregs = self.fp_registers()?;
+        let entry = self.entry_point()?;
+        let orig_byte = self.write_byte(entry, 0xcc)?;
+        let outcome = self.run_call(
+            func,
+            args,
+            memory_return_size,
+            entry,
+            saved_regs,
+            saved_fpregs,
+        );
+        match outcome {
+            Ok(CallOutcome::Interrupted(Status::Exited(_)))
+            | Ok(CallOutcome::Interrupted(Status::Signaled(_))) => self.exited.set(true),
+            // The process is gone, so there's nothing left to restore
+            Err(nix::Error::Sys(Errno::ESRCH)) => {}
+            _ => {
+                self.write_byte(entry, orig_byte)?;
+                self.set_registers(saved_regs)?;
+                self.set_fp_registers(saved_fpregs)?;
+            }
+        }
+        outcome
+    }
+
+    fn run_call(
+        &self,
+        func: usize,
+        args: &[CallArgument],
+        memory_return_size: usize,
+        return_addr: usize,
+        mut regs: libc::user_regs_struct,
+        mut fpregs: libc::user_fpregs_struct,
+    ) -> Result<CallOutcome, nix::Error> {
+        let mut sp = (regs.rsp as usize - RED_ZONE) & !0xf;
+        let mut integers = Vec::new();
+        let mut floats = Vec::new();
+        let mut stack = Vec::new();
+        let mut buffer = 0;
+        if memory_return_size > 0 {
+            // The address of the buffer is passed like a hidden first argument
+            sp = (sp - memory_return_size) & !0xf;
+            buffer = sp;
+            integers.push(buffer as u64);
+        }
+        for arg in args {
+            match arg {
+                CallArgument::Integer(value) if integers.len() < INTEGER_ARGUMENT_REGISTERS => {
+                    integers.push(*value)
+                }
+                CallArgument::Float(bytes) if floats.len() < FLOAT_ARGUMENT_REGISTERS => {
+                    floats.push(bytes.clone())
+                }
+                CallArgument::Integer(value) => stack.push(*value),
+                CallArgument::Float(bytes) => {
+                    let mut raw = [0u8; 8];
+                    raw[..bytes.len()].copy_from_slice(bytes);
+                    stack.push(u64::from_le_bytes(raw));
+                }
+            }
+        }
+        // Arguments passed on the stack start at a 16-byte boundary, right above the return
+        // address
+        sp = (sp - 8 * stack.len()) & !0xf;
+        for (i, value) in stack.iter().enumerate() {
+            self.write_word(sp + 8 * i, *value)?;
+        }
+        sp -= 8;
+        self.write_word(sp, return_addr as u64)?;
+
+        let mut registers = [
+            &mut regs.rdi,
+            &mut regs.rsi,
+            &mut regs.rdx,
+            &mut regs.rcx,
+            &mut regs.r8,
+            &mut regs.r9,
+        ];
+        for (register, value) in registers.iter_mut().zip(integers) {
+            **register = value;
+        }
+        for (i, bytes) in floats.iter().enumerate() {
+            let mut raw = [0u8; 16];
+            raw[..bytes.len()].copy_from_slice(bytes);
+            for (j, word) in raw.chunks(4).enumerate() {
+                fpregs.xmm_space[4 * i + j] =
+                    u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+            }
+        }
+        // Variadic functions are told in %al how many vector registers hold arguments
+        regs.rax = floats.len() as u64;
+        regs.rsp = sp as u64;
+        regs.rip = func as u64;
+        // Keep the kernel from restarting a system call the inferior was stopped in
+        regs.orig_rax = u64::MAX;
+        self.set_registers(regs)?;
+        self.set_fp_registers(fpregs)?;
+
         ptrace::cont(self.pid(), None)?;
-        self.wait(None)
+        loop {
+            match self.wait(None)? {
+                Status::Stopped(signal::SIGTRAP, rip) if rip == return_addr + 1 => {
+                    let memory = if buffer != 0 {
+                        self.read_memory(buffer, memory_return_size)?
+                    } else {
+                        Vec::new()
+                    };
+                    return Ok(CallOutcome::Returned {
+                        regs: self.registers()?,
+                        fpregs: self.fp_registers()?,
+                        memory,
+                    });
+                }
+                // Children forked by the function are let go, and an exit is reported once the
+                // process is gone
+                Status::Event(_) => ptrace::cont(self.pid(), None)?,
+                other => return Ok(CallOutcome::Interrupted(other)),
+            }
+        }
+    }
+
+    /// Makes a copy of the stopped process by having it call fork(), the way gdb makes
+    /// checkpoints. The copy is left stopped under ptrace with the same breakpoints, and both
+    /// processes are put back the way they were before the call.
+    pub fn fork(&self) -> Result<Inferior, nix::Error> {
+        let saved_regs = self.registers()?;
+        let addr = saved_regs.rip as usize;
+        let saved_word = self.read_word(addr)? as u64;
+        // syscall; int3
+        self.write_word(addr, (saved_word & !0xff_ffff) | 0xcc_05_0f)?;
+        let mut regs = saved_regs;
+        regs.rax = libc::SYS_fork as u64;
+        regs.orig_rax = u64::MAX;
+        self.set_registers(regs)?;
+        let forked = self.run_fork();
+        self.write_word(addr, saved_word)?;
+        self.set_registers(saved_regs)?;
+
+        let copy = Inferior {
+            pid: forked?,
+            breakpoints: self.breakpoints.clone(),
+            exited: Cell::new(false),
+            recorder: None,
+            pending_signal: None,
+            syscall_stops: self.syscall_stops,
+            follow_forks: false,
+        };
+        ptrace::setoptions(copy.pid(), trace_options())?;
+        copy.write_word(addr, saved_word)?;
+        copy.set_registers(saved_regs)?;
+        Ok(copy)
+    }
+
+    /// Runs the injected fork() and returns the pid of the new process once both it and its
+    /// parent have stopped.
+    fn run_fork(&self) -> Result<Pid, nix::Error> {
+        ptrace::cont(self.pid(), None)?;
+        let mut forked = None;
+        loop {
+            match waitpid(self.pid(), None)? {
+                WaitStatus::PtraceEvent(_, _, event)
+                    if event == ptrace::Event::PTRACE_EVENT_FORK as i32 =>
+                {
+                    forked = Some(Pid::from_raw(ptrace::getevent(self.pid())? as i32));
+                    ptrace::cont(self.pid(), None)?;
+                }
+                // The int3 after the syscall
+                WaitStatus::Stopped(_, signal::SIGTRAP) => break,
+                _ => return Err(nix::Error::Sys(Errno::ECHILD)),
+            }
+        }
+        let forked = match forked {
+            Some(pid) => pid,
+            None => return Err(nix::Error::Sys(Errno::EAGAIN)),
+        };
+        // A traced child starts out stopped by SIGSTOP
+        match waitpid(forked, None)? {
+            WaitStatus::Stopped(_, _) => Ok(forked),
+            _ => Err(nix::Error::Sys(Errno::ECHILD)),
+        }
+    }
+
+    /// Makes the user breakpoints in this process match `addrs`, e.g. after a checkpoint was
+    /// made before some breakpoints were set or deleted.
+    pub fn sync_breakpoints(&mut self, addrs: &[usize]) -> Result<(), nix::Error> {
+        let stale: Vec<usize> = self
+            .breakpoints
+            .values()
+            .filter(|bp| bp.user && !addrs.contains(&bp.addr))
+            .map(|bp| bp.addr)
+            .collect();
+        for addr in stale {
+            self.remove_breakpoint(addr)?;
+        }
+        for addr in addrs {
+            self.insert_breakpoint(*addr)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the address of the program's entry point (`_start`), from its auxiliary vector.
+    fn entry_point(&self) -> Result<usize, nix::Error> {
+        let auxv = std::fs::read(format!("/proc/{}/auxv", self.pid()))
+            .map_err(|_| nix::Error::Sys(Errno::EIO))?;
+        auxv.chunks_exact(16)
+            .map(|pair| {
+                let key = u64::from_le_bytes(pair[..8].try_into().unwrap());
+                let value = u64::from_le_bytes(pair[8..].try_into().unwrap());
+                (key, value)
+            })
+            .find(|(key, _)| *key == libc::AT_ENTRY)
+            .map(|(_, value)| value as usize)
+            .ok_or(nix::Error::Sys(Errno::ENOENT))
+    }
+
+    fn write_word(&self, addr: usize, value: u64) -> Result<(), nix::Error> {
+        ptrace::write(
+            self.pid(),
+            addr as ptrace::AddressType,
+            value as *mut std::ffi::c_void,
+        )
+    }
+
+    /// Returns the general purpose registers of the stopped inferior.
+    pub fn registers(&self) -> Result<libc::user_regs_struct, nix::Error> {
+        ptrace::getregs(self.pid())
+    }
+
+    /// Sets the general purpose registers of the stopped inferior.
+    pub fn set_registers(&self, regs: libc::user_regs_struct) -> Result<(), nix::Error> {
+        ptrace::setregs(self.pid(), regs)
+    }
+
+    /// Returns the x87/SSE registers of the stopped inferior.
+    pub fn fp_registers(&self) -> Result<libc::user_fpregs_struct, nix::Error> {
+        let mut fpregs: libc::user_fpregs_struct = unsafe { std::mem::zeroed() };
+        let ret = unsafe {
+            libc::ptrace(
+                libc::PTRACE_GETFPREGS,
+                self.pid().as_raw(),
+                std::ptr::null_mut::<libc::c_void>(),
+                &mut fpregs as *mut libc::user_fpregs_struct as *mut libc::c_void,
+            )
+        };
+        Errno::result(ret)?;
+        Ok(fpregs)
+    }
+
+    /// Sets the x87/SSE registers of the stopped inferior.
+    pub fn set_fp_registers(&self, mut fpregs: libc::user_fpregs_struct) -> Result<(), nix::Error> {
+        let ret = unsafe {
+            libc::ptrace(
+                libc::PTRACE_SETFPREGS,
+                self.pid().as_raw(),
+                std::ptr::null_mut::<libc::c_void>(),
+                &mut fpregs as *mut libc::user_fpregs_struct as *mut libc::c_void,
+            )
+        };
+        Errno::result(ret)?;
+        Ok(())
     }
 
     /// Calls waitpid on this inferior and returns a Status to indicate the state of the process
-    /// after the waitpid call.
+    /// after the waitpid call. If the process stopped on one of our breakpoints, %rip is moved
+    /// back onto the breakpoint's address, so that it points at the instruction that was
+    /// replaced.
     pub fn wait(&self, options: Option<WaitPidFlag>) -> Result<Status, nix::Error> {
-        Ok(match waitpid(self.pid(), options)? {
+        let status = self.wait_status(options)?;
+        if let Status::Stopped(signal::SIGTRAP, rip) = status {
+            if self.breakpoints.contains_key(&(rip - 1)) {
+                let mut regs = ptrace::getregs(self.pid())?;
+                regs.rip = (rip - 1) as u64;
+                ptrace::setregs(self.pid(), regs)?;
+                return Ok(Status::Stopped(signal::SIGTRAP, rip - 1));
+            }
+        }
+        Ok(status)
+    }
+
+    fn wait_status(&self, options: Option<WaitPidFlag>) -> Result<Status, nix::Error> {
+        let status = waitpid(self.pid(), options)?;
+        self.status(status)
+    }
+
+    /// Turns what waitpid said about this inferior into a Status.
+    pub fn status(&self, status: WaitStatus) -> Result<Status, nix::Error> {
+        Ok(match status {
             WaitStatus::Exited(_pid, exit_code) => Status::Exited(exit_code),
             WaitStatus::Signaled(_pid, signal, _core_dumped) => Status::Signaled(signal),
             WaitStatus::Stopped(_pid, signal) => {
                 let regs = ptrace::getregs(self.pid())?;
                 Status::Stopped(signal, regs.rip as usize)
             }
+            WaitStatus::PtraceEvent(_pid, _signal, event) => Status::Event(self.event(event)?),
+            WaitStatus::PtraceSyscall(_pid) => {
+                let regs = ptrace::getregs(self.pid())?;
+                Status::Syscall(self.at_syscall_entry(&regs), regs.orig_rax as usize)
+            }
             other => panic!("waitpid returned unexpected status: {:?}", other),
         })
     }
-    pub fn print_backtrace(&self, debug_data: &DwarfData) -> Result<(), nix::Error> {
+
+    /// Tells whether a system call stop is on entry to the call or on return from it. The stops
+    /// can't simply be counted, since single-stepping over a system call makes neither. Older
+    /// kernels don't say, so there the result register is checked, which the kernel sets to
+    /// -ENOSYS on entry (a call that fails with ENOSYS is mistaken for one being entered).
+    fn at_syscall_entry(&self, regs: &libc::user_regs_struct) -> bool {
+        let mut info = [0u8; 88];
+        let ret = unsafe {
+            libc::ptrace(
+                PTRACE_GET_SYSCALL_INFO,
+                self.pid().as_raw(),
+                info.len(),
+                info.as_mut_ptr(),
+            )
+        };
+        if ret > 0 {
+            info[0] == PTRACE_SYSCALL_INFO_ENTRY
+        } else {
+            regs.rax as i64 == -(libc::ENOSYS as i64)
+        }
+    }
+
+    /// Finds out more about a ptrace event the inferior stopped at. Forked children are let go
+    /// once our breakpoints are out of them, unless they are followed.
+    fn event(&self, event: i32) -> Result<Event, nix::Error> {
+        let message = ptrace::getevent(self.pid())?;
+        if event == ptrace::Event::PTRACE_EVENT_FORK as i32
+            || event == ptrace::Event::PTRACE_EVENT_VFORK as i32
+        {
+            let child = Pid::from_raw(message as i32);
+            if self.follow_forks {
+                return Ok(if event == ptrace::Event::PTRACE_EVENT_FORK as i32 {
+                    Event::Fork(child)
+                } else {
+                    Event::Vfork(child)
+                });
+            }
+            // A traced child starts out stopped by SIGSTOP
+            waitpid(child, Some(WaitPidFlag::__WALL))?;
+            if event == ptrace::Event::PTRACE_EVENT_FORK as i32 {
+                for bp in self.breakpoints.values() {
+                    write_byte_in(child, bp.addr, bp.orig_byte)?;
+                }
+                ptrace::detach(child, None)?;
+                Ok(Event::Fork(child))
+            } else {
+                // A vforked child shares our memory, breakpoints and all, until it execs
+                ptrace::detach(child, None)?;
+                Ok(Event::Vfork(child))
+            }
+        } else if event == ptrace::Event::PTRACE_EVENT_EXEC as i32 {
+            Ok(Event::Exec)
+        } else if event == ptrace::Event::PTRACE_EVENT_EXIT as i32 {
+            Ok(Event::Exit(message as i32))
+        } else {
+            Err(nix::Error::Sys(Errno::EINVAL))
+        }
+    }
+
+    /// Reads `len` bytes of the inferior's memory starting at `addr`. Bytes replaced by our
+    /// breakpoints are reported with their original values.
+    pub fn read_memory(&self, addr: usize, len: usize) -> Result<Vec<u8>, nix::Error> {
+        let mut bytes = Vec::with_capacity(len);
+        let mut word_addr = align_addr_to_word(addr);
+        while word_addr < addr + len {
+            let word = ptrace::read(self.pid(), word_addr as ptrace::AddressType)? as u64;
+            for (i, byte) in word.to_le_bytes().iter().enumerate() {
+                let byte_addr = word_addr + i;
+                if byte_addr >= addr && byte_addr < addr + len {
+                    bytes.push(match self.breakpoints.get(&byte_addr) {
+                        Some(bp) => bp.orig_byte,
+                        None => *byte,
+                    });
+                }
+            }
+            word_addr += size_of::<usize>();
+        }
+        Ok(bytes)
+    }
+
+    fn read_word(&self, addr: usize) -> Result<usize, nix::Error> {
+        Ok(ptrace::read(self.pid(), addr as ptrace::AddressType)? as usize)
+    }
+
+    /// Works out how much of the prologue of the function starting at `func_addr` has run when
+    /// the inferior is at `pc`. Functions are expected to be compiled with frame pointers. In the
+    /// `innermost` frame, `pc` may also be at the `ret` ending the epilogue, which has already
+    /// popped %rbp.
+    fn prologue_state(
+        &self,
+        func_addr: usize,
+        pc: usize,
+        innermost: bool,
+    ) -> Result<PrologueState, nix::Error> {
+        let code = self.read_memory(func_addr, 8)?;
+        // Skip endbr64, which starts functions compiled with -fcf-protection
+        let push_addr = if code[..4] == [0xf3, 0x0f, 0x1e, 0xfa] {
+            func_addr + 4
+        } else {
+            func_addr
+        };
+        let push_len = 1; // push %rbp
+        let mov_len = 3; // mov %rsp,%rbp
+        let at_ret = innermost && self.read_memory(pc, 1)? == [0xc3];
+        Ok(if pc <= push_addr || at_ret {
+            PrologueState::Entry
+        } else if pc < push_addr + push_len + mov_len {
+            PrologueState::RbpPushed
+        } else {
+            PrologueState::FrameSetUp
+        })
+    }
+
+    /// Unwinds the inferior's stack by following the chain of saved frame pointers and returns
+    /// its frames, innermost first. Unwinding stops at `main` or at the first address without
+    /// debugging information.
+    pub fn backtrace(&self, debug_data: &DwarfData) -> Result<Vec<Frame>, nix::Error> {
         let regs = ptrace::getregs(self.pid())?;
-        println!("%rip register: {:#x}", regs.rip);
-        let mut base_ptr = regs.rbp as usize;
-        let mut instruction_ptr = regs.rip as usize;
+        self.unwind(
+            debug_data,
+            regs.rip as usize,
+            regs.rsp as usize,
+            regs.rbp as usize,
+            false,
+        )
+    }
+
+    /// Unwinds the stack of an inferior stopped in code without debugging information, such as
+    /// the C library, from the innermost return address on the stack that is in a function of
+    /// the program. The frame of that function is where %rbp points, unless the code uses %rbp
+    /// for something else, in which case it has saved it on the stack below the return address.
+    /// Returns no frames if there is no such address near the top of the stack.
+    pub fn backtrace_from_stack(&self, debug_data: &DwarfData) -> Result<Vec<Frame>, nix::Error> {
+        let regs = ptrace::getregs(self.pid())?;
+        let stack_ptr = regs.rsp as usize;
+        for addr in (stack_ptr..stack_ptr + 4096).step_by(size_of::<usize>()) {
+            let word = match self.read_word(addr) {
+                Ok(word) => word,
+                Err(_) => break,
+            };
+            if debug_data.get_function_containing(word).is_none() {
+                continue;
+            }
+            // A frame pointer of the caller is a little further up the stack
+            let is_frame = |base_ptr: usize| {
+                base_ptr > addr
+                    && base_ptr - addr < 0x10_0000
+                    && align_addr_to_word(base_ptr) == base_ptr
+            };
+            let mut base_ptr = regs.rbp as usize;
+            let mut saved = addr;
+            while !is_frame(base_ptr) && saved > stack_ptr {
+                saved -= size_of::<usize>();
+                base_ptr = self.read_word(saved)?;
+            }
+            if !is_frame(base_ptr) {
+                break;
+            }
+            return self.unwind(debug_data, word, addr + 8, base_ptr, true);
+        }
+        Ok(Vec::new())
+    }
+
+    /// Unwinds the stack from a frame at `pc`, which is a return address if `returning`.
+    fn unwind(
+        &self,
+        debug_data: &DwarfData,
+        mut pc: usize,
+        mut stack_ptr: usize,
+        mut base_ptr: usize,
+        returning: bool,
+    ) -> Result<Vec<Frame>, nix::Error> {
+        let mut frames = Vec::new();
         loop {
-            let line = match debug_data.get_line_from_addr(instruction_ptr) {
-                Some(line) => line,
-                None => {
-                    println!("No line information found");
-                    return Ok(());
-                }
+            let func_addr = match debug_data.get_function_containing(pc) {
+                Some(func) => func.address,
+                None => break,
             };
-            let func = match debug_data.get_function_from_addr(instruction_ptr) {
-                Some(func) => func,
-                None => {
-                    println!("No function information found");
-                    return Ok(());
-                }
+            let innermost = frames.is_empty() && !returning;
+            let (cfa, caller_base_ptr) = match self.prologue_state(func_addr, pc, innermost)? {
+                PrologueState::Entry => (stack_ptr + 8, base_ptr),
+                PrologueState::RbpPushed => (stack_ptr + 16, self.read_word(stack_ptr)?),
+                PrologueState::FrameSetUp => (base_ptr + 16, self.read_word(base_ptr)?),
             };
-            println!("{} ({}:{})", func, line.file, line.number);
-            if func == "main" {
+            let return_addr = self.read_word(cfa - 8)?;
+            // A return address points after the call instruction, which may already belong to
+            // the next line, so look up the call itself for callers
+            let lookup_addr = if innermost { pc } else { pc - 1 };
+            let inline_frames = debug_data.get_frames_from_addr(lookup_addr);
+            let count = inline_frames.len();
+            let mut reached_main = false;
+            for (i, inline_frame) in inline_frames.into_iter().enumerate() {
+                // The `main` of a Rust program is in the program's crate, e.g. "hello::main"
+                reached_main |= match &inline_frame.function {
+                    Some(function) => function == "main" || function.ends_with("::main"),
+                    None => false,
+                };
+                frames.push(Frame {
+                    pc,
+                    cfa,
+                    base_ptr: cfa - 16,
+                    return_addr,
+                    caller_base_ptr,
+                    function: inline_frame.function,
+                    line: inline_frame.line,
+                    inlined: i + 1 < count,
+                });
+            }
+            if count == 0 || reached_main {
                 break;
             }
-            instruction_ptr =
-                ptrace::read(self.pid(), (base_ptr + 8) as ptrace::AddressType)? as usize;
-            base_ptr = ptrace::read(self.pid(), base_ptr as ptrace::AddressType)? as usize;
+            pc = return_addr;
+            stack_ptr = cfa;
+            base_ptr = caller_base_ptr;
         }
-        Ok(())
+        Ok(frames)
     }
 }
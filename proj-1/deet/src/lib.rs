@@ -0,0 +1,33 @@
+//! deet, a small debugger for C and Rust programs on x86-64 Linux. The `deet` binary is a command
+//! line front end over this library; other front ends and test tooling can drive a program
+//! through `Session`, which returns what happens as values rather than printing it.
+
+mod cli;
+mod completion;
+mod debug_file;
+mod debugger;
+mod debugger_command;
+mod dwarf_data;
+mod expression;
+mod format;
+mod gimli_wrapper;
+mod inferior;
+mod pretty_printers;
+mod profile;
+mod record;
+mod regex;
+mod session;
+mod syscalls;
+mod trace;
+mod tui;
+
+pub use crate::dwarf_data::{Encoding, Line, Member, Type, TypeKind, Variant};
+pub use crate::expression::{Error as ExpressionError, Value};
+pub use crate::inferior::{Event, Frame, Launch, Redirection};
+pub use crate::session::{
+    Breakpoint, Catchpoint, CatchpointKind, Caught, Error, Finish, Notice, Session, Stop,
+};
+
+/// The `deet` command line, which the binary runs.
+#[doc(hidden)]
+pub use crate::cli::main;
@@ -0,0 +1,697 @@
+//! Pretty-printers, which show values of particular types in a more readable form than their
+//! members.
+//!
+//! The built-in ones are for Rust standard library types. A `String` is made of a `Vec<u8>`,
+//! which is made of a `RawVec` and so on down to a pointer and a length; these show such values
+//! the way Rust code sees them, following their layout through the types in the debugging
+//! information rather than assuming the layout of a particular version of the standard library.
+//! `Option` needs no pretty-printer of its own: like every Rust enum, it is shown as its active
+//! variant by `format::format_typed_value`.
+//!
+//! Users describe their own types with the `pretty-printer` command, usually in a .deetinit or a
+//! file they source: `pretty-printer struct list = list head next value` shows a linked list as
+//! its values, read from the program's memory node by node.
+
+use crate::dwarf_data::{DwarfData, Type, TypeKind};
+use crate::format::{self, MAX_ELEMENTS};
+use crate::inferior::Inferior;
+use crate::regex::Regex;
+
+/// Number of bytes of a string that are shown before eliding the rest.
+const MAX_STRING_LEN: usize = 200;
+
+/// A pretty-printer for the types whose names start with `prefix`. It returns None if the value
+/// can't be read, in which case it is shown as the struct it is.
+struct PrettyPrinter {
+    prefix: &'static str,
+    format: fn(&DwarfData, &Inferior, &PrettyPrinters, &[u8], &Type) -> Option<String>,
+}
+
+const PRETTY_PRINTERS: &[PrettyPrinter] = &[
+    PrettyPrinter {
+        prefix: "struct alloc::string::String",
+        format: format_string,
+    },
+    PrettyPrinter {
+        prefix: "struct &str",
+        format: format_str,
+    },
+    PrettyPrinter {
+        prefix: "struct alloc::vec::Vec<",
+        format: format_vec,
+    },
+    PrettyPrinter {
+        prefix: "alloc::boxed::Box<",
+        format: format_box,
+    },
+    PrettyPrinter {
+        prefix: "struct alloc::rc::Rc<",
+        format: format_rc,
+    },
+];
+
+/// The layouts a user's pretty-printer can describe, with the arguments each takes.
+const LAYOUTS: &[(&str, &str)] = &[
+    ("format", "TEMPLATE"),
+    ("array", "DATA LENGTH"),
+    ("list", "HEAD NEXT [VALUE]"),
+    ("ring", "BUFFER START COUNT CAPACITY"),
+    ("hash", "BUCKETS SIZE NEXT KEY [VALUE]"),
+];
+
+/// The pretty-printers values are shown with: the ones the user registered, which are tried
+/// first, the most recent first, and then the built-in ones.
+#[derive(Default)]
+pub struct PrettyPrinters {
+    user: Vec<UserPrinter>,
+}
+
+/// A pretty-printer registered with the `pretty-printer` command.
+struct UserPrinter {
+    /// The definition as it was given, without the command
+    definition: String,
+    /// The type name or `/regex/` before the `=`
+    pattern: String,
+    /// Matches the names of the types this is for, if it was given as a `/regex/`
+    regex: Option<Regex>,
+    layout: Layout,
+}
+
+/// How a user's pretty-printer finds what to show in a value. Members are given as paths: names
+/// separated by dots, which look through pointers, or `.` for the value itself. Counts are paths
+/// to integers, or numbers.
+enum Layout {
+    /// The template with each `{path}` replaced by the value of that member
+    Format(String),
+    /// `length` elements of an array or of the buffer a pointer points to
+    Array { data: String, length: String },
+    /// The nodes of a linked list, starting with `head` (a pointer, or the first node itself)
+    /// and following the `next` pointer of each. The nodes are shown whole unless `value`
+    /// names the member to show.
+    List {
+        head: String,
+        next: String,
+        value: Option<String>,
+    },
+    /// `count` elements of a circular buffer of `capacity` elements, starting at index `start`
+    Ring {
+        buffer: String,
+        start: String,
+        count: String,
+        capacity: String,
+    },
+    /// A hash table that chains its entries: `size` buckets, each pointing to a linked list of
+    /// entries, shown as `[key] = value`
+    Hash {
+        buckets: String,
+        size: String,
+        next: String,
+        key: String,
+        value: Option<String>,
+    },
+}
+
+impl PrettyPrinters {
+    /// Registers a pretty-printer defined as `TYPE = LAYOUT ARGS...`, where TYPE is the name of
+    /// a type or a `/regex/` matching the names of several. It replaces any pretty-printer
+    /// registered for the same TYPE before.
+    pub fn add(&mut self, definition: &str) -> Result<(), String> {
+        let printer = UserPrinter::parse(definition)?;
+        self.user.retain(|other| other.pattern != printer.pattern);
+        self.user.push(printer);
+        Ok(())
+    }
+
+    /// Returns the definitions of the pretty-printers the user registered, in the order they
+    /// were registered.
+    pub fn definitions(&self) -> Vec<&str> {
+        self.user
+            .iter()
+            .map(|printer| printer.definition.as_str())
+            .collect()
+    }
+
+    /// Formats a value with the pretty-printer for its type, if it has one. Values are read from
+    /// the program's memory, so they can only be shown while it is running.
+    pub fn format(
+        &self,
+        debug_data: &DwarfData,
+        inferior: Option<&Inferior>,
+        bytes: &[u8],
+        entity_type: &Type,
+    ) -> Option<String> {
+        let inferior = inferior?;
+        if let Some(printer) = self
+            .user
+            .iter()
+            .rev()
+            .find(|printer| printer.matches(&entity_type.name))
+        {
+            let reader = Reader {
+                debug_data,
+                inferior,
+                printers: self,
+                printer,
+            };
+            return reader.format(bytes, entity_type);
+        }
+        let printer = PRETTY_PRINTERS
+            .iter()
+            .find(|printer| entity_type.name.starts_with(printer.prefix))?;
+        (printer.format)(debug_data, inferior, self, bytes, entity_type)
+    }
+}
+
+impl UserPrinter {
+    fn parse(definition: &str) -> Result<UserPrinter, String> {
+        let equals = match definition.find('=') {
+            Some(equals) => equals,
+            None => return Err("Usage: pretty-printer TYPE|/REGEX/ = LAYOUT ARGS...".to_string()),
+        };
+        let pattern = definition[..equals].trim().to_string();
+        let words: Vec<&str> = definition[equals + 1..].split_whitespace().collect();
+        if pattern.is_empty() {
+            return Err("No type given.".to_string());
+        }
+        let regex = if pattern.len() >= 2 && pattern.starts_with('/') && pattern.ends_with('/') {
+            Some(Regex::new(&pattern[1..pattern.len() - 1])?)
+        } else {
+            None
+        };
+        let (name, arguments) = match LAYOUTS.iter().find(|(name, _)| words.first() == Some(name)) {
+            Some(layout) => *layout,
+            None => {
+                let names: Vec<&str> = LAYOUTS.iter().map(|(name, _)| *name).collect();
+                return Err(format!("Layout must be one of {}.", names.join(", ")));
+            }
+        };
+        let args: Vec<String> = words[1..].iter().map(|word| word.to_string()).collect();
+        let optional = args.get(2).cloned();
+        let layout = match (name, args.len()) {
+            ("format", count) if count > 0 => {
+                let template = args.join(" ");
+                if !placeholders_closed(&template) {
+                    return Err(format!("Unmatched {{ in \"{}\".", template));
+                }
+                Layout::Format(template)
+            }
+            ("array", 2) => Layout::Array {
+                data: args[0].clone(),
+                length: args[1].clone(),
+            },
+            ("list", 2) | ("list", 3) => Layout::List {
+                head: args[0].clone(),
+                next: args[1].clone(),
+                value: optional,
+            },
+            ("ring", 4) => Layout::Ring {
+                buffer: args[0].clone(),
+                start: args[1].clone(),
+                count: args[2].clone(),
+                capacity: args[3].clone(),
+            },
+            ("hash", 4) | ("hash", 5) => Layout::Hash {
+                buckets: args[0].clone(),
+                size: args[1].clone(),
+                next: args[2].clone(),
+                key: args[3].clone(),
+                value: args.get(4).cloned(),
+            },
+            _ => {
+                return Err(format!(
+                    "Usage: pretty-printer TYPE|/REGEX/ = {} {}",
+                    name, arguments
+                ))
+            }
+        };
+        Ok(UserPrinter {
+            definition: format!("{} = {}", pattern, words.join(" ")),
+            pattern,
+            regex,
+            layout,
+        })
+    }
+
+    fn matches(&self, type_name: &str) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(type_name),
+            None => self.pattern == type_name,
+        }
+    }
+}
+
+/// Checks that every `{` in a template is closed by a `}`.
+fn placeholders_closed(template: &str) -> bool {
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        match rest[open..].find('}') {
+            Some(close) => rest = &rest[open + close + 1..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Reads the values a user's pretty-printer shows out of the program's memory.
+struct Reader<'a> {
+    debug_data: &'a DwarfData,
+    inferior: &'a Inferior,
+    printers: &'a PrettyPrinters,
+    printer: &'a UserPrinter,
+}
+
+impl<'a> Reader<'a> {
+    /// Formats a value as the pretty-printer's layout describes, or returns None if a member it
+    /// names is missing or can't be read.
+    fn format(&self, bytes: &[u8], entity_type: &'a Type) -> Option<String> {
+        match &self.printer.layout {
+            Layout::Format(template) => {
+                let mut text = String::new();
+                let mut rest = template.as_str();
+                while let Some(open) = rest.find('{') {
+                    let close = open + rest[open..].find('}')?;
+                    text.push_str(&rest[..open]);
+                    text.push_str(&self.show_member(bytes, entity_type, &rest[open + 1..close])?);
+                    rest = &rest[close + 1..];
+                }
+                text.push_str(rest);
+                Some(text)
+            }
+            Layout::Array { data, length } => {
+                let (data, data_type) = self.resolve(bytes, entity_type, data)?;
+                let length = self.count(bytes, entity_type, length)?;
+                self.show_elements(&data, data_type, 0..length)
+            }
+            Layout::List { head, next, value } => {
+                let (head, head_type) = self.resolve(bytes, entity_type, head)?;
+                let (nodes, node_type) = match head_type.kind {
+                    TypeKind::Pointer(_) => {
+                        let (addr, node_type) = self.pointer(&head, head_type)?;
+                        (
+                            self.chain(addr, node_type, next, MAX_ELEMENTS + 1)?,
+                            node_type,
+                        )
+                    }
+                    _ => {
+                        let (after, after_type) = self.resolve(&head, head_type, next)?;
+                        let (addr, _) = self.pointer(&after, after_type)?;
+                        let mut nodes = vec![head];
+                        nodes.extend(self.chain(addr, head_type, next, MAX_ELEMENTS)?);
+                        (nodes, head_type)
+                    }
+                };
+                let path = value.as_deref().unwrap_or(".");
+                let mut elements = Vec::new();
+                for node in nodes {
+                    elements.push(self.show_member(&node, node_type, path)?);
+                }
+                Some(format!("[{}]", elide(elements).join(", ")))
+            }
+            Layout::Ring {
+                buffer,
+                start,
+                count,
+                capacity,
+            } => {
+                let (buffer, buffer_type) = self.resolve(bytes, entity_type, buffer)?;
+                let start = self.count(bytes, entity_type, start)?;
+                let count = self.count(bytes, entity_type, count)?;
+                let capacity = self.count(bytes, entity_type, capacity)?;
+                let indices = (0..count.min(capacity)).map(|i| (start + i) % capacity);
+                self.show_elements(&buffer, buffer_type, indices)
+            }
+            Layout::Hash {
+                buckets,
+                size,
+                next,
+                key,
+                value,
+            } => {
+                let (buckets, buckets_type) = self.resolve(bytes, entity_type, buckets)?;
+                let size = self.count(bytes, entity_type, size)?;
+                let mut entries = Vec::new();
+                for index in 0..size {
+                    if entries.len() > MAX_ELEMENTS {
+                        break;
+                    }
+                    let (bucket, bucket_type) = self.element(&buckets, buckets_type, index)?;
+                    let (addr, node_type) = self.pointer(&bucket, bucket_type)?;
+                    let limit = MAX_ELEMENTS + 1 - entries.len();
+                    for node in self.chain(addr, node_type, next, limit)? {
+                        let key = self.show_member(&node, node_type, key)?;
+                        entries.push(match value {
+                            Some(value) => format!(
+                                "[{}] = {}",
+                                key,
+                                self.show_member(&node, node_type, value)?
+                            ),
+                            None => key,
+                        });
+                    }
+                }
+                Some(format!("{{{}}}", elide(entries).join(", ")))
+            }
+        }
+    }
+
+    /// Finds the member a path names in a value, reading the structs that pointers on the way
+    /// point to.
+    fn resolve(
+        &self,
+        bytes: &[u8],
+        entity_type: &'a Type,
+        path: &str,
+    ) -> Option<(Vec<u8>, &'a Type)> {
+        let mut value = (bytes.to_vec(), entity_type);
+        if path == "." {
+            return Some(value);
+        }
+        for name in path.split('.') {
+            if let TypeKind::Pointer(_) = value.1.kind {
+                let (addr, pointee) = self.pointer(&value.0, value.1)?;
+                value = (self.inferior.read_memory(addr, pointee.size).ok()?, pointee);
+            }
+            let (bytes, ty) = member(self.debug_data, &value.0, value.1, name)?;
+            value = (bytes.to_vec(), ty);
+        }
+        Some(value)
+    }
+
+    /// Returns the number a count is, or the value of the integer member it names.
+    fn count(&self, bytes: &[u8], entity_type: &'a Type, count: &str) -> Option<usize> {
+        if let Ok(number) = count.parse::<usize>() {
+            return Some(number);
+        }
+        match self.resolve(bytes, entity_type, count)? {
+            (
+                bytes,
+                Type {
+                    kind: TypeKind::Base(_),
+                    ..
+                },
+            )
+            | (
+                bytes,
+                Type {
+                    kind: TypeKind::Enum(_),
+                    ..
+                },
+            ) => Some(format::read_unsigned(&bytes) as usize),
+            _ => None,
+        }
+    }
+
+    /// Returns the address a pointer holds and the type it points to.
+    fn pointer(&self, bytes: &[u8], entity_type: &'a Type) -> Option<(usize, &'a Type)> {
+        match &entity_type.kind {
+            TypeKind::Pointer(Some(pointee)) => {
+                Some((format::read_unsigned(bytes) as usize, pointee))
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads an element of an array, or of the buffer a pointer points to.
+    fn element(
+        &self,
+        buffer: &[u8],
+        buffer_type: &'a Type,
+        index: usize,
+    ) -> Option<(Vec<u8>, &'a Type)> {
+        match &buffer_type.kind {
+            TypeKind::Array(element) => {
+                let bytes = buffer.get(index * element.size..(index + 1) * element.size)?;
+                Some((bytes.to_vec(), element))
+            }
+            TypeKind::Pointer(Some(element)) => {
+                let addr = format::read_unsigned(buffer) as usize + index * element.size;
+                Some((self.inferior.read_memory(addr, element.size).ok()?, element))
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads the nodes of a linked list, starting with the one at `addr` and following the
+    /// `next` pointer of each, until a null pointer or `limit` nodes.
+    fn chain(
+        &self,
+        mut addr: usize,
+        node_type: &'a Type,
+        next: &str,
+        limit: usize,
+    ) -> Option<Vec<Vec<u8>>> {
+        let mut nodes = Vec::new();
+        while addr != 0 && nodes.len() < limit {
+            let node = self.inferior.read_memory(addr, node_type.size).ok()?;
+            let (pointer, pointer_type) = self.resolve(&node, node_type, next)?;
+            addr = self.pointer(&pointer, pointer_type)?.0;
+            nodes.push(node);
+        }
+        Some(nodes)
+    }
+
+    /// Formats the elements of an array or buffer at the given indices, e.g. `[1, 2, 3]`.
+    fn show_elements(
+        &self,
+        buffer: &[u8],
+        buffer_type: &'a Type,
+        indices: impl Iterator<Item = usize>,
+    ) -> Option<String> {
+        let mut elements = Vec::new();
+        for index in indices.take(MAX_ELEMENTS + 1) {
+            let (bytes, ty) = self.element(buffer, buffer_type, index)?;
+            elements.push(self.show(&bytes, ty));
+        }
+        Some(format!("[{}]", elide(elements).join(", ")))
+    }
+
+    /// Formats the member a path names in a value.
+    fn show_member(&self, bytes: &[u8], entity_type: &'a Type, path: &str) -> Option<String> {
+        let (bytes, ty) = self.resolve(bytes, entity_type, path)?;
+        Some(self.show(&bytes, ty))
+    }
+
+    /// Formats a value the way `print` would, except that a value of the very type being
+    /// pretty-printed, such as a node of a list shown whole, is shown as its members.
+    fn show(&self, bytes: &[u8], entity_type: &Type) -> String {
+        if self.printer.matches(&entity_type.name) {
+            format::format_members(
+                self.debug_data,
+                Some(self.inferior),
+                self.printers,
+                bytes,
+                entity_type,
+            )
+        } else {
+            format::format_typed_value(
+                self.debug_data,
+                Some(self.inferior),
+                self.printers,
+                bytes,
+                entity_type,
+            )
+        }
+    }
+}
+
+/// Replaces the elements past the first `MAX_ELEMENTS` with "...".
+fn elide(mut elements: Vec<String>) -> Vec<String> {
+    if elements.len() > MAX_ELEMENTS {
+        elements.truncate(MAX_ELEMENTS);
+        elements.push("...".to_string());
+    }
+    elements
+}
+
+/// Returns the bytes and type of the named member of a struct.
+fn member<'a, 'b>(
+    debug_data: &'a DwarfData,
+    bytes: &'b [u8],
+    entity_type: &Type,
+    name: &str,
+) -> Option<(&'b [u8], &'a Type)> {
+    let members = match &entity_type.kind {
+        TypeKind::Struct(members) => members,
+        _ => return None,
+    };
+    let member = members.iter().find(|member| member.name == name)?;
+    let ty = debug_data.get_type(member.type_offset?)?;
+    Some((bytes.get(member.offset..member.offset + ty.size)?, ty))
+}
+
+/// Finds the first value of a base or pointer type in a struct, looking through the structs it
+/// is nested in: the `usize` in a `Cell<usize>`, or the pointer in a `NonNull<T>`.
+fn innermost<'a>(
+    debug_data: &'a DwarfData,
+    bytes: &'a [u8],
+    entity_type: &'a Type,
+) -> Option<(&'a [u8], &'a Type)> {
+    match &entity_type.kind {
+        TypeKind::Base(_) | TypeKind::Pointer(_) => Some((bytes, entity_type)),
+        TypeKind::Struct(members) => members.iter().find_map(|member| {
+            let ty = debug_data.get_type(member.type_offset?)?;
+            let bytes = bytes.get(member.offset..member.offset + ty.size)?;
+            innermost(debug_data, bytes, ty)
+        }),
+        _ => None,
+    }
+}
+
+/// Reads the first integer in a struct, such as the count in a `Cell<usize>`.
+fn read_integer(debug_data: &DwarfData, bytes: &[u8], entity_type: &Type) -> Option<u64> {
+    match innermost(debug_data, bytes, entity_type)? {
+        (
+            bytes,
+            Type {
+                kind: TypeKind::Base(_),
+                ..
+            },
+        ) => Some(format::read_unsigned(bytes)),
+        _ => None,
+    }
+}
+
+/// Reads the first pointer in a struct, such as the one in a `NonNull<T>`, and returns it with
+/// the type it points to.
+fn read_pointer<'a>(
+    debug_data: &'a DwarfData,
+    bytes: &'a [u8],
+    entity_type: &'a Type,
+) -> Option<(usize, Option<&'a Type>)> {
+    match innermost(debug_data, bytes, entity_type)? {
+        (
+            bytes,
+            Type {
+                kind: TypeKind::Pointer(pointee),
+                ..
+            },
+        ) => Some((format::read_unsigned(bytes) as usize, pointee.as_deref())),
+        _ => None,
+    }
+}
+
+/// Reads `len` bytes of text at `addr` and quotes them.
+fn read_string(inferior: &Inferior, addr: usize, len: usize) -> Option<String> {
+    let bytes = inferior.read_memory(addr, len.min(MAX_STRING_LEN)).ok()?;
+    let mut quoted = format::quote_c_string(&String::from_utf8_lossy(&bytes));
+    if len > MAX_STRING_LEN {
+        quoted.push_str("...");
+    }
+    Some(quoted)
+}
+
+/// Returns the buffer and length of a `Vec<T>`.
+fn vec_buffer(debug_data: &DwarfData, bytes: &[u8], entity_type: &Type) -> Option<(usize, usize)> {
+    let (buf, buf_type) = member(debug_data, bytes, entity_type, "buf")?;
+    let (len, len_type) = member(debug_data, bytes, entity_type, "len")?;
+    let (addr, _) = read_pointer(debug_data, buf, buf_type)?;
+    Some((addr, read_integer(debug_data, len, len_type)? as usize))
+}
+
+/// Formats a `String` as its text.
+fn format_string(
+    debug_data: &DwarfData,
+    inferior: &Inferior,
+    _printers: &PrettyPrinters,
+    bytes: &[u8],
+    entity_type: &Type,
+) -> Option<String> {
+    let (vec, vec_type) = member(debug_data, bytes, entity_type, "vec")?;
+    let (addr, len) = vec_buffer(debug_data, vec, vec_type)?;
+    read_string(inferior, addr, len)
+}
+
+/// Formats a `&str` as its text.
+fn format_str(
+    debug_data: &DwarfData,
+    inferior: &Inferior,
+    _printers: &PrettyPrinters,
+    bytes: &[u8],
+    entity_type: &Type,
+) -> Option<String> {
+    let (data_ptr, data_ptr_type) = member(debug_data, bytes, entity_type, "data_ptr")?;
+    let (length, length_type) = member(debug_data, bytes, entity_type, "length")?;
+    let (addr, _) = read_pointer(debug_data, data_ptr, data_ptr_type)?;
+    read_string(
+        inferior,
+        addr,
+        read_integer(debug_data, length, length_type)? as usize,
+    )
+}
+
+/// Formats a `Vec<T>` as its elements, e.g. `[1, 2, 3]`. The buffer is a `*const u8` in recent
+/// versions of the standard library, so the type of the elements is the type parameter `T`.
+fn format_vec(
+    debug_data: &DwarfData,
+    inferior: &Inferior,
+    printers: &PrettyPrinters,
+    bytes: &[u8],
+    entity_type: &Type,
+) -> Option<String> {
+    let (addr, len) = vec_buffer(debug_data, bytes, entity_type)?;
+    let (_, element_offset) = entity_type
+        .type_parameters
+        .iter()
+        .find(|(name, _)| name == "T")?;
+    let element = debug_data.get_type(*element_offset)?;
+    let shown = len.min(MAX_ELEMENTS);
+    let data = if element.size == 0 {
+        Vec::new()
+    } else {
+        inferior.read_memory(addr, shown * element.size).ok()?
+    };
+    let mut elements: Vec<String> = (0..shown)
+        .map(|i| {
+            let bytes = data
+                .get(i * element.size..(i + 1) * element.size)
+                .unwrap_or(&[]);
+            format::format_typed_value(debug_data, Some(inferior), printers, bytes, element)
+        })
+        .collect();
+    if len > MAX_ELEMENTS {
+        elements.push("...".to_string());
+    }
+    Some(format!("[{}]", elements.join(", ")))
+}
+
+/// Formats a `Box<T>` as the value it owns, e.g. `Box({x = 1, y = 2})`.
+fn format_box(
+    debug_data: &DwarfData,
+    inferior: &Inferior,
+    printers: &PrettyPrinters,
+    bytes: &[u8],
+    entity_type: &Type,
+) -> Option<String> {
+    let (addr, pointee) = read_pointer(debug_data, bytes, entity_type)?;
+    let pointee = pointee?;
+    let value = inferior.read_memory(addr, pointee.size).ok()?;
+    Some(format!(
+        "Box({})",
+        format::format_typed_value(debug_data, Some(inferior), printers, &value, pointee)
+    ))
+}
+
+/// Formats an `Rc<T>` as the value it shares and its reference counts, e.g.
+/// `Rc("shared", strong = 2, weak = 0)`. The weak count is the number of `Weak`s, as
+/// `Rc::weak_count` gives it: the strong references together hold one more.
+fn format_rc(
+    debug_data: &DwarfData,
+    inferior: &Inferior,
+    printers: &PrettyPrinters,
+    bytes: &[u8],
+    entity_type: &Type,
+) -> Option<String> {
+    let (ptr, ptr_type) = member(debug_data, bytes, entity_type, "ptr")?;
+    let (addr, inner_type) = read_pointer(debug_data, ptr, ptr_type)?;
+    let inner_type = inner_type?;
+    let inner = inferior.read_memory(addr, inner_type.size).ok()?;
+    let (strong, strong_type) = member(debug_data, &inner, inner_type, "strong")?;
+    let (weak, weak_type) = member(debug_data, &inner, inner_type, "weak")?;
+    let (value, value_type) = member(debug_data, &inner, inner_type, "value")?;
+    Some(format!(
+        "Rc({}, strong = {}, weak = {})",
+        format::format_typed_value(debug_data, Some(inferior), printers, value, value_type),
+        read_integer(debug_data, strong, strong_type)?,
+        read_integer(debug_data, weak, weak_type)?.saturating_sub(1)
+    ))
+}
@@ -0,0 +1,257 @@
+//! This file contains `deet --profile`, a sampling profiler. The program runs without the
+//! interactive prompt and is stopped with PTRACE_INTERRUPT many times a second; each time, its
+//! stack is unwound and symbolized the way `backtrace` does it. When the program ends, the
+//! stacks are written in the collapsed format that flamegraph.pl and inferno read, one line per
+//! stack with its functions outermost first, and the functions and source lines it spent the
+//! most time in are reported on stderr.
+
+use crate::dwarf_data::{DwarfData, Error as DwarfError};
+use crate::inferior::{Inferior, Launch, Status};
+use nix::sys::signal;
+use nix::sys::wait::{waitpid, WaitStatus};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The ptrace event of the stops PTRACE_INTERRUPT causes, which the libc crate doesn't define.
+const PTRACE_EVENT_STOP: i32 = 128;
+
+/// What `--profile` was asked to do.
+pub struct ProfileOptions {
+    /// Samples taken per second (`--hz`)
+    pub hz: u32,
+    /// Number of functions and lines reported (`--top`)
+    pub top: usize,
+    /// File the collapsed stacks are written to (`-o`)
+    pub output: String,
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        ProfileOptions {
+            hz: 99,
+            top: 10,
+            output: "deet.folded".to_string(),
+        }
+    }
+}
+
+/// The samples taken so far.
+#[derive(Default)]
+struct Profile {
+    /// Number of samples of each stack, given as its functions outermost first
+    stacks: HashMap<Vec<String>, usize>,
+    /// Number of samples in each source line, as `file:line`. Samples outside the program's
+    /// code count for the line that called out of it.
+    lines: HashMap<String, usize>,
+    samples: usize,
+}
+
+impl Profile {
+    /// Takes a sample of the stack of the stopped inferior.
+    fn add(&mut self, inferior: &Inferior, debug_data: &DwarfData) {
+        let (frames, outside) = match inferior.backtrace(debug_data) {
+            Ok(frames) if !frames.is_empty() => (frames, None),
+            // In code without debugging information, such as the C library
+            _ => (
+                inferior
+                    .backtrace_from_stack(debug_data)
+                    .unwrap_or_default(),
+                Some(object_name(inferior)),
+            ),
+        };
+        self.samples += 1;
+        if let Some(line) = frames.iter().find_map(|frame| frame.line.as_ref()) {
+            *self.lines.entry(line.to_string()).or_insert(0) += 1;
+        }
+        let mut stack: Vec<String> = frames
+            .iter()
+            .rev()
+            .map(|frame| frame.function.clone().unwrap_or_else(|| "??".to_string()))
+            .collect();
+        stack.extend(outside);
+        *self.stacks.entry(stack).or_insert(0) += 1;
+    }
+
+    /// Writes the stacks in the collapsed format, e.g. `main;work;spin 312`.
+    fn write_collapsed(&self, path: &str) -> std::io::Result<()> {
+        let mut stacks: Vec<(String, usize)> = self
+            .stacks
+            .iter()
+            .map(|(stack, count)| (stack.join(";"), *count))
+            .collect();
+        stacks.sort();
+        let mut file = BufWriter::new(File::create(path)?);
+        for (stack, count) in stacks {
+            writeln!(file, "{} {}", stack, count)?;
+        }
+        file.flush()
+    }
+
+    /// Prints the `top` functions with the most samples in them (self) or in what they called
+    /// (total), and the `top` source lines with the most samples.
+    fn report(&self, top: usize) {
+        let mut functions: HashMap<&str, (usize, usize)> = HashMap::new();
+        for (stack, count) in &self.stacks {
+            if let Some(leaf) = stack.last() {
+                functions.entry(leaf).or_insert((0, 0)).0 += count;
+            }
+            // Recursive functions count once per sample
+            let unique: HashSet<&String> = stack.iter().collect();
+            for function in unique {
+                functions.entry(function).or_insert((0, 0)).1 += count;
+            }
+        }
+        let mut functions: Vec<(&str, (usize, usize))> = functions.into_iter().collect();
+        functions.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then(a_name.cmp(b_name)));
+        let percent = |count: usize| 100.0 * count as f64 / self.samples as f64;
+        eprintln!("\nFunctions with the most samples:");
+        eprintln!("{:>7} {:>7}  Function", "Self", "Total");
+        for (name, (own, total)) in functions.into_iter().take(top) {
+            eprintln!("{:>6.1}% {:>6.1}%  {}", percent(own), percent(total), name);
+        }
+        let mut lines: Vec<(&String, &usize)> = self.lines.iter().collect();
+        lines.sort_by(|(a_line, a), (b_line, b)| b.cmp(a).then(a_line.cmp(b_line)));
+        eprintln!("\nSource lines with the most samples:");
+        eprintln!("{:>7}  Line", "Self");
+        for (line, count) in lines.into_iter().take(top) {
+            eprintln!("{:>6.1}%  {}", percent(*count), line);
+        }
+    }
+}
+
+/// Names the code the inferior is stopped in after the file it was loaded from, such as
+/// `[libc.so.6]`, for samples outside the program's functions.
+fn object_name(inferior: &Inferior) -> String {
+    let rip = match inferior.registers() {
+        Ok(regs) => regs.rip as usize,
+        Err(_) => return "[unknown]".to_string(),
+    };
+    let maps = fs::read_to_string(format!("/proc/{}/maps", inferior.pid())).unwrap_or_default();
+    for line in maps.lines() {
+        // start-end perms offset dev inode [path]
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let range: Vec<usize> = fields[0]
+            .split('-')
+            .filter_map(|addr| usize::from_str_radix(addr, 16).ok())
+            .collect();
+        if range.len() != 2 || rip < range[0] || rip >= range[1] {
+            continue;
+        }
+        return match fields.get(5) {
+            // [vdso], [heap] and the like
+            Some(path) if path.starts_with('[') => path.to_string(),
+            Some(path) => match Path::new(path).file_name() {
+                Some(name) => format!("[{}]", name.to_string_lossy()),
+                None => format!("[{}]", path),
+            },
+            None => "[unknown]".to_string(),
+        };
+    }
+    "[unknown]".to_string()
+}
+
+/// Runs `target` with `args`, sampling its stack, and writes and reports the profile when it
+/// ends. Returns the exit status deet should exit with, which is the profiled program's.
+pub fn profile(
+    target: &str,
+    args: &Vec<String>,
+    options: &ProfileOptions,
+    debug_file_directories: &[String],
+) -> i32 {
+    let debug_data = match DwarfData::from_file_with_debug_dirs(target, debug_file_directories) {
+        Ok(debug_data) => debug_data,
+        Err(DwarfError::ErrorOpeningFile) => {
+            eprintln!("deet: Could not open file {}", target);
+            return 1;
+        }
+        Err(DwarfError::DwarfFormatError(err)) => {
+            eprintln!(
+                "deet: Could not read debugging symbols from {}: {:?}",
+                target, err
+            );
+            return 1;
+        }
+    };
+    let launch = Launch {
+        interruptible: true,
+        ..Launch::default()
+    };
+    let mut inferior = match Inferior::new(target, args, &Vec::new(), &launch) {
+        Ok(inferior) => inferior,
+        Err(err) => {
+            eprintln!("deet: Could not start {}: {}", target, err);
+            return 1;
+        }
+    };
+    let mut profile = Profile::default();
+    let exit_status = match sample(&mut inferior, &debug_data, options.hz, &mut profile) {
+        Ok(exit_status) => exit_status,
+        Err(err) => {
+            eprintln!("deet: Lost process {}: {}", inferior.pid(), err);
+            inferior.kill();
+            1
+        }
+    };
+    if profile.samples == 0 {
+        eprintln!("deet: No samples; {} ended before the first one.", target);
+        return exit_status;
+    }
+    match profile.write_collapsed(&options.output) {
+        Ok(()) => eprintln!(
+            "deet: {} samples at {} Hz; stacks written to {}",
+            profile.samples, options.hz, options.output
+        ),
+        Err(err) => eprintln!("deet: {}: {}", options.output, err),
+    }
+    profile.report(options.top);
+    exit_status
+}
+
+/// Lets the inferior run to its end, interrupting it `hz` times a second to sample its stack.
+/// Returns the exit status deet should exit with. ctrl+c ends the program and the profile.
+fn sample(
+    inferior: &mut Inferior,
+    debug_data: &DwarfData,
+    hz: u32,
+    profile: &mut Profile,
+) -> Result<i32, nix::Error> {
+    let period = Duration::from_secs(1) / hz;
+    let mut next_sample = Instant::now() + period;
+    inferior.resume(None)?;
+    loop {
+        let now = Instant::now();
+        if next_sample > now {
+            thread::sleep(next_sample - now);
+        }
+        // Samples missed while the last one was taken are not made up for
+        next_sample = (next_sample + period).max(Instant::now());
+        // This fails if the process has just ended, which waitpid tells
+        let _ = inferior.interrupt();
+        loop {
+            let status = waitpid(inferior.pid(), None)?;
+            match status {
+                WaitStatus::PtraceEvent(_, _, PTRACE_EVENT_STOP) => {
+                    profile.add(inferior, debug_data);
+                    inferior.resume(None)?;
+                    break;
+                }
+                WaitStatus::Exited(_, code) => return Ok(code),
+                WaitStatus::Signaled(_, signal, _) => return Ok(128 + signal as i32),
+                _ => {}
+            }
+            // The interrupt stop comes once the process is resumed from this one
+            match inferior.status(status)? {
+                Status::Stopped(signal::SIGINT, _) => {
+                    inferior.kill();
+                    return Ok(128 + signal::SIGINT as i32);
+                }
+                Status::Stopped(signal, _) => inferior.resume(Some(signal))?,
+                _ => inferior.resume(None)?,
+            }
+        }
+    }
+}
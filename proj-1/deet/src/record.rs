@@ -0,0 +1,779 @@
+//! This file contains the instruction recorder behind `record` and the `reverse-*` commands.
+//! While recording, the inferior is single-stepped. Before each instruction runs, the registers
+//! and the memory the instruction may write are saved, so that the instruction can be undone
+//! later by putting them back. Which memory an instruction may write is found by decoding it;
+//! the decoder only needs to find memory operands, so it errs on the side of saving too much
+//! (saving memory that isn't written is harmless).
+
+use std::collections::VecDeque;
+
+/// Instructions kept in the log before the oldest ones are dropped.
+const MAX_INSTRUCTIONS: usize = 200_000;
+
+/// Bytes saved for an explicit memory operand, enough for the widest vector register.
+const OPERAND_BYTES: usize = 64;
+/// Bytes saved for x87 state saves (fnsave/fnstenv).
+const X87_STATE_BYTES: usize = 128;
+/// Bytes saved for fxsave and the xsave family.
+const XSAVE_BYTES: usize = 4096;
+/// Most bytes saved for one iteration-spanning string instruction or system call buffer.
+const MAX_REGION_BYTES: usize = 1 << 16;
+
+/// Everything needed to undo one instruction.
+pub struct Step {
+    /// Registers before the instruction ran
+    pub regs: libc::user_regs_struct,
+    /// x87/SSE registers before the instruction ran, if it changed them
+    pub fpregs: Option<libc::user_fpregs_struct>,
+    /// Memory the instruction may have written, as it was before
+    pub memory: Vec<(usize, Vec<u8>)>,
+}
+
+#[derive(Default)]
+pub struct Recorder {
+    log: VecDeque<Step>,
+    /// The x87/SSE registers after the last recorded instruction, to tell whether the next one
+    /// changes them
+    pub fpregs: Option<libc::user_fpregs_struct>,
+    /// Address of an instruction that can't be undone, which stopped the recording: one the
+    /// decoder didn't understand, a system call it doesn't know, or a store too large to save
+    pub unsupported: Option<usize>,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder::default()
+    }
+
+    pub fn push(&mut self, step: Step) {
+        if self.log.len() == MAX_INSTRUCTIONS {
+            self.log.pop_front();
+        }
+        self.log.push_back(step);
+    }
+
+    pub fn pop(&mut self) -> Option<Step> {
+        self.log.pop_back()
+    }
+
+    /// Returns the registers before the last recorded instruction, i.e. where undoing it leads.
+    pub fn last(&self) -> Option<&libc::user_regs_struct> {
+        self.log.back().map(|step| &step.regs)
+    }
+
+    pub fn instruction_count(&self) -> usize {
+        self.log.len()
+    }
+}
+
+/// Compares two sets of x87/SSE registers.
+pub fn same_fpregs(a: &libc::user_fpregs_struct, b: &libc::user_fpregs_struct) -> bool {
+    let size = std::mem::size_of::<libc::user_fpregs_struct>();
+    let as_bytes = |fpregs: &libc::user_fpregs_struct| unsafe {
+        std::slice::from_raw_parts(fpregs as *const libc::user_fpregs_struct as *const u8, size)
+    };
+    as_bytes(a) == as_bytes(b)
+}
+
+/// Returns the general purpose register with the given number in ModRM/SIB encoding.
+fn register(regs: &libc::user_regs_struct, number: u8) -> u64 {
+    match number {
+        0 => regs.rax,
+        1 => regs.rcx,
+        2 => regs.rdx,
+        3 => regs.rbx,
+        4 => regs.rsp,
+        5 => regs.rbp,
+        6 => regs.rsi,
+        7 => regs.rdi,
+        8 => regs.r8,
+        9 => regs.r9,
+        10 => regs.r10,
+        11 => regs.r11,
+        12 => regs.r12,
+        13 => regs.r13,
+        14 => regs.r14,
+        _ => regs.r15,
+    }
+}
+
+/// What an instruction does with the memory operand in its ModRM byte.
+#[derive(Clone, Copy, PartialEq)]
+enum Access {
+    /// Reads it at most, or only computes its address (lea, prefetch)
+    Read,
+    /// May write up to this many bytes
+    Write(usize),
+}
+
+const WRITE: Access = Access::Write(OPERAND_BYTES);
+
+/// The parts of an instruction that matter for finding the memory it writes.
+struct Instruction {
+    /// Offset of the ModRM byte, if there is one
+    modrm: Option<usize>,
+    access: Access,
+    /// Size of the immediate after the ModRM byte and displacement
+    immediate: usize,
+    /// Scale of 8-bit displacements (EVEX compresses them); 0 if it isn't known exactly
+    disp8_scale: usize,
+    /// Memory written besides the ModRM operand, e.g. by push
+    implicit: Vec<(usize, usize)>,
+}
+
+/// Prefixes and REX/VEX/EVEX bits that affect decoding.
+#[derive(Default)]
+struct Prefixes {
+    operand_size: bool,
+    address_size: bool,
+    rep: bool,
+    repne: bool,
+    segment_base: u64,
+    rex_w: bool,
+    rex_r: bool,
+    rex_x: bool,
+    rex_b: bool,
+}
+
+/// Returns the memory regions (address and length) that the instruction at the start of `code`
+/// may write, or None if it can't be decoded or what it writes can't be saved. `regs` are the
+/// registers before it runs.
+pub fn memory_writes(code: &[u8], regs: &libc::user_regs_struct) -> Option<Vec<(usize, usize)>> {
+    let mut prefixes = Prefixes::default();
+    let mut pos = 0;
+    loop {
+        match *code.get(pos)? {
+            0x66 => prefixes.operand_size = true,
+            0x67 => prefixes.address_size = true,
+            0xf3 => prefixes.rep = true,
+            0xf2 => prefixes.repne = true,
+            0x64 => prefixes.segment_base = regs.fs_base,
+            0x65 => prefixes.segment_base = regs.gs_base,
+            0xf0 | 0x2e | 0x36 | 0x3e | 0x26 => {}
+            _ => break,
+        }
+        pos += 1;
+    }
+    let byte = *code.get(pos)?;
+    if byte & 0xf0 == 0x40 {
+        prefixes.rex_w = byte & 8 != 0;
+        prefixes.rex_r = byte & 4 != 0;
+        prefixes.rex_x = byte & 2 != 0;
+        prefixes.rex_b = byte & 1 != 0;
+        pos += 1;
+    }
+
+    let instruction = match *code.get(pos)? {
+        0xc4 | 0xc5 | 0x62 => vector_instruction(code, pos, &mut prefixes)?,
+        0x0f => {
+            pos += 1;
+            match *code.get(pos)? {
+                0x38 => {
+                    pos += 1;
+                    three_byte_0f38(*code.get(pos)?, &prefixes, pos + 1)
+                }
+                0x3a => {
+                    pos += 1;
+                    three_byte_0f3a(*code.get(pos)?, pos + 1)
+                }
+                opcode => two_byte(opcode, code, &prefixes, regs, pos + 1)?,
+            }
+        }
+        opcode => one_byte(opcode, code, &prefixes, regs, pos + 1)?,
+    };
+
+    let mut regions = instruction.implicit.clone();
+    if let Some(modrm_pos) = instruction.modrm {
+        if let Access::Write(len) = instruction.access {
+            regions.extend(memory_operand(
+                code,
+                modrm_pos,
+                &instruction,
+                &prefixes,
+                regs,
+                len,
+            )?);
+        }
+    }
+    Some(regions)
+}
+
+/// Works out the address of a ModRM memory operand. Returns no region for register operands.
+fn memory_operand(
+    code: &[u8],
+    modrm_pos: usize,
+    instruction: &Instruction,
+    prefixes: &Prefixes,
+    regs: &libc::user_regs_struct,
+    len: usize,
+) -> Option<Vec<(usize, usize)>> {
+    let modrm = *code.get(modrm_pos)?;
+    let mode = modrm >> 6;
+    if mode == 3 {
+        return Some(Vec::new());
+    }
+    let rm = modrm & 7;
+    let mut pos = modrm_pos + 1;
+    let mut address: u64 = 0;
+    let mut rip_relative = false;
+    if rm == 4 {
+        let sib = *code.get(pos)?;
+        pos += 1;
+        let index = ((sib >> 3) & 7) | ((prefixes.rex_x as u8) << 3);
+        let base = (sib & 7) | ((prefixes.rex_b as u8) << 3);
+        if index != 4 {
+            address = register(regs, index) << (sib >> 6);
+        }
+        if sib & 7 == 5 && mode == 0 {
+            address = address.wrapping_add(read_i32(code, pos)? as u64);
+            pos += 4;
+        } else {
+            address = address.wrapping_add(register(regs, base));
+        }
+    } else if rm == 5 && mode == 0 {
+        rip_relative = true;
+        address = read_i32(code, pos)? as u64;
+        pos += 4;
+    } else {
+        address = register(regs, rm | ((prefixes.rex_b as u8) << 3));
+    }
+
+    let mut regions = Vec::new();
+    match mode {
+        1 => {
+            let disp = *code.get(pos)? as i8 as i64;
+            pos += 1;
+            if instruction.disp8_scale == 0 {
+                // The displacement is scaled by a factor we don't know; cover every candidate
+                let low = address.wrapping_add((disp * 64).min(disp) as u64);
+                let high = address.wrapping_add((disp * 64).max(disp) as u64);
+                let span = high.wrapping_sub(low) as usize;
+                regions.push((low as usize, span + len));
+                return Some(regions);
+            }
+            address = address.wrapping_add((disp * instruction.disp8_scale as i64) as u64);
+        }
+        2 => {
+            address = address.wrapping_add(read_i32(code, pos)? as u64);
+            pos += 4;
+        }
+        _ => {}
+    }
+    if rip_relative {
+        let next = regs.rip.wrapping_add((pos + instruction.immediate) as u64);
+        address = address.wrapping_add(next);
+    }
+    if prefixes.address_size {
+        address &= 0xffff_ffff;
+    }
+    address = address.wrapping_add(prefixes.segment_base);
+    regions.push((address as usize, len));
+    Some(regions)
+}
+
+fn read_i32(code: &[u8], pos: usize) -> Option<i32> {
+    let bytes = code.get(pos..pos + 4)?;
+    Some(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Returns the reg field of the ModRM byte at `pos`, which selects the operation for group
+/// opcodes such as 0x80 or 0xff.
+fn reg_field(code: &[u8], pos: usize) -> Option<u8> {
+    Some((*code.get(pos)? >> 3) & 7)
+}
+
+fn with_modrm(pos: usize, access: Access, immediate: usize) -> Instruction {
+    Instruction {
+        modrm: Some(pos),
+        access,
+        immediate,
+        disp8_scale: 1,
+        implicit: Vec::new(),
+    }
+}
+
+fn without_modrm(implicit: Vec<(usize, usize)>) -> Instruction {
+    Instruction {
+        modrm: None,
+        access: Access::Read,
+        immediate: 0,
+        disp8_scale: 1,
+        implicit,
+    }
+}
+
+/// The 8 bytes a push or call writes below the stack pointer.
+fn pushed(regs: &libc::user_regs_struct, len: usize) -> Vec<(usize, usize)> {
+    vec![(regs.rsp as usize - len, len)]
+}
+
+/// Memory written by one iteration of movs/stos, or all of them if the CPU does several at once.
+/// Returns None if that is more than can be saved.
+fn string_store(
+    regs: &libc::user_regs_struct,
+    prefixes: &Prefixes,
+    unit: usize,
+) -> Option<Vec<(usize, usize)>> {
+    let count = if prefixes.rep || prefixes.repne {
+        (regs.rcx as usize).max(1)
+    } else {
+        1
+    };
+    let len = count.saturating_mul(unit);
+    if len > MAX_REGION_BYTES {
+        return None;
+    }
+    let backwards = regs.eflags & 0x400 != 0;
+    let start = if backwards {
+        (regs.rdi as usize + unit).saturating_sub(len)
+    } else {
+        regs.rdi as usize
+    };
+    Some(vec![(start, len)])
+}
+
+fn operand_size(prefixes: &Prefixes) -> usize {
+    if prefixes.rex_w {
+        8
+    } else if prefixes.operand_size {
+        2
+    } else {
+        4
+    }
+}
+
+fn one_byte(
+    opcode: u8,
+    code: &[u8],
+    prefixes: &Prefixes,
+    regs: &libc::user_regs_struct,
+    modrm: usize,
+) -> Option<Instruction> {
+    let immediate_z = if prefixes.operand_size { 2 } else { 4 };
+    let instruction = match opcode {
+        // Invalid in 64-bit mode
+        0x06 | 0x07 | 0x0e | 0x16 | 0x17 | 0x1e | 0x1f | 0x27 | 0x2f | 0x37 | 0x3f | 0x60
+        | 0x61 | 0x82 | 0x9a | 0xce | 0xd4 | 0xd5 | 0xd6 | 0xea => return None,
+        // add/or/adc/sbb/and/sub/xor/cmp: only the forms with r/m as destination write it
+        0x00..=0x3f if opcode & 7 < 4 => {
+            let writes = opcode & 7 < 2 && opcode >> 3 != 7;
+            with_modrm(modrm, if writes { WRITE } else { Access::Read }, 0)
+        }
+        0x63 | 0x84 | 0x85 | 0x8a | 0x8b | 0x8d | 0x8e => with_modrm(modrm, Access::Read, 0),
+        0x69 => with_modrm(modrm, Access::Read, immediate_z),
+        0x6b => with_modrm(modrm, Access::Read, 1),
+        0x80 | 0x81 | 0x83 => {
+            let immediate = if opcode == 0x81 { immediate_z } else { 1 };
+            let writes = reg_field(code, modrm)? != 7;
+            with_modrm(modrm, if writes { WRITE } else { Access::Read }, immediate)
+        }
+        // xchg, mov stores, pop r/m and shifts
+        0x86..=0x89 | 0x8c | 0x8f | 0xd0..=0xd3 => with_modrm(modrm, WRITE, 0),
+        0xc0 | 0xc1 | 0xc6 => with_modrm(modrm, WRITE, 1),
+        0xc7 => with_modrm(modrm, WRITE, immediate_z),
+        0xd8..=0xdf => with_modrm(modrm, Access::Write(X87_STATE_BYTES), 0),
+        0xf6 | 0xf7 => {
+            let immediate = if opcode == 0xf6 { 1 } else { immediate_z };
+            match reg_field(code, modrm)? {
+                0 | 1 => with_modrm(modrm, Access::Read, immediate),
+                2 | 3 => with_modrm(modrm, WRITE, 0),
+                _ => with_modrm(modrm, Access::Read, 0),
+            }
+        }
+        0xfe => with_modrm(modrm, WRITE, 0),
+        0xff => {
+            let mut instruction = match reg_field(code, modrm)? {
+                0 | 1 => with_modrm(modrm, WRITE, 0),
+                _ => with_modrm(modrm, Access::Read, 0),
+            };
+            match reg_field(code, modrm)? {
+                2 | 6 => instruction.implicit = pushed(regs, 8),
+                3 => instruction.implicit = pushed(regs, 16),
+                _ => {}
+            }
+            instruction
+        }
+        // push reg, push imm, pushf, call rel32
+        0x50..=0x57 | 0x68 | 0x6a | 0x9c | 0xe8 => without_modrm(pushed(regs, 8)),
+        // enter
+        0xc8 => without_modrm(pushed(regs, OPERAND_BYTES)),
+        0xa4 | 0xaa => without_modrm(string_store(regs, prefixes, 1)?),
+        0xa5 | 0xab => without_modrm(string_store(regs, prefixes, operand_size(prefixes))?),
+        0xc4 | 0xc5 | 0x62 => unreachable!(),
+        _ => without_modrm(Vec::new()),
+    };
+    Some(instruction)
+}
+
+fn two_byte(
+    opcode: u8,
+    code: &[u8],
+    prefixes: &Prefixes,
+    regs: &libc::user_regs_struct,
+    modrm: usize,
+) -> Option<Instruction> {
+    let instruction = match opcode {
+        0x05 => without_modrm(syscall_writes(regs)?),
+        0x06
+        | 0x07
+        | 0x08
+        | 0x09
+        | 0x0b
+        | 0x0e
+        | 0x30..=0x37
+        | 0x77
+        | 0x80..=0x8f
+        | 0xa1
+        | 0xa2
+        | 0xa9
+        | 0xaa
+        | 0xc8..=0xcf => without_modrm(Vec::new()),
+        0xa0 | 0xa8 => without_modrm(pushed(regs, 8)),
+        0x0f => return None,
+        // Loads and arithmetic into registers: movups/movss loads, prefetch and nops, movaps
+        // loads, conversions and comparisons, cmov, SSE arithmetic, bt, imul, movzx/movsx,
+        // popcnt, bsf/bsr and most of the MMX/SSE2 integer instructions
+        0x10
+        | 0x12
+        | 0x14..=0x16
+        | 0x18..=0x1f
+        | 0x28
+        | 0x2a
+        | 0x2c..=0x2f
+        | 0x40..=0x6f
+        | 0x74..=0x76
+        | 0x7c
+        | 0x7d
+        | 0xa3
+        | 0xaf
+        | 0xb6
+        | 0xb7
+        | 0xb8
+        | 0xbc..=0xbf
+        | 0xc5
+        | 0xd0..=0xd5
+        | 0xd7..=0xe6
+        | 0xe8..=0xff => with_modrm(modrm, Access::Read, 0),
+        0x70..=0x73 | 0xc2 | 0xc4 | 0xc6 => with_modrm(modrm, Access::Read, 1),
+        // movd/movq xmm to r/m; with f3 it is a load instead
+        0x7e if prefixes.rep => with_modrm(modrm, Access::Read, 0),
+        0xa4 | 0xac => with_modrm(modrm, WRITE, 1),
+        0xba => {
+            let writes = reg_field(code, modrm)? != 4;
+            with_modrm(modrm, if writes { WRITE } else { Access::Read }, 1)
+        }
+        0xae => match reg_field(code, modrm)? {
+            // fxsave, xsave, xsaveopt
+            0 | 4 | 6 => with_modrm(modrm, Access::Write(XSAVE_BYTES), 0),
+            3 => with_modrm(modrm, WRITE, 0),
+            _ => with_modrm(modrm, Access::Read, 0),
+        },
+        0xc7 => match reg_field(code, modrm)? {
+            1 => with_modrm(modrm, WRITE, 0),
+            // xsavec, xsaves
+            4 | 5 => with_modrm(modrm, Access::Write(XSAVE_BYTES), 0),
+            _ => with_modrm(modrm, Access::Read, 0),
+        },
+        // Stores (movups, movlps, movhps, movaps, movnt*, movd/movq, setcc, bts, btr, btc,
+        // shld/shrd by cl, cmpxchg, xadd, movnti, movq, movntdq) and anything else not listed
+        _ => with_modrm(modrm, WRITE, 0),
+    };
+    Some(instruction)
+}
+
+fn three_byte_0f38(opcode: u8, prefixes: &Prefixes, modrm: usize) -> Instruction {
+    match opcode {
+        // movbe to memory (crc32 with f2 only reads)
+        0xf1 if !prefixes.repne => with_modrm(modrm, WRITE, 0),
+        _ => with_modrm(modrm, Access::Read, 0),
+    }
+}
+
+fn three_byte_0f3a(opcode: u8, modrm: usize) -> Instruction {
+    match opcode {
+        // pextrb/pextrw/pextrd/pextrq/extractps
+        0x14..=0x17 => with_modrm(modrm, WRITE, 1),
+        _ => with_modrm(modrm, Access::Read, 1),
+    }
+}
+
+/// Decodes VEX (c4/c5) and EVEX (62) encoded instructions. In these the memory operand is a
+/// source unless the instruction is one of a few stores.
+fn vector_instruction(code: &[u8], pos: usize, prefixes: &mut Prefixes) -> Option<Instruction> {
+    let (map, opcode_pos, evex) = match code[pos] {
+        0xc5 => {
+            let byte = *code.get(pos + 1)?;
+            prefixes.rex_r = byte & 0x80 == 0;
+            (1, pos + 2, None)
+        }
+        0xc4 => {
+            let byte1 = *code.get(pos + 1)?;
+            let byte2 = *code.get(pos + 2)?;
+            prefixes.rex_r = byte1 & 0x80 == 0;
+            prefixes.rex_x = byte1 & 0x40 == 0;
+            prefixes.rex_b = byte1 & 0x20 == 0;
+            prefixes.rex_w = byte2 & 0x80 != 0;
+            (byte1 & 0x1f, pos + 3, None)
+        }
+        _ => {
+            let p0 = *code.get(pos + 1)?;
+            let p1 = *code.get(pos + 2)?;
+            let p2 = *code.get(pos + 3)?;
+            prefixes.rex_r = p0 & 0x80 == 0;
+            prefixes.rex_x = p0 & 0x40 == 0;
+            prefixes.rex_b = p0 & 0x20 == 0;
+            prefixes.rex_w = p1 & 0x80 != 0;
+            (p0 & 0x07, pos + 4, Some(p2))
+        }
+    };
+    let opcode = *code.get(opcode_pos)?;
+    let modrm = opcode_pos + 1;
+    if map == 1 && opcode == 0x77 {
+        // vzeroupper/vzeroall
+        return Some(without_modrm(Vec::new()));
+    }
+    let stores = match map {
+        1 => matches!(
+            opcode,
+            0x11 | 0x13 | 0x17 | 0x29 | 0x2b | 0x7e | 0x7f | 0xd6 | 0xe7
+        ),
+        2 => matches!(opcode, 0x2e | 0x2f | 0x8a | 0x8b | 0x8e | 0xa0..=0xa3),
+        3 => matches!(
+            opcode,
+            0x14..=0x17 | 0x19 | 0x1b | 0x1d | 0x39 | 0x3b | 0x7d
+        ),
+        _ => return None,
+    };
+    let immediate = match (map, opcode) {
+        (3, _) | (1, 0x70..=0x73) | (1, 0xc2) | (1, 0xc4..=0xc6) => 1,
+        _ => 0,
+    };
+    let access = if stores { WRITE } else { Access::Read };
+    let mut instruction = with_modrm(modrm, access, immediate);
+    if evex.is_some() {
+        // EVEX scales 8-bit displacements by an operand size that depends on the instruction
+        instruction.disp8_scale = 0;
+    }
+    Some(instruction)
+}
+
+/// Memory the kernel writes for the system calls that programs like ours commonly make. Returns
+/// None for other system calls, whose effects on memory aren't known, and for buffers too large
+/// to save.
+fn syscall_writes(regs: &libc::user_regs_struct) -> Option<Vec<(usize, usize)>> {
+    const STAT_SIZE: u64 = 144;
+    let region = match regs.rax as libc::c_long {
+        libc::SYS_read | libc::SYS_pread64 | libc::SYS_getdents64 => (regs.rsi, regs.rdx),
+        libc::SYS_readlink => (regs.rsi, regs.rdx),
+        libc::SYS_getrandom | libc::SYS_getcwd => (regs.rdi, regs.rsi),
+        libc::SYS_readlinkat => (regs.rdx, regs.r10),
+        libc::SYS_stat | libc::SYS_fstat | libc::SYS_lstat => (regs.rsi, STAT_SIZE),
+        libc::SYS_newfstatat => (regs.rdx, STAT_SIZE),
+        libc::SYS_statx => (regs.r8, 256),
+        libc::SYS_ioctl => (regs.rdx, OPERAND_BYTES as u64),
+        libc::SYS_clock_gettime | libc::SYS_nanosleep => (regs.rsi, 16),
+        libc::SYS_clock_nanosleep | libc::SYS_prlimit64 => (regs.r10, 16),
+        libc::SYS_gettimeofday | libc::SYS_pipe | libc::SYS_pipe2 => (regs.rdi, 16),
+        libc::SYS_wait4 => (regs.rsi, 4),
+        libc::SYS_uname => (regs.rdi, std::mem::size_of::<libc::utsname>() as u64),
+        libc::SYS_rt_sigaction => (regs.rdx, 32),
+        libc::SYS_rt_sigprocmask => (regs.rdx, 8),
+        libc::SYS_sigaltstack => (regs.rsi, 24),
+        libc::SYS_arch_prctl => (regs.rsi, 8),
+        // System calls that don't write the caller's memory
+        libc::SYS_write
+        | libc::SYS_pwrite64
+        | libc::SYS_writev
+        | libc::SYS_open
+        | libc::SYS_openat
+        | libc::SYS_close
+        | libc::SYS_lseek
+        | libc::SYS_access
+        | libc::SYS_faccessat
+        | libc::SYS_dup
+        | libc::SYS_dup2
+        | libc::SYS_dup3
+        | libc::SYS_brk
+        | libc::SYS_mmap
+        | libc::SYS_mprotect
+        | libc::SYS_munmap
+        | libc::SYS_madvise
+        | libc::SYS_sched_yield
+        | libc::SYS_getpid
+        | libc::SYS_getppid
+        | libc::SYS_gettid
+        | libc::SYS_getuid
+        | libc::SYS_geteuid
+        | libc::SYS_getgid
+        | libc::SYS_getegid
+        | libc::SYS_set_tid_address
+        | libc::SYS_set_robust_list
+        | libc::SYS_kill
+        | libc::SYS_tgkill
+        | libc::SYS_exit
+        | libc::SYS_exit_group => return Some(Vec::new()),
+        _ => return None,
+    };
+    if region.1 > MAX_REGION_BYTES as u64 {
+        None
+    } else if region.0 == 0 {
+        Some(Vec::new())
+    } else {
+        Some(vec![(region.0 as usize, region.1 as usize)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registers() -> libc::user_regs_struct {
+        let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+        regs.rax = 0x10;
+        regs.rcx = 3;
+        regs.rbx = 0x2000;
+        regs.rsp = 0x7000;
+        regs.rbp = 0x7100;
+        regs.rsi = 0x3000;
+        regs.rdi = 0x5000;
+        regs.r8 = 0x8000;
+        regs.r12 = 0xc000;
+        regs.r13 = 0xd000;
+        regs.rip = 0x40_1000;
+        regs.fs_base = 0x9000_0000;
+        regs
+    }
+
+    /// An instruction as hex bytes, and the regions it writes
+    type Case<'a> = (&'a str, Option<&'a [(usize, usize)]>);
+
+    /// Checks what each instruction writes with registers `regs`.
+    fn check(regs: &libc::user_regs_struct, cases: &[Case]) {
+        for (hex, expected) in cases {
+            let code: Vec<u8> = hex
+                .split_whitespace()
+                .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+                .collect();
+            let writes = memory_writes(&code, regs);
+            assert_eq!(writes.as_deref(), *expected, "{}", hex);
+        }
+    }
+
+    #[test]
+    fn modrm_sib_and_rip_relative_operands() {
+        check(
+            &registers(),
+            &[
+                // mov [rbx], eax; mov eax, [rbx]; mov ebx, eax
+                ("89 03", Some(&[(0x2000, 64)])),
+                ("8b 03", Some(&[])),
+                ("89 c3", Some(&[])),
+                // mov [rbx-8], eax; mov [rbx+0x100], eax
+                ("89 43 f8", Some(&[(0x1ff8, 64)])),
+                ("89 83 00 01 00 00", Some(&[(0x2100, 64)])),
+                // mov [rbx+rcx*4], eax; mov [0x1000], eax
+                ("89 04 8b", Some(&[(0x200c, 64)])),
+                ("89 04 25 00 10 00 00", Some(&[(0x1000, 64)])),
+                // mov [rip+0x10], eax; movl $1, [rip+0x10] (the immediate moves the next rip)
+                ("89 05 10 00 00 00", Some(&[(0x40_1016, 64)])),
+                ("c7 05 10 00 00 00 01 00 00 00", Some(&[(0x40_101a, 64)])),
+                // cmp [rbx], eax; lea eax, [rbx]; add [rbx], 1
+                ("39 03", Some(&[])),
+                ("8d 03", Some(&[])),
+                ("83 03 01", Some(&[(0x2000, 64)])),
+                // Invalid in 64-bit mode
+                ("06", None),
+            ],
+        );
+    }
+
+    #[test]
+    fn rex_vex_evex_and_segment_prefixes() {
+        check(
+            &registers(),
+            &[
+                // mov [rbx], rax; mov [r12], eax; mov [rax+r8], eax; mov [r13+8], eax
+                ("48 89 03", Some(&[(0x2000, 64)])),
+                ("41 89 04 24", Some(&[(0xc000, 64)])),
+                ("42 89 04 00", Some(&[(0x8010, 64)])),
+                ("41 89 45 08", Some(&[(0xd008, 64)])),
+                // mov fs:[0x28], rax
+                ("64 48 89 04 25 28 00 00 00", Some(&[(0x9000_0028, 64)])),
+                // vmovups [rbx], xmm0; vmovups xmm0, [rbx]; vmovups [r12], xmm0
+                ("c5 f8 11 03", Some(&[(0x2000, 64)])),
+                ("c5 f8 10 03", Some(&[])),
+                ("c4 c1 78 11 04 24", Some(&[(0xc000, 64)])),
+                // vmovups [rbx+0x40], zmm0, whose displacement is scaled by a size that isn't
+                // decoded, so every scale is covered
+                ("62 f1 7c 48 11 43 01", Some(&[(0x2001, 127)])),
+                ("62 f1 7c 48 10 43 01", Some(&[])),
+            ],
+        );
+    }
+
+    #[test]
+    fn pushes_calls_and_enter() {
+        check(
+            &registers(),
+            &[
+                // push rbp; push 1; call rel32; call rax; call [rbx]
+                ("55", Some(&[(0x6ff8, 8)])),
+                ("6a 01", Some(&[(0x6ff8, 8)])),
+                ("e8 00 00 00 00", Some(&[(0x6ff8, 8)])),
+                ("ff d0", Some(&[(0x6ff8, 8)])),
+                ("ff 13", Some(&[(0x6ff8, 8)])),
+                // enter 0x10, 0
+                (
+                    "c8 10 00 00",
+                    Some(&[(0x7000 - OPERAND_BYTES, OPERAND_BYTES)]),
+                ),
+                // pop rbp; ret
+                ("5d", Some(&[])),
+                ("c3", Some(&[])),
+            ],
+        );
+    }
+
+    #[test]
+    fn string_stores() {
+        let mut regs = registers();
+        check(
+            &regs,
+            &[
+                // stosb; rep stosb; rep movsd; rep stosq
+                ("aa", Some(&[(0x5000, 1)])),
+                ("f3 aa", Some(&[(0x5000, 3)])),
+                ("f3 a5", Some(&[(0x5000, 12)])),
+                ("f3 48 ab", Some(&[(0x5000, 24)])),
+            ],
+        );
+        // With the direction flag set, they go down from rdi
+        regs.eflags |= 0x400;
+        check(
+            &regs,
+            &[
+                ("aa", Some(&[(0x5000, 1)])),
+                ("f3 aa", Some(&[(0x4ffe, 3)])),
+                ("f3 a5", Some(&[(0x4ff8, 12)])),
+                ("f3 48 ab", Some(&[(0x4ff0, 24)])),
+            ],
+        );
+        // Too much to save
+        regs.rcx = 1 << 20;
+        check(&regs, &[("f3 48 ab", None)]);
+    }
+
+    #[test]
+    fn system_calls() {
+        let mut regs = registers();
+        regs.rax = libc::SYS_write as u64;
+        check(&regs, &[("0f 05", Some(&[]))]);
+        regs.rax = libc::SYS_read as u64;
+        regs.rdx = 3;
+        check(&regs, &[("0f 05", Some(&[(0x3000, 3)]))]);
+        regs.rdx = 1 << 20;
+        check(&regs, &[("0f 05", None)]);
+        regs.rax = libc::SYS_process_vm_readv as u64;
+        check(&regs, &[("0f 05", None)]);
+    }
+}
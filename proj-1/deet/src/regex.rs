@@ -0,0 +1,222 @@
+//! A small regular expression matcher for the patterns of `info functions`, `info variables` and
+//! `info types`. It understands literal characters, `.`, bracket expressions such as `[a-z_]`
+//! and `[^0-9]`, the repetitions `*`, `+` and `?`, and the anchors `^` and `$`. Like grep, a
+//! pattern matches a name if it matches any part of it.
+
+enum Atom {
+    Char(char),
+    Any,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Char(expected) => c == *expected,
+            Atom::Any => true,
+            Atom::Class { negated, ranges } => {
+                ranges.iter().any(|(low, high)| *low <= c && c <= *high) != *negated
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Repeat {
+    Once,
+    ZeroOrOne,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+struct Piece {
+    atom: Atom,
+    repeat: Repeat,
+}
+
+pub struct Regex {
+    pieces: Vec<Piece>,
+    anchored_start: bool,
+    anchored_end: bool,
+}
+
+impl Regex {
+    /// Compiles a pattern, saying what is wrong with it if it cannot be.
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        let mut chars: Vec<char> = pattern.chars().collect();
+        let anchored_start = chars.first() == Some(&'^');
+        if anchored_start {
+            chars.remove(0);
+        }
+        let escaped_end = chars.len() >= 2 && chars[chars.len() - 2] == '\\';
+        let anchored_end = chars.last() == Some(&'$') && !escaped_end;
+        if anchored_end {
+            chars.pop();
+        }
+        let mut pieces: Vec<Piece> = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let repeat = match chars[i] {
+                '*' => Some(Repeat::ZeroOrMore),
+                '+' => Some(Repeat::OneOrMore),
+                '?' => Some(Repeat::ZeroOrOne),
+                _ => None,
+            };
+            // A repetition with nothing before it stands for itself
+            if let (Some(repeat), Some(last)) = (repeat, pieces.last_mut()) {
+                if last.repeat != Repeat::Once {
+                    return Err("Invalid preceding regular expression".to_string());
+                }
+                last.repeat = repeat;
+                i += 1;
+                continue;
+            }
+            let atom = match chars[i] {
+                '.' => Atom::Any,
+                '\\' => {
+                    i += 1;
+                    match chars.get(i) {
+                        Some(c) => Atom::Char(*c),
+                        None => return Err("Trailing backslash".to_string()),
+                    }
+                }
+                '[' => {
+                    let (atom, end) = Regex::parse_class(&chars, i + 1)?;
+                    i = end;
+                    atom
+                }
+                c => Atom::Char(c),
+            };
+            pieces.push(Piece {
+                atom,
+                repeat: Repeat::Once,
+            });
+            i += 1;
+        }
+        Ok(Regex {
+            pieces,
+            anchored_start,
+            anchored_end,
+        })
+    }
+
+    /// Parses the bracket expression starting at `start`, just past the `[`. Returns it with the
+    /// index of its closing `]`.
+    fn parse_class(chars: &[char], start: usize) -> Result<(Atom, usize), String> {
+        let mut i = start;
+        let negated = chars.get(i) == Some(&'^');
+        if negated {
+            i += 1;
+        }
+        let mut ranges = Vec::new();
+        let first = i;
+        loop {
+            let low = match chars.get(i) {
+                // A `]` right after the `[` or `[^` is one of the characters
+                Some(']') if i > first => return Ok((Atom::Class { negated, ranges }, i)),
+                Some(c) => *c,
+                None => return Err("Unmatched [ or [^".to_string()),
+            };
+            match (chars.get(i + 1), chars.get(i + 2)) {
+                (Some('-'), Some(high)) if *high != ']' => {
+                    ranges.push((low, *high));
+                    i += 3;
+                }
+                _ => {
+                    ranges.push((low, low));
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    /// Returns whether the pattern matches any part of `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        if self.anchored_start {
+            self.match_here(&self.pieces, &text, 0)
+        } else {
+            (0..=text.len()).any(|start| self.match_here(&self.pieces, &text, start))
+        }
+    }
+
+    fn match_here(&self, pieces: &[Piece], text: &[char], at: usize) -> bool {
+        let piece = match pieces.first() {
+            Some(piece) => piece,
+            None => return !self.anchored_end || at == text.len(),
+        };
+        let rest = &pieces[1..];
+        let matches_at = |i: usize| i < text.len() && piece.atom.matches(text[i]);
+        match piece.repeat {
+            Repeat::Once => matches_at(at) && self.match_here(rest, text, at + 1),
+            Repeat::ZeroOrOne => {
+                (matches_at(at) && self.match_here(rest, text, at + 1))
+                    || self.match_here(rest, text, at)
+            }
+            Repeat::ZeroOrMore | Repeat::OneOrMore => {
+                let mut run = 0;
+                while matches_at(at + run) {
+                    run += 1;
+                }
+                let least = if piece.repeat == Repeat::OneOrMore {
+                    1
+                } else {
+                    0
+                };
+                // Take as many as possible, giving them back until the rest matches
+                (least..=run)
+                    .rev()
+                    .any(|count| self.match_here(rest, text, at + count))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Regex::new(pattern).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn literals_match_anywhere() {
+        assert!(matches("func", "func2"));
+        assert!(matches("nc2", "func2"));
+        assert!(!matches("func4", "func2"));
+        assert!(matches("", "main"));
+    }
+
+    #[test]
+    fn anchors() {
+        assert!(matches("^func", "func1"));
+        assert!(!matches("^unc", "func1"));
+        assert!(matches("1$", "func1"));
+        assert!(!matches("^func$", "func1"));
+        assert!(matches("a\\$", "a$"));
+    }
+
+    #[test]
+    fn repetitions_and_classes() {
+        assert!(matches("^func[0-9]+$", "func12"));
+        assert!(!matches("^func[0-9]+$", "func"));
+        assert!(matches("^func[0-9]*$", "func"));
+        assert!(matches("^f.*3$", "func3"));
+        assert!(matches("^colou?r$", "color"));
+        assert!(matches("^[^_]", "main"));
+        assert!(!matches("^[^_]", "_start"));
+        assert!(matches("[]x]", "a]"));
+        assert!(matches("*", "a*b"));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert!(Regex::new("[abc").is_err());
+        assert!(Regex::new("a**").is_err());
+        assert!(Regex::new("a\\").is_err());
+    }
+}
@@ -0,0 +1,1144 @@
+//! A programmatic interface to deet, for test tooling and front ends other than the command line.
+//! A `Session` drives one program and hands back what happened as values instead of printing it.
+//! The `deet` command line is one such front end: it decides what to print, while where the
+//! program stops and why is decided here.
+
+use crate::debug_file;
+use crate::dwarf_data::{
+    DwarfData, Encoding, Error as DwarfError, Line, Location, Type, TypeKind, Variable,
+};
+use crate::expression::{self, Value};
+use crate::format;
+use crate::inferior::{
+    CallArgument, CallOutcome, Event, Frame, Inferior, Launch, ReturnLocation, Status,
+    QUIET_SIGNALS,
+};
+use crate::pretty_printers::PrettyPrinters;
+use crate::syscalls;
+use nix::sys::signal::{self, Signal};
+use nix::unistd::Pid;
+use std::convert::TryInto;
+use std::fmt;
+use std::path::Path;
+
+/// Something a `Session` could not do.
+#[derive(Debug)]
+pub enum Error {
+    /// The program could not be opened, or its debugging information could not be read
+    Symbols(String),
+    /// The program could not be started
+    Launch(String),
+    /// There is no process, because the program has not been launched or has ended
+    NotRunning,
+    /// A location that is not a line, function or address of the program
+    UnknownLocation(String),
+    NoBreakpoint(usize),
+    /// A name that isn't a variable in the frame it was looked up in
+    UnknownVariable(String),
+    /// A frame past the outermost one
+    NoFrame(usize),
+    /// The process is in code without line information, so there is no line to step from
+    NoLineInformation,
+    /// `finish` was given an inlined frame, which has no return address of its own
+    InlinedFrame,
+    /// `finish` was given the outermost frame, which has no caller to return to
+    OutermostFrame,
+    /// An expression that could not be parsed or evaluated
+    Expression(expression::Error),
+    /// Something deet does not know how to do, such as reading an x87 return value
+    Unsupported(String),
+    /// A pretty-printer definition that could not be understood
+    PrettyPrinter(String),
+    /// Controlling or reading the process failed
+    Ptrace(nix::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Symbols(message)
+            | Error::Launch(message)
+            | Error::Unsupported(message)
+            | Error::PrettyPrinter(message) => write!(f, "{}", message),
+            Error::NotRunning => write!(f, "The program is not being run."),
+            Error::UnknownLocation(location) => {
+                write!(f, "Function \"{}\" not defined.", location)
+            }
+            Error::NoBreakpoint(number) => write!(f, "No breakpoint number {}.", number),
+            Error::UnknownVariable(name) => write!(f, "No symbol \"{}\" in current context.", name),
+            Error::NoFrame(index) => write!(f, "No frame at level {}.", index),
+            Error::NoLineInformation => write!(f, "Cannot find bounds of current function"),
+            Error::InlinedFrame => write!(f, "Cannot finish out of an inlined frame."),
+            Error::OutermostFrame => {
+                write!(f, "\"finish\" not meaningful in the outermost frame.")
+            }
+            Error::Expression(err) => write!(f, "{}", err),
+            Error::Ptrace(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<nix::Error> for Error {
+    fn from(err: nix::Error) -> Self {
+        Error::Ptrace(err)
+    }
+}
+
+impl From<expression::Error> for Error {
+    fn from(err: expression::Error) -> Self {
+        Error::Expression(err)
+    }
+}
+
+/// Why the process stopped, as told by `Session::cont` and the other methods that run it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stop {
+    /// It reached the breakpoint with this number
+    Breakpoint(usize),
+    /// It got where it was going: the next line for `step`, the location of `until` and
+    /// `advance`, or the caller for `finish`
+    Step,
+    /// It got a signal, which is delivered when it continues
+    Signal(Signal),
+    /// It did what the catchpoint with this number waits for
+    Catchpoint(usize, Caught),
+    /// It exited with this status
+    Exited(i32),
+    /// It was killed by this signal
+    Signaled(Signal),
+}
+
+/// What a catchpoint caught the process doing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Caught {
+    Signal(Signal),
+    /// Entering (`true`) or returning from (`false`) the system call with this number
+    Syscall(bool, usize),
+    Event(Event),
+}
+
+/// What `Session::finish` ended with.
+#[derive(Debug)]
+pub struct Finish {
+    pub stop: Stop,
+    /// What the function returned, if it did return and isn't void
+    pub value: Option<Result<Value, Error>>,
+}
+
+/// Something that happened while the process ran that did not stop it, but that a front end
+/// may want to tell the user about.
+#[derive(Debug)]
+pub enum Notice {
+    /// The instruction at this address could not be recorded, so the recording was stopped
+    UnsupportedInstruction(usize),
+    /// The condition of a breakpoint could not be evaluated, so the breakpoint stopped the
+    /// process
+    ConditionError {
+        number: usize,
+        error: expression::Error,
+    },
+    /// The process forked a child with this pid, which is not debugged
+    Detached(Pid),
+    /// The process replaced its program with another one
+    Exec { pid: Pid, program: String },
+    /// The temporary breakpoint with this number was hit and deleted
+    TemporaryBreakpointDeleted(usize),
+}
+
+/// A breakpoint set with `Session::set_breakpoint`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakpoint {
+    pub number: usize,
+    pub addr: usize,
+    /// Where it was set, as `file:line`, a function or `*address`, so that the breakpoint still
+    /// lands in the right place after the program is recompiled
+    pub location: String,
+    /// Deleted the first time it is hit (`tbreak`)
+    pub temporary: bool,
+    /// Expression that must be true for the breakpoint to stop the process
+    pub condition: Option<String>,
+}
+
+/// A catchpoint set with `Session::catch`. Catchpoints are numbered along with breakpoints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catchpoint {
+    pub number: usize,
+    pub kind: CatchpointKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CatchpointKind {
+    /// Stops on entry to and return from these system calls, or any if empty
+    Syscall(Vec<usize>),
+    Fork,
+    Vfork,
+    Exec,
+    /// Stops when these signals are delivered, or any but SIGTRAP and SIGINT if empty
+    Signal(Vec<Signal>),
+    Exit,
+}
+
+impl CatchpointKind {
+    /// The arguments of the `catch` command that sets this catchpoint.
+    pub(crate) fn arguments(&self) -> String {
+        match self {
+            CatchpointKind::Syscall(numbers) => {
+                let mut arguments = vec!["syscall".to_string()];
+                arguments.extend(numbers.iter().map(|number| syscalls::name(*number)));
+                arguments.join(" ")
+            }
+            CatchpointKind::Signal(signals) => {
+                let mut arguments = vec!["signal".to_string()];
+                arguments.extend(signals.iter().map(|signal| signal.to_string()));
+                arguments.join(" ")
+            }
+            CatchpointKind::Fork => "fork".to_string(),
+            CatchpointKind::Vfork => "vfork".to_string(),
+            CatchpointKind::Exec => "exec".to_string(),
+            CatchpointKind::Exit => "exit".to_string(),
+        }
+    }
+}
+
+pub struct Session {
+    target: String,
+    debug_data: DwarfData,
+    inferior: Option<Inferior>,
+    breakpoints: Vec<Breakpoint>,
+    catchpoints: Vec<Catchpoint>,
+    /// Number given to the next breakpoint or catchpoint
+    next_breakpoint_number: usize,
+    /// Numbers of the breakpoints and catchpoints the process last stopped at
+    hit_breakpoints: Vec<usize>,
+    /// What happened since the notices were last taken
+    notices: Vec<Notice>,
+    /// Environment, working directory and redirections the program is launched with
+    launch: Launch,
+    pretty_printers: PrettyPrinters,
+}
+
+impl Session {
+    /// Loads the debugging information of a program, looking for separate debug files in the
+    /// usual directory.
+    pub fn new(target: &str) -> Result<Session, Error> {
+        let directories = [debug_file::DEFAULT_DEBUG_FILE_DIRECTORY.to_string()];
+        Session::with_debug_file_directories(target, &directories)
+    }
+
+    /// Loads the debugging information of a program, looking for separate debug files in
+    /// `debug_file_directories`.
+    pub fn with_debug_file_directories(
+        target: &str,
+        debug_file_directories: &[String],
+    ) -> Result<Session, Error> {
+        let debug_data = DwarfData::from_file_with_debug_dirs(target, debug_file_directories)
+            .map_err(|err| match err {
+                DwarfError::ErrorOpeningFile => {
+                    Error::Symbols(format!("Could not open file {}", target))
+                }
+                DwarfError::DwarfFormatError(err) => Error::Symbols(format!(
+                    "Could not read debugging symbols from {}: {:?}",
+                    target, err
+                )),
+            })?;
+        Ok(Session {
+            target: target.to_string(),
+            debug_data,
+            inferior: None,
+            breakpoints: Vec::new(),
+            catchpoints: Vec::new(),
+            next_breakpoint_number: 1,
+            hit_breakpoints: Vec::new(),
+            notices: Vec::new(),
+            launch: Launch::default(),
+            pretty_printers: PrettyPrinters::default(),
+        })
+    }
+
+    pub(crate) fn debug_data(&self) -> &DwarfData {
+        &self.debug_data
+    }
+
+    pub(crate) fn inferior(&self) -> Option<&Inferior> {
+        self.inferior.as_ref()
+    }
+
+    pub(crate) fn inferior_mut(&mut self) -> Option<&mut Inferior> {
+        self.inferior.as_mut()
+    }
+
+    fn running(&self) -> Result<&Inferior, Error> {
+        self.inferior.as_ref().ok_or(Error::NotRunning)
+    }
+
+    fn running_mut(&mut self) -> Result<&mut Inferior, Error> {
+        self.inferior.as_mut().ok_or(Error::NotRunning)
+    }
+
+    /// Sets the environment, working directory, terminal and redirections that the program is
+    /// launched with from now on. By default it gets deet's.
+    pub fn set_launch(&mut self, launch: Launch) {
+        self.launch = launch;
+    }
+
+    /// Starts the program with the given arguments, stopped before its first instruction with
+    /// the breakpoints and catchpoints in place. A process that is already running is killed
+    /// first.
+    pub fn launch(&mut self, args: &[&str]) -> Result<(), Error> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let addrs: Vec<usize> = self.breakpoints.iter().map(|bp| bp.addr).collect();
+        let mut inferior =
+            Inferior::new(&self.target, &args, &addrs, &self.launch).map_err(Error::Launch)?;
+        inferior.set_syscall_stops(self.wants_syscall_stops());
+        self.kill();
+        self.inferior = Some(inferior);
+        Ok(())
+    }
+
+    /// Makes `inferior`, a copy of another process of the program, the one being debugged, with
+    /// the current breakpoints and catchpoints. The process it replaces is killed.
+    pub(crate) fn switch_to(&mut self, mut inferior: Inferior) -> Result<(), Error> {
+        let addrs: Vec<usize> = self.breakpoints.iter().map(|bp| bp.addr).collect();
+        let synced = inferior.sync_breakpoints(&addrs);
+        inferior.set_syscall_stops(self.wants_syscall_stops());
+        self.kill();
+        self.inferior = Some(inferior);
+        Ok(synced?)
+    }
+
+    /// Kills the process, if there is one.
+    pub fn kill(&mut self) {
+        if let Some(mut inf) = self.inferior.take() {
+            inf.kill();
+        }
+        self.hit_breakpoints.clear();
+    }
+
+    /// Forgets the process if it has exited, as it may have while a function called by an
+    /// expression ran.
+    pub(crate) fn forget_exited(&mut self) {
+        if self.inferior.as_ref().map_or(false, |inf| inf.has_exited()) {
+            self.inferior = None;
+        }
+    }
+
+    /// Returns true while there is a process.
+    pub fn is_running(&self) -> bool {
+        self.inferior.is_some()
+    }
+
+    /// Sets a breakpoint at a location given the way `break` takes it: `*address`, a line
+    /// number or a function name, optionally preceded by the source file (`file:line`,
+    /// `file:function`). A temporary breakpoint is deleted when it is first hit, and one with a
+    /// condition only stops the process when the condition is true.
+    pub fn set_breakpoint(
+        &mut self,
+        location: &str,
+        temporary: bool,
+        condition: Option<String>,
+    ) -> Result<Breakpoint, Error> {
+        if let Some(condition) = &condition {
+            self.parse(condition)?;
+        }
+        let addr = self
+            .debug_data
+            .get_addr_for_location(location)
+            .ok_or_else(|| Error::UnknownLocation(location.to_string()))?;
+        if let Some(inf) = &mut self.inferior {
+            inf.insert_breakpoint(addr)?;
+        }
+        let breakpoint = Breakpoint {
+            number: self.next_breakpoint_number,
+            addr,
+            location: self.symbolic_location(location, addr),
+            temporary,
+            condition,
+        };
+        self.next_breakpoint_number += 1;
+        self.breakpoints.push(breakpoint.clone());
+        Ok(breakpoint)
+    }
+
+    /// Names the file a bare line number given as a location was found in, so that the location
+    /// still means the same line when the program has more than one source file.
+    fn symbolic_location(&self, location: &str, addr: usize) -> String {
+        if location.parse::<usize>().is_err() {
+            return location.to_string();
+        }
+        match self.debug_data.get_line_from_addr(addr) {
+            Some(line) => {
+                let file = Path::new(&line.file).file_name().unwrap_or_default();
+                format!("{}:{}", file.to_string_lossy(), location)
+            }
+            None => location.to_string(),
+        }
+    }
+
+    pub fn delete_breakpoint(&mut self, number: usize) -> Result<(), Error> {
+        let index = self
+            .breakpoints
+            .iter()
+            .position(|bp| bp.number == number)
+            .ok_or(Error::NoBreakpoint(number))?;
+        let addr = self.breakpoints.remove(index).addr;
+        let still_used = self.breakpoints.iter().any(|bp| bp.addr == addr);
+        if let (false, Some(inf)) = (still_used, &mut self.inferior) {
+            inf.remove_breakpoint(addr)?;
+        }
+        Ok(())
+    }
+
+    /// Gives a breakpoint a condition, or with None makes it unconditional.
+    pub fn set_condition(&mut self, number: usize, condition: Option<String>) -> Result<(), Error> {
+        if let Some(condition) = &condition {
+            self.parse(condition)?;
+        }
+        match self.breakpoints.iter_mut().find(|bp| bp.number == number) {
+            Some(bp) => {
+                bp.condition = condition;
+                Ok(())
+            }
+            None => Err(Error::NoBreakpoint(number)),
+        }
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Sets a catchpoint and returns its number.
+    pub fn catch(&mut self, kind: CatchpointKind) -> usize {
+        let number = self.next_breakpoint_number;
+        self.next_breakpoint_number += 1;
+        self.catchpoints.push(Catchpoint { number, kind });
+        let syscall_stops = self.wants_syscall_stops();
+        if let Some(inf) = &mut self.inferior {
+            inf.set_syscall_stops(syscall_stops);
+        }
+        number
+    }
+
+    pub fn catchpoints(&self) -> &[Catchpoint] {
+        &self.catchpoints
+    }
+
+    /// Returns true if some catchpoint needs the process to stop at system calls.
+    pub fn wants_syscall_stops(&self) -> bool {
+        self.catchpoints
+            .iter()
+            .any(|catchpoint| match catchpoint.kind {
+                CatchpointKind::Syscall(_) => true,
+                _ => false,
+            })
+    }
+
+    /// Returns the numbers of the breakpoints or catchpoints the process last stopped at. There
+    /// can be more than one breakpoint at the same place.
+    pub fn hit_breakpoints(&self) -> &[usize] {
+        &self.hit_breakpoints
+    }
+
+    /// Returns what has happened since the last call that did not stop the process.
+    pub fn take_notices(&mut self) -> Vec<Notice> {
+        std::mem::take(&mut self.notices)
+    }
+
+    /// Continues the process until it reaches a breakpoint, is caught by a catchpoint, gets a
+    /// signal or ends. Signals that programs routinely get, such as SIGCHLD, are passed on
+    /// without stopping.
+    pub fn cont(&mut self) -> Result<Stop, Error> {
+        self.resume(&[], |inf| inf.cont())
+    }
+
+    /// Runs until the process reaches the start of another source line. Functions called on the
+    /// way are stepped into if they have debugging information, and run through if they don't
+    /// (library functions, for example). Stops early for the same reasons as `cont`.
+    pub fn step(&mut self) -> Result<Stop, Error> {
+        self.step_line(false)
+    }
+
+    /// Runs until the process reaches the start of another source line, running through every
+    /// function called on the way.
+    pub fn step_over(&mut self) -> Result<Stop, Error> {
+        self.step_line(true)
+    }
+
+    /// Executes a single instruction.
+    pub fn stepi(&mut self) -> Result<Stop, Error> {
+        self.hit_breakpoints.clear();
+        loop {
+            match self.running_mut()?.step_instruction()? {
+                Status::Stopped(signal::SIGTRAP, pc) => return Ok(self.stop_at(pc)),
+                status => {
+                    if let Some(stop) = self.stop_for(status, &[]) {
+                        return Ok(stop);
+                    }
+                }
+            }
+        }
+    }
+
+    fn step_line(&mut self, over: bool) -> Result<Stop, Error> {
+        let (start_line, start_cfa) = self.position()?.ok_or(Error::NoLineInformation)?;
+        self.hit_breakpoints.clear();
+        loop {
+            let inf = self.running_mut()?;
+            let sp_before = inf.registers()?.rsp as usize;
+            let pc = match inf.step_instruction()? {
+                Status::Stopped(signal::SIGTRAP, pc) => pc,
+                status => match self.stop_for(status, &[]) {
+                    Some(stop) => return Ok(stop),
+                    None => continue,
+                },
+            };
+            let sp = self.running()?.registers()?.rsp as usize;
+            let called = sp + 8 == sp_before;
+            let function = self.debug_data.get_function_containing(pc);
+            let pc = match function {
+                _ if called && (over || function.is_none()) => {
+                    let bytes = self.running()?.read_memory(sp, 8)?;
+                    let return_addr = u64::from_le_bytes(bytes[..].try_into().unwrap()) as usize;
+                    match self
+                        .resume(&[return_addr], |inf| inf.finish_frame(return_addr, sp + 8))?
+                    {
+                        Stop::Step => return_addr,
+                        stop => return Ok(stop),
+                    }
+                }
+                // Left the program's code some other way, as when main returns
+                None => return self.cont(),
+                Some(func) if called && func.address == pc => {
+                    let body = self.debug_data.get_addr_after_prologue(pc);
+                    return self.run_to(body);
+                }
+                Some(_) => pc,
+            };
+            let at_line_start = match self.debug_data.get_line_range(pc) {
+                Some((line, _)) => line.address == pc,
+                None => false,
+            };
+            if !at_line_start {
+                continue;
+            }
+            if let Some((line, cfa)) = self.position()? {
+                if (&line.file, line.number, cfa)
+                    != (&start_line.file, start_line.number, start_cfa)
+                {
+                    return Ok(self.stop_at(pc));
+                }
+            }
+        }
+    }
+
+    /// Runs until frame `frame` (0 is the innermost) returns to its caller, and reads what it
+    /// returned.
+    pub fn finish(&mut self, frame: usize) -> Result<Finish, Error> {
+        let frames = self.backtrace()?;
+        let finished = Session::finished_frame(&frames, frame)?.clone();
+        let return_type = self
+            .debug_data
+            .get_frame_function(&frames, frame)
+            .and_then(|function| function.return_type.clone());
+        let (return_addr, cfa) = (finished.return_addr, finished.cfa);
+        let stop = self.resume(&[return_addr], |inf| inf.finish_frame(return_addr, cfa))?;
+        let returned = match (stop, &self.inferior) {
+            (Stop::Step, Some(inf)) | (Stop::Breakpoint(_), Some(inf)) => {
+                let regs = inf.registers()?;
+                regs.rip as usize == return_addr && regs.rsp as usize == cfa
+            }
+            _ => false,
+        };
+        let value = match (returned, return_type) {
+            (true, Some(return_type)) => Some(self.return_value(return_type)),
+            _ => None,
+        };
+        Ok(Finish { stop, value })
+    }
+
+    /// Checks that frame `frame` can be finished, without running the process.
+    pub fn check_finish(&self, frame: usize) -> Result<(), Error> {
+        Session::finished_frame(&self.backtrace()?, frame).map(|_| ())
+    }
+
+    /// Returns frame `frame` if it is a call that returns to a caller.
+    fn finished_frame(frames: &[Frame], frame: usize) -> Result<&Frame, Error> {
+        let finished = frames.get(frame).ok_or(Error::NoFrame(frame))?;
+        if finished.inlined {
+            return Err(Error::InlinedFrame);
+        }
+        if frame + 1 == frames.len() {
+            return Err(Error::OutermostFrame);
+        }
+        Ok(finished)
+    }
+
+    /// Reads the value a function of the given return type has just returned, following the
+    /// System V AMD64 calling convention.
+    fn return_value(&self, return_type: Type) -> Result<Value, Error> {
+        let inf = self.running()?;
+        let regs = inf.registers()?;
+        let bytes = match ReturnLocation::of(&self.debug_data, &return_type) {
+            ReturnLocation::Memory => inf.read_memory(regs.rax as usize, return_type.size)?,
+            ReturnLocation::Registers(classes) => {
+                let fpregs = inf.fp_registers()?;
+                ReturnLocation::read_registers(&classes, return_type.size, &regs, &fpregs)
+            }
+            ReturnLocation::X87 => {
+                return Err(Error::Unsupported(format!(
+                    "{} is returned in %st0, which is not supported",
+                    return_type.name
+                )))
+            }
+        };
+        Ok(Value {
+            ty: return_type,
+            bytes,
+            address: None,
+        })
+    }
+
+    /// Runs until the current function reaches `location` or returns, whichever comes first,
+    /// passing over deeper recursive calls that reach `location`.
+    pub fn until(&mut self, location: &str) -> Result<Stop, Error> {
+        self.run_to_location(location, false)
+    }
+
+    /// Runs until the process reaches `location`, or the current function returns.
+    pub fn advance(&mut self, location: &str) -> Result<Stop, Error> {
+        self.run_to_location(location, true)
+    }
+
+    fn run_to_location(
+        &mut self,
+        location: &str,
+        stop_in_deeper_frames: bool,
+    ) -> Result<Stop, Error> {
+        let addr = self
+            .debug_data
+            .get_addr_for_location(location)
+            .ok_or_else(|| Error::UnknownLocation(location.to_string()))?;
+        let frames = self.backtrace()?;
+        // Stop in the first real frame's caller if the function returns first
+        let frame = frames
+            .iter()
+            .find(|frame| !frame.inlined)
+            .ok_or(Error::NoFrame(0))?
+            .clone();
+        let mut stops = vec![addr];
+        if frames.len() > 1 {
+            stops.push(frame.return_addr);
+        }
+        loop {
+            let stop = self.resume(&stops, |inf| inf.run_to(&stops))?;
+            if stop == Stop::Step && !stop_in_deeper_frames {
+                let inf = self.running()?;
+                if inf.registers()?.rip as usize == addr && self.in_deeper_frame(inf, frame.cfa) {
+                    continue;
+                }
+            }
+            return Ok(stop);
+        }
+    }
+
+    /// Returns true if the innermost frame is a deeper call made while the frame at `cfa` is
+    /// still on the stack.
+    fn in_deeper_frame(&self, inf: &Inferior, cfa: usize) -> bool {
+        let frames = inf.backtrace(&self.debug_data).unwrap_or_default();
+        frames.first().map_or(false, |frame| frame.cfa < cfa)
+            && frames.iter().any(|frame| frame.cfa == cfa)
+    }
+
+    /// Runs a process that has just been launched to the start of main.
+    pub fn run_to_main(&mut self) -> Result<Stop, Error> {
+        let main = self
+            .debug_data
+            .get_addr_for_location("main")
+            .ok_or_else(|| Error::UnknownLocation("main".to_string()))?;
+        self.run_to(main)
+    }
+
+    /// Continues until the process reaches `addr`, unless something else stops it first.
+    fn run_to(&mut self, addr: usize) -> Result<Stop, Error> {
+        if self.running()?.registers()?.rip as usize == addr {
+            return Ok(self.stop_at(addr));
+        }
+        self.resume(&[addr], |inf| inf.run_to(&[addr]))
+    }
+
+    /// Resumes the process with `resume` until it stops somewhere the caller should know about,
+    /// and tells why. Reaching one of `targets` is a `Stop::Step`, unless a breakpoint whose
+    /// condition holds is there too.
+    fn resume<F>(&mut self, targets: &[usize], mut resume: F) -> Result<Stop, Error>
+    where
+        F: FnMut(&mut Inferior) -> Result<Status, nix::Error>,
+    {
+        self.hit_breakpoints.clear();
+        loop {
+            let inf = self.running_mut()?;
+            let status = resume(inf)?;
+            if let Some(addr) = inf.take_unsupported_instruction() {
+                self.notices.push(Notice::UnsupportedInstruction(addr));
+            }
+            if let Some(stop) = self.stop_for(status, targets) {
+                return Ok(stop);
+            }
+        }
+    }
+
+    /// Tells what a status of the process means to the caller, or returns None for stops that
+    /// are passed over: breakpoints whose conditions are false, signals that programs routinely
+    /// get, and system calls and events that no catchpoint waits for.
+    fn stop_for(&mut self, status: Status, targets: &[usize]) -> Option<Stop> {
+        let caught = self.catchpoints_hit(&status);
+        let stop = match status {
+            Status::Stopped(signal::SIGTRAP, pc) => {
+                let hit = self.breakpoints_hit(pc, true);
+                if !hit.is_empty() {
+                    return Some(self.stop_at_breakpoints(hit));
+                }
+                if targets.contains(&pc) {
+                    Stop::Step
+                } else if self.breakpoints.iter().any(|bp| bp.addr == pc) {
+                    return None;
+                } else {
+                    Stop::Signal(signal::SIGTRAP)
+                }
+            }
+            Status::Stopped(signal, _) => match caught.first() {
+                Some(&number) => Stop::Catchpoint(number, Caught::Signal(signal)),
+                None if QUIET_SIGNALS.contains(&signal) => return None,
+                None => Stop::Signal(signal),
+            },
+            Status::Syscall(entry, number) => {
+                Stop::Catchpoint(*caught.first()?, Caught::Syscall(entry, number))
+            }
+            Status::Event(event) => match caught.first() {
+                Some(&number) => Stop::Catchpoint(number, Caught::Event(event)),
+                None => {
+                    let inf = self.inferior.as_ref()?;
+                    match event {
+                        Event::Fork(pid) | Event::Vfork(pid) => {
+                            self.notices.push(Notice::Detached(pid))
+                        }
+                        Event::Exec => self.notices.push(Notice::Exec {
+                            pid: inf.pid(),
+                            program: inf.executable(),
+                        }),
+                        Event::Exit(_) => {}
+                    }
+                    return None;
+                }
+            },
+            Status::Exited(code) => {
+                self.inferior = None;
+                Stop::Exited(code)
+            }
+            Status::Signaled(signal) => {
+                self.inferior = None;
+                Stop::Signaled(signal)
+            }
+        };
+        self.hit_breakpoints = caught;
+        Some(stop)
+    }
+
+    /// Returns the numbers of the catchpoints that the process stopping with `status` hits.
+    fn catchpoints_hit(&self, status: &Status) -> Vec<usize> {
+        self.catchpoints
+            .iter()
+            .filter(|catchpoint| match (&catchpoint.kind, status) {
+                (CatchpointKind::Syscall(numbers), Status::Syscall(_, number)) => {
+                    numbers.is_empty() || numbers.contains(number)
+                }
+                (CatchpointKind::Signal(signals), Status::Stopped(signal, _)) => {
+                    if signals.is_empty() {
+                        *signal != signal::SIGTRAP && *signal != signal::SIGINT
+                    } else {
+                        signals.contains(signal)
+                    }
+                }
+                (CatchpointKind::Fork, Status::Event(Event::Fork(_)))
+                | (CatchpointKind::Vfork, Status::Event(Event::Vfork(_)))
+                | (CatchpointKind::Exec, Status::Event(Event::Exec))
+                | (CatchpointKind::Exit, Status::Event(Event::Exit(_))) => true,
+                _ => false,
+            })
+            .map(|catchpoint| catchpoint.number)
+            .collect()
+    }
+
+    /// Returns the numbers of the breakpoints at `pc` whose conditions hold in the innermost
+    /// frame. Conditions that can't be evaluated hold, so that the user can see what is wrong.
+    /// Without `allow_calls`, conditions that call functions in the process can't be evaluated,
+    /// as while running backwards.
+    pub(crate) fn breakpoints_hit(&mut self, pc: usize, allow_calls: bool) -> Vec<usize> {
+        let mut hit = Vec::new();
+        let mut errors = Vec::new();
+        for bp in self.breakpoints.iter().filter(|bp| bp.addr == pc) {
+            let condition = match &bp.condition {
+                Some(condition) => condition,
+                None => {
+                    hit.push(bp.number);
+                    continue;
+                }
+            };
+            let value = self.evaluate_in(condition, 0, allow_calls);
+            match value.and_then(|value| Ok(expression::is_true(&value)?)) {
+                Ok(false) => {}
+                Ok(true) => hit.push(bp.number),
+                Err(Error::Expression(error)) => {
+                    errors.push(Notice::ConditionError {
+                        number: bp.number,
+                        error,
+                    });
+                    hit.push(bp.number);
+                }
+                Err(_) => hit.push(bp.number),
+            }
+        }
+        self.notices.extend(errors);
+        hit
+    }
+
+    /// Tells that the process stopped at the breakpoints `hit`, and deletes the temporary ones.
+    fn stop_at_breakpoints(&mut self, hit: Vec<usize>) -> Stop {
+        let temporary: Vec<usize> = self
+            .breakpoints
+            .iter()
+            .filter(|bp| bp.temporary && hit.contains(&bp.number))
+            .map(|bp| bp.number)
+            .collect();
+        for number in temporary {
+            let _ = self.delete_breakpoint(number);
+            self.notices
+                .push(Notice::TemporaryBreakpointDeleted(number));
+        }
+        let stop = Stop::Breakpoint(hit[0]);
+        self.hit_breakpoints = hit;
+        stop
+    }
+
+    /// What to tell about a step that ended at `pc`.
+    fn stop_at(&mut self, pc: usize) -> Stop {
+        let hit = self.breakpoints_hit(pc, true);
+        if hit.is_empty() {
+            Stop::Step
+        } else {
+            self.stop_at_breakpoints(hit)
+        }
+    }
+
+    /// Returns the line and canonical frame address of the innermost frame, or None if the
+    /// process is in code without line information.
+    fn position(&self) -> Result<Option<(Line, usize)>, Error> {
+        let frame = self.backtrace()?.into_iter().next();
+        Ok(frame.and_then(|frame| Some((frame.line?, frame.cfa))))
+    }
+
+    /// Returns the frames of the process's call stack, innermost first.
+    pub fn backtrace(&self) -> Result<Vec<Frame>, Error> {
+        Ok(self.running()?.backtrace(&self.debug_data)?)
+    }
+
+    /// Reads a variable as seen from frame `frame` (0 is the innermost): a parameter or local
+    /// variable of that frame's function, or else a global variable.
+    pub fn read_variable(&self, name: &str, frame: usize) -> Result<Value, Error> {
+        let frames = self.backtrace()?;
+        if frame >= frames.len() {
+            return Err(Error::NoFrame(frame));
+        }
+        let context = FrameContext {
+            session: self,
+            frames,
+            index: frame,
+            allow_calls: false,
+        };
+        let (addr, ty) = expression::Context::variable(&context, name)
+            .ok_or_else(|| Error::UnknownVariable(name.to_string()))?;
+        let bytes = self.running()?.read_memory(addr, ty.size)?;
+        Ok(Value {
+            ty,
+            bytes,
+            address: Some(addr),
+        })
+    }
+
+    /// Parses an expression, to check it before it is evaluated.
+    fn parse(&self, expression: &str) -> Result<expression::Expr, Error> {
+        let is_type_name = |name: &str| self.debug_data.get_type_by_name(name).is_some();
+        Ok(expression::parse(expression, &is_type_name)?)
+    }
+
+    /// Evaluates a C expression as seen from frame `frame` (0 is the innermost). Expressions can
+    /// call the program's functions. Without a process, only expressions that don't read memory
+    /// or registers can be evaluated.
+    pub fn evaluate(&self, expression: &str, frame: usize) -> Result<Value, Error> {
+        self.evaluate_in(expression, frame, true)
+    }
+
+    /// Evaluates an expression, refusing to call functions in the process unless `allow_calls`
+    /// is set.
+    pub(crate) fn evaluate_in(
+        &self,
+        expression: &str,
+        frame: usize,
+        allow_calls: bool,
+    ) -> Result<Value, Error> {
+        let frames = match &self.inferior {
+            Some(inf) => inf.backtrace(&self.debug_data).unwrap_or_default(),
+            None => Vec::new(),
+        };
+        if frame > 0 && frame >= frames.len() {
+            return Err(Error::NoFrame(frame));
+        }
+        let context = FrameContext {
+            session: self,
+            frames,
+            index: frame,
+            allow_calls,
+        };
+        let expr = self.parse(expression)?;
+        Ok(expression::evaluate(&context, &expr)?)
+    }
+
+    /// Registers a pretty-printer for `format_value`, defined as for the `pretty-printer`
+    /// command: `TYPE = LAYOUT ARGS...`.
+    pub fn add_pretty_printer(&mut self, definition: &str) -> Result<(), Error> {
+        self.pretty_printers
+            .add(definition)
+            .map_err(Error::PrettyPrinter)
+    }
+
+    /// Returns the definitions of the pretty-printers added with `add_pretty_printer`.
+    pub fn pretty_printers(&self) -> Vec<&str> {
+        self.pretty_printers.definitions()
+    }
+
+    /// Formats a value the way `print` shows it.
+    pub fn format_value(&self, value: &Value) -> String {
+        self.format_bytes(&value.bytes, &value.ty)
+    }
+
+    /// Formats the bytes of a value of the given type the way `print` shows it.
+    pub(crate) fn format_bytes(&self, bytes: &[u8], entity_type: &Type) -> String {
+        format::format_typed_value(
+            &self.debug_data,
+            self.inferior.as_ref(),
+            &self.pretty_printers,
+            bytes,
+            entity_type,
+        )
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+pub(crate) fn is_floating_point(entity_type: &Type) -> bool {
+    match entity_type.kind {
+        TypeKind::Base(Encoding::Float) => true,
+        _ => false,
+    }
+}
+
+/// Lets expressions see the variables and registers of a frame.
+struct FrameContext<'a> {
+    session: &'a Session,
+    frames: Vec<Frame>,
+    /// Index of the frame in `frames`
+    index: usize,
+    /// False while running backwards, when calling a function would run code that is not in
+    /// the recording
+    allow_calls: bool,
+}
+
+impl expression::Context for FrameContext<'_> {
+    fn variable(&self, name: &str) -> Option<(usize, Type)> {
+        let debug_data = &self.session.debug_data;
+        if !self.frames.is_empty() {
+            let local = debug_data
+                .get_frame_function(&self.frames, self.index)
+                .and_then(|func| {
+                    let var = func.variables.iter().rev().find(|var| var.name == name)?;
+                    let frame = &self.frames[self.index];
+                    let addr = frame.variable_address(var, func.frame_base);
+                    Some((addr, var.entity_type.clone()))
+                });
+            if local.is_some() {
+                return local;
+            }
+        }
+        let var = debug_data.get_global_variable(name)?;
+        match var.location {
+            Location::Address(addr) => Some((addr, var.entity_type.clone())),
+            Location::FramePointerOffset(_) => None,
+        }
+    }
+
+    fn register(&self, name: &str) -> Option<u64> {
+        let regs = self.session.inferior.as_ref()?.registers().ok()?;
+        // The frame registers of outer frames are the ones they will have once the frames
+        // inside them return
+        let frame = self.frames.get(self.index);
+        let value = match name {
+            "pc" | "rip" => frame.map_or(regs.rip, |frame| frame.pc as u64),
+            "sp" | "rsp" if self.index > 0 => self.frames[self.index - 1].cfa as u64,
+            "sp" | "rsp" => regs.rsp,
+            "fp" | "rbp" => frame.map_or(regs.rbp, |frame| frame.base_ptr as u64),
+            "rax" => regs.rax,
+            "rbx" => regs.rbx,
+            "rcx" => regs.rcx,
+            "rdx" => regs.rdx,
+            "rsi" => regs.rsi,
+            "rdi" => regs.rdi,
+            "r8" => regs.r8,
+            "r9" => regs.r9,
+            "r10" => regs.r10,
+            "r11" => regs.r11,
+            "r12" => regs.r12,
+            "r13" => regs.r13,
+            "r14" => regs.r14,
+            "r15" => regs.r15,
+            "eflags" => regs.eflags,
+            "orig_rax" => regs.orig_rax,
+            "cs" => regs.cs,
+            "ss" => regs.ss,
+            "ds" => regs.ds,
+            "es" => regs.es,
+            "fs" => regs.fs,
+            "gs" => regs.gs,
+            "fs_base" => regs.fs_base,
+            "gs_base" => regs.gs_base,
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn read_memory(&self, addr: usize, len: usize) -> Result<Vec<u8>, expression::Error> {
+        let error =
+            || expression::Error::Eval(format!("Cannot access memory at address {:#x}", addr));
+        match &self.session.inferior {
+            Some(inf) => inf.read_memory(addr, len).map_err(|_| error()),
+            None => Err(error()),
+        }
+    }
+
+    fn get_type(&self, offset: usize) -> Option<Type> {
+        self.session.debug_data.get_type(offset).cloned()
+    }
+
+    fn get_type_by_name(&self, name: &str) -> Option<Type> {
+        self.session.debug_data.get_type_by_name(name).cloned()
+    }
+
+    fn call_function(&self, name: &str, args: &[Value]) -> Result<Value, expression::Error> {
+        let debug_data = &self.session.debug_data;
+        let func = match debug_data.get_function(name) {
+            Some(func) => func,
+            None => return Err(expression::Error::NoSymbol(name.to_string())),
+        };
+        let inf = match &self.session.inferior {
+            Some(inf) => inf,
+            None => {
+                return Err(expression::Error::Eval(
+                    "You can't do that without a process to debug.".to_string(),
+                ))
+            }
+        };
+        if !self.allow_calls {
+            return Err(expression::Error::Eval(format!(
+                "Cannot call function {} while running backwards.",
+                name
+            )));
+        }
+        let params: Vec<&Variable> = func
+            .variables
+            .iter()
+            .filter(|var| var.is_parameter)
+            .collect();
+        if args.len() < params.len() {
+            return Err(expression::Error::Eval(
+                "Too few arguments in function call.".to_string(),
+            ));
+        }
+        let mut call_args = Vec::new();
+        for (i, arg) in args.iter().enumerate() {
+            let param_type = params.get(i).map(|param| &param.entity_type);
+            let value = expression::argument(arg.clone(), param_type)?;
+            if is_floating_point(&value.ty) {
+                call_args.push(CallArgument::Float(value.bytes));
+            } else {
+                let mut raw = [0u8; 8];
+                let len = value.bytes.len().min(8);
+                raw[..len].copy_from_slice(&value.bytes[..len]);
+                call_args.push(CallArgument::Integer(u64::from_le_bytes(raw)));
+            }
+        }
+
+        let return_type = func
+            .return_type
+            .clone()
+            .unwrap_or_else(|| Type::new("void".to_string(), 0));
+        let location = ReturnLocation::of(debug_data, &return_type);
+        let memory_return_size = match location {
+            ReturnLocation::Memory => return_type.size,
+            ReturnLocation::Registers(_) => 0,
+            ReturnLocation::X87 => {
+                return Err(expression::Error::Eval(format!(
+                    "Cannot call function {}: returning {} in %st0 is not supported.",
+                    name, return_type.name
+                )))
+            }
+        };
+        let outcome = inf
+            .call_function(func.address, &call_args, memory_return_size)
+            .map_err(|err| {
+                expression::Error::Eval(format!("Cannot call function {}: {}", name, err))
+            })?;
+        let message = match outcome {
+            CallOutcome::Returned {
+                regs,
+                fpregs,
+                memory,
+            } => {
+                let bytes = match location {
+                    ReturnLocation::Registers(classes) => {
+                        ReturnLocation::read_registers(&classes, return_type.size, &regs, &fpregs)
+                    }
+                    _ => memory,
+                };
+                return Ok(Value {
+                    ty: return_type,
+                    bytes,
+                    address: None,
+                });
+            }
+            CallOutcome::Interrupted(Status::Stopped(signal::SIGTRAP, _))
+            | CallOutcome::Interrupted(Status::Syscall(_, _))
+            | CallOutcome::Interrupted(Status::Event(_)) => format!(
+                "The program being debugged stopped while in a function called from deet.\n\
+                 Evaluation of the expression containing the function ({}) will be abandoned.",
+                name
+            ),
+            CallOutcome::Interrupted(Status::Stopped(signal, _)) => format!(
+                "The program being debugged was signaled while in a function called from deet.\n\
+                 Program received signal {} in {}; the state before the call was restored.",
+                signal, name
+            ),
+            CallOutcome::Interrupted(Status::Exited(code)) => format!(
+                "Child exited (status {})\n\
+                 The program being debugged exited while in a function called from deet.",
+                code
+            ),
+            CallOutcome::Interrupted(Status::Signaled(signal)) => format!(
+                "Child exited (signal {})\n\
+                 The program being debugged was killed while in a function called from deet.",
+                signal
+            ),
+        };
+        Err(expression::Error::Eval(message))
+    }
+}
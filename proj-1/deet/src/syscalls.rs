@@ -0,0 +1,1029 @@
+//! This file contains the x86-64 Linux system call table used by `catch syscall`, and formats
+//! system calls the way strace does: `openat(AT_FDCWD, "/etc/hosts", O_RDONLY) = 3`.
+
+use nix::errno::Errno;
+use nix::sys::signal::Signal;
+use std::convert::{TryFrom, TryInto};
+
+/// Longest string or buffer shown before it is cut short with `...`, as in strace.
+pub const STRING_LIMIT: usize = 32;
+
+/// Most strings shown from an array such as argv.
+const ARRAY_LIMIT: usize = 16;
+
+/// How an argument of a system call is shown.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Arg {
+    Int,
+    /// A size or count
+    Uint,
+    /// An address, or anything we don't know how to show
+    Hex,
+    Fd,
+    /// The directory fd of the `*at` calls, which may be AT_FDCWD
+    DirFd,
+    /// A NUL-terminated string, usually a path
+    Str,
+    /// Data passed to the kernel, whose length is the argument with the given index
+    InBuf(usize),
+    /// Data filled in by the kernel. Its length is the return value, so it can only be shown
+    /// once the call returns.
+    OutBuf,
+    OpenFlags,
+    /// File permissions
+    Mode,
+    /// PROT_* flags of mmap and mprotect
+    Prot,
+    /// MAP_* flags of mmap
+    MapFlags,
+    Signal,
+    /// A NULL-terminated array of strings, like argv
+    StrArray,
+    /// An environment, which is only counted
+    Environment,
+}
+
+use Arg::*;
+
+/// Classes of system calls that can be traced together, as in strace's `-e trace=`.
+pub const FILE: u8 = 1;
+pub const NETWORK: u8 = 2;
+pub const PROCESS: u8 = 4;
+pub const SIGNAL: u8 = 8;
+pub const MEMORY: u8 = 16;
+pub const DESC: u8 = 32;
+
+pub struct Syscall {
+    pub number: usize,
+    pub name: &'static str,
+    /// Calls we have no signature for show all six argument registers in hex.
+    pub args: &'static [Arg],
+    /// The classes the call belongs to, as a bit set
+    pub classes: u8,
+}
+
+const fn call(number: usize, name: &'static str, args: &'static [Arg], classes: u8) -> Syscall {
+    Syscall {
+        number,
+        name,
+        args,
+        classes,
+    }
+}
+
+/// Every x86-64 system call, in order of number.
+static SYSCALLS: &[Syscall] = &[
+    call(0, "read", &[Fd, OutBuf, Uint], DESC),
+    call(1, "write", &[Fd, InBuf(2), Uint], DESC),
+    call(2, "open", &[Str, OpenFlags, Mode], FILE | DESC),
+    call(3, "close", &[Fd], DESC),
+    call(4, "stat", &[Str, Hex], FILE),
+    call(5, "fstat", &[Fd, Hex], DESC),
+    call(6, "lstat", &[Str, Hex], FILE),
+    call(7, "poll", &[Hex, Uint, Int], DESC),
+    call(8, "lseek", &[Fd, Int, Int], DESC),
+    call(
+        9,
+        "mmap",
+        &[Hex, Uint, Prot, MapFlags, Fd, Int],
+        MEMORY | DESC,
+    ),
+    call(10, "mprotect", &[Hex, Uint, Prot], MEMORY),
+    call(11, "munmap", &[Hex, Uint], MEMORY),
+    call(12, "brk", &[Hex], MEMORY),
+    call(13, "rt_sigaction", &[Signal, Hex, Hex, Uint], SIGNAL),
+    call(14, "rt_sigprocmask", &[Int, Hex, Hex, Uint], SIGNAL),
+    call(15, "rt_sigreturn", &[], SIGNAL),
+    call(16, "ioctl", &[Fd, Hex, Hex], DESC),
+    call(17, "pread64", &[Fd, OutBuf, Uint, Int], DESC),
+    call(18, "pwrite64", &[Fd, InBuf(2), Uint, Int], DESC),
+    call(19, "readv", &[Fd, Hex, Int], DESC),
+    call(20, "writev", &[Fd, Hex, Int], DESC),
+    call(21, "access", &[Str, Int], FILE),
+    call(22, "pipe", &[Hex], DESC),
+    call(23, "select", &[Int, Hex, Hex, Hex, Hex], DESC),
+    call(24, "sched_yield", &[], 0),
+    call(25, "mremap", &[Hex, Uint, Uint, Hex, Hex], MEMORY),
+    call(26, "msync", &[Hex, Uint, Int], MEMORY),
+    call(27, "mincore", &[Hex, Uint, Hex], MEMORY),
+    call(28, "madvise", &[Hex, Uint, Int], MEMORY),
+    call(29, "shmget", &[Int, Uint, Int], 0),
+    call(30, "shmat", &[Int, Hex, Int], MEMORY),
+    call(31, "shmctl", &[Int, Int, Hex], 0),
+    call(32, "dup", &[Fd], DESC),
+    call(33, "dup2", &[Fd, Fd], DESC),
+    call(34, "pause", &[], SIGNAL),
+    call(35, "nanosleep", &[Hex, Hex], 0),
+    call(36, "getitimer", &[Int, Hex], 0),
+    call(37, "alarm", &[Uint], 0),
+    call(38, "setitimer", &[Int, Hex, Hex], 0),
+    call(39, "getpid", &[], 0),
+    call(40, "sendfile", &[Fd, Fd, Hex, Uint], DESC | NETWORK),
+    call(41, "socket", &[Int, Int, Int], NETWORK | DESC),
+    call(42, "connect", &[Fd, Hex, Uint], NETWORK | DESC),
+    call(43, "accept", &[Fd, Hex, Hex], NETWORK | DESC),
+    call(
+        44,
+        "sendto",
+        &[Fd, InBuf(2), Uint, Int, Hex, Uint],
+        NETWORK | DESC,
+    ),
+    call(
+        45,
+        "recvfrom",
+        &[Fd, OutBuf, Uint, Int, Hex, Hex],
+        NETWORK | DESC,
+    ),
+    call(46, "sendmsg", &[Fd, Hex, Int], NETWORK | DESC),
+    call(47, "recvmsg", &[Fd, Hex, Int], NETWORK | DESC),
+    call(48, "shutdown", &[Fd, Int], NETWORK | DESC),
+    call(49, "bind", &[Fd, Hex, Uint], NETWORK | DESC),
+    call(50, "listen", &[Fd, Int], NETWORK | DESC),
+    call(51, "getsockname", &[Fd, Hex, Hex], NETWORK | DESC),
+    call(52, "getpeername", &[Fd, Hex, Hex], NETWORK | DESC),
+    call(53, "socketpair", &[Int, Int, Int, Hex], NETWORK | DESC),
+    call(54, "setsockopt", &[Fd, Int, Int, Hex, Uint], NETWORK | DESC),
+    call(55, "getsockopt", &[Fd, Int, Int, Hex, Hex], NETWORK | DESC),
+    call(56, "clone", &[Hex, Hex, Hex, Hex, Hex], PROCESS),
+    call(57, "fork", &[], PROCESS),
+    call(58, "vfork", &[], PROCESS),
+    call(59, "execve", &[Str, StrArray, Environment], FILE | PROCESS),
+    call(60, "exit", &[Int], PROCESS),
+    call(61, "wait4", &[Int, Hex, Int, Hex], PROCESS),
+    call(62, "kill", &[Int, Signal], SIGNAL | PROCESS),
+    call(63, "uname", &[Hex], 0),
+    call(64, "semget", &[Int, Int, Int], 0),
+    call(65, "semop", &[Int, Hex, Uint], 0),
+    call(66, "semctl", &[Int, Int, Int, Hex], 0),
+    call(67, "shmdt", &[Hex], MEMORY),
+    call(68, "msgget", &[Int, Int], 0),
+    call(69, "msgsnd", &[Int, Hex, Uint, Int], 0),
+    call(70, "msgrcv", &[Int, Hex, Uint, Int, Int], 0),
+    call(71, "msgctl", &[Int, Int, Hex], 0),
+    call(72, "fcntl", &[Fd, Int, Hex], DESC),
+    call(73, "flock", &[Fd, Int], DESC),
+    call(74, "fsync", &[Fd], DESC),
+    call(75, "fdatasync", &[Fd], DESC),
+    call(76, "truncate", &[Str, Int], FILE),
+    call(77, "ftruncate", &[Fd, Int], DESC),
+    call(78, "getdents", &[Fd, Hex, Uint], DESC),
+    call(79, "getcwd", &[Hex, Uint], FILE),
+    call(80, "chdir", &[Str], FILE),
+    call(81, "fchdir", &[Fd], DESC),
+    call(82, "rename", &[Str, Str], FILE),
+    call(83, "mkdir", &[Str, Mode], FILE),
+    call(84, "rmdir", &[Str], FILE),
+    call(85, "creat", &[Str, Mode], FILE | DESC),
+    call(86, "link", &[Str, Str], FILE),
+    call(87, "unlink", &[Str], FILE),
+    call(88, "symlink", &[Str, Str], FILE),
+    call(89, "readlink", &[Str, OutBuf, Uint], FILE),
+    call(90, "chmod", &[Str, Mode], FILE),
+    call(91, "fchmod", &[Fd, Mode], DESC),
+    call(92, "chown", &[Str, Int, Int], FILE),
+    call(93, "fchown", &[Fd, Int, Int], DESC),
+    call(94, "lchown", &[Str, Int, Int], FILE),
+    call(95, "umask", &[Mode], 0),
+    call(96, "gettimeofday", &[Hex, Hex], 0),
+    call(97, "getrlimit", &[Int, Hex], 0),
+    call(98, "getrusage", &[Int, Hex], 0),
+    call(99, "sysinfo", &[Hex], 0),
+    call(100, "times", &[Hex], 0),
+    call(101, "ptrace", &[Int, Int, Hex, Hex], 0),
+    call(102, "getuid", &[], 0),
+    call(103, "syslog", &[Int, Hex, Int], 0),
+    call(104, "getgid", &[], 0),
+    call(105, "setuid", &[Int], 0),
+    call(106, "setgid", &[Int], 0),
+    call(107, "geteuid", &[], 0),
+    call(108, "getegid", &[], 0),
+    call(109, "setpgid", &[Int, Int], 0),
+    call(110, "getppid", &[], 0),
+    call(111, "getpgrp", &[], 0),
+    call(112, "setsid", &[], 0),
+    call(113, "setreuid", &[Int, Int], 0),
+    call(114, "setregid", &[Int, Int], 0),
+    call(115, "getgroups", &[Int, Hex], 0),
+    call(116, "setgroups", &[Int, Hex], 0),
+    call(117, "setresuid", &[Int, Int, Int], 0),
+    call(118, "getresuid", &[Hex, Hex, Hex], 0),
+    call(119, "setresgid", &[Int, Int, Int], 0),
+    call(120, "getresgid", &[Hex, Hex, Hex], 0),
+    call(121, "getpgid", &[Int], 0),
+    call(122, "setfsuid", &[Int], 0),
+    call(123, "setfsgid", &[Int], 0),
+    call(124, "getsid", &[Int], 0),
+    call(125, "capget", &[Hex, Hex], 0),
+    call(126, "capset", &[Hex, Hex], 0),
+    call(127, "rt_sigpending", &[Hex, Uint], SIGNAL),
+    call(128, "rt_sigtimedwait", &[Hex, Hex, Hex, Uint], SIGNAL),
+    call(129, "rt_sigqueueinfo", &[Int, Signal, Hex], SIGNAL),
+    call(130, "rt_sigsuspend", &[Hex, Uint], SIGNAL),
+    call(131, "sigaltstack", &[Hex, Hex], SIGNAL),
+    call(132, "utime", &[Str, Hex], FILE),
+    call(133, "mknod", &[Str, Mode, Hex], FILE),
+    call(134, "uselib", &[Str], FILE),
+    call(135, "personality", &[Hex], 0),
+    call(136, "ustat", &[Hex, Hex], 0),
+    call(137, "statfs", &[Str, Hex], FILE),
+    call(138, "fstatfs", &[Fd, Hex], DESC),
+    call(139, "sysfs", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(140, "getpriority", &[Int, Int], 0),
+    call(141, "setpriority", &[Int, Int, Int], 0),
+    call(142, "sched_setparam", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(143, "sched_getparam", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(
+        144,
+        "sched_setscheduler",
+        &[Hex, Hex, Hex, Hex, Hex, Hex],
+        0,
+    ),
+    call(
+        145,
+        "sched_getscheduler",
+        &[Hex, Hex, Hex, Hex, Hex, Hex],
+        0,
+    ),
+    call(
+        146,
+        "sched_get_priority_max",
+        &[Hex, Hex, Hex, Hex, Hex, Hex],
+        0,
+    ),
+    call(
+        147,
+        "sched_get_priority_min",
+        &[Hex, Hex, Hex, Hex, Hex, Hex],
+        0,
+    ),
+    call(
+        148,
+        "sched_rr_get_interval",
+        &[Hex, Hex, Hex, Hex, Hex, Hex],
+        0,
+    ),
+    call(149, "mlock", &[Hex, Uint], MEMORY),
+    call(150, "munlock", &[Hex, Uint], MEMORY),
+    call(151, "mlockall", &[Int], MEMORY),
+    call(152, "munlockall", &[], MEMORY),
+    call(153, "vhangup", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(154, "modify_ldt", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(155, "pivot_root", &[Str, Str], FILE),
+    call(156, "_sysctl", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(157, "prctl", &[Int, Hex, Hex, Hex, Hex], 0),
+    call(158, "arch_prctl", &[Int, Hex], 0),
+    call(159, "adjtimex", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(160, "setrlimit", &[Int, Hex], 0),
+    call(161, "chroot", &[Str], FILE),
+    call(162, "sync", &[], 0),
+    call(163, "acct", &[Str], FILE),
+    call(164, "settimeofday", &[Hex, Hex], 0),
+    call(165, "mount", &[Str, Str, Str, Hex, Hex], FILE),
+    call(166, "umount2", &[Str, Int], FILE),
+    call(167, "swapon", &[Str, Int], FILE),
+    call(168, "swapoff", &[Str], FILE),
+    call(169, "reboot", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(170, "sethostname", &[InBuf(2), Uint], 0),
+    call(171, "setdomainname", &[InBuf(2), Uint], 0),
+    call(172, "iopl", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(173, "ioperm", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(174, "create_module", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(175, "init_module", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(176, "delete_module", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(177, "get_kernel_syms", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(178, "query_module", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(179, "quotactl", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(180, "nfsservctl", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(181, "getpmsg", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(182, "putpmsg", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(183, "afs_syscall", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(184, "tuxcall", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(185, "security", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(186, "gettid", &[], 0),
+    call(187, "readahead", &[Fd, Int, Uint], DESC),
+    call(188, "setxattr", &[Str, Str, Hex, Uint, Int], FILE),
+    call(189, "lsetxattr", &[Str, Str, Hex, Uint, Int], FILE),
+    call(190, "fsetxattr", &[Fd, Str, Hex, Uint, Int], DESC),
+    call(191, "getxattr", &[Str, Str, Hex, Uint], FILE),
+    call(192, "lgetxattr", &[Str, Str, Hex, Uint], FILE),
+    call(193, "fgetxattr", &[Fd, Str, Hex, Uint], DESC),
+    call(194, "listxattr", &[Str, Hex, Uint], FILE),
+    call(195, "llistxattr", &[Str, Hex, Uint], FILE),
+    call(196, "flistxattr", &[Fd, Hex, Uint], DESC),
+    call(197, "removexattr", &[Str, Str], FILE),
+    call(198, "lremovexattr", &[Str, Str], FILE),
+    call(199, "fremovexattr", &[Fd, Str], DESC),
+    call(200, "tkill", &[Int, Signal], SIGNAL),
+    call(201, "time", &[Hex], 0),
+    call(202, "futex", &[Hex, Int, Hex, Hex, Hex, Int], 0),
+    call(203, "sched_setaffinity", &[Int, Uint, Hex], 0),
+    call(204, "sched_getaffinity", &[Int, Uint, Hex], 0),
+    call(205, "set_thread_area", &[Hex], 0),
+    call(206, "io_setup", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(207, "io_destroy", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(208, "io_getevents", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(209, "io_submit", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(210, "io_cancel", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(211, "get_thread_area", &[Hex], 0),
+    call(212, "lookup_dcookie", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(213, "epoll_create", &[Int], DESC),
+    call(214, "epoll_ctl_old", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(215, "epoll_wait_old", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(216, "remap_file_pages", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(217, "getdents64", &[Fd, Hex, Uint], DESC),
+    call(218, "set_tid_address", &[Hex], 0),
+    call(219, "restart_syscall", &[], 0),
+    call(220, "semtimedop", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(221, "fadvise64", &[Fd, Int, Uint, Int], DESC),
+    call(222, "timer_create", &[Int, Hex, Hex], 0),
+    call(223, "timer_settime", &[Int, Int, Hex, Hex], 0),
+    call(224, "timer_gettime", &[Int, Hex], 0),
+    call(225, "timer_getoverrun", &[Int], 0),
+    call(226, "timer_delete", &[Int], 0),
+    call(227, "clock_settime", &[Int, Hex], 0),
+    call(228, "clock_gettime", &[Int, Hex], 0),
+    call(229, "clock_getres", &[Int, Hex], 0),
+    call(230, "clock_nanosleep", &[Int, Int, Hex, Hex], 0),
+    call(231, "exit_group", &[Int], PROCESS),
+    call(232, "epoll_wait", &[Fd, Hex, Int, Int], DESC),
+    call(233, "epoll_ctl", &[Fd, Int, Fd, Hex], DESC),
+    call(234, "tgkill", &[Int, Int, Signal], SIGNAL),
+    call(235, "utimes", &[Str, Hex], FILE),
+    call(236, "vserver", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(237, "mbind", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(238, "set_mempolicy", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(239, "get_mempolicy", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(240, "mq_open", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(241, "mq_unlink", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(242, "mq_timedsend", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(243, "mq_timedreceive", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(244, "mq_notify", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(245, "mq_getsetattr", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(246, "kexec_load", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(247, "waitid", &[Int, Int, Hex, Int, Hex], PROCESS),
+    call(248, "add_key", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(249, "request_key", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(250, "keyctl", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(251, "ioprio_set", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(252, "ioprio_get", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(253, "inotify_init", &[], DESC),
+    call(254, "inotify_add_watch", &[Fd, Str, Hex], FILE | DESC),
+    call(255, "inotify_rm_watch", &[Fd, Int], DESC),
+    call(256, "migrate_pages", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(257, "openat", &[DirFd, Str, OpenFlags, Mode], FILE | DESC),
+    call(258, "mkdirat", &[DirFd, Str, Mode], FILE | DESC),
+    call(259, "mknodat", &[DirFd, Str, Mode, Hex], FILE | DESC),
+    call(260, "fchownat", &[DirFd, Str, Int, Int, Int], FILE | DESC),
+    call(261, "futimesat", &[DirFd, Str, Hex], FILE | DESC),
+    call(262, "newfstatat", &[DirFd, Str, Hex, Int], FILE | DESC),
+    call(263, "unlinkat", &[DirFd, Str, Int], FILE | DESC),
+    call(264, "renameat", &[DirFd, Str, DirFd, Str], FILE | DESC),
+    call(265, "linkat", &[DirFd, Str, DirFd, Str, Int], FILE | DESC),
+    call(266, "symlinkat", &[Str, DirFd, Str], FILE | DESC),
+    call(267, "readlinkat", &[DirFd, Str, OutBuf, Uint], FILE | DESC),
+    call(268, "fchmodat", &[DirFd, Str, Mode], FILE | DESC),
+    call(269, "faccessat", &[DirFd, Str, Int], FILE | DESC),
+    call(270, "pselect6", &[Int, Hex, Hex, Hex, Hex, Hex], DESC),
+    call(271, "ppoll", &[Hex, Uint, Hex, Hex, Uint], DESC),
+    call(272, "unshare", &[Hex], PROCESS),
+    call(273, "set_robust_list", &[Hex, Uint], 0),
+    call(274, "get_robust_list", &[Int, Hex, Hex], 0),
+    call(275, "splice", &[Fd, Hex, Fd, Hex, Uint, Hex], DESC),
+    call(276, "tee", &[Fd, Fd, Uint, Hex], DESC),
+    call(277, "sync_file_range", &[Fd, Int, Int, Hex], DESC),
+    call(278, "vmsplice", &[Fd, Hex, Uint, Hex], DESC),
+    call(279, "move_pages", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(280, "utimensat", &[DirFd, Str, Hex, Int], FILE | DESC),
+    call(281, "epoll_pwait", &[Fd, Hex, Int, Int, Hex, Uint], DESC),
+    call(282, "signalfd", &[Fd, Hex, Uint], DESC | SIGNAL),
+    call(283, "timerfd_create", &[Int, Int], DESC),
+    call(284, "eventfd", &[Uint], DESC),
+    call(285, "fallocate", &[Fd, Int, Int, Int], DESC),
+    call(286, "timerfd_settime", &[Fd, Int, Hex, Hex], DESC),
+    call(287, "timerfd_gettime", &[Fd, Hex], DESC),
+    call(288, "accept4", &[Fd, Hex, Hex, Int], NETWORK | DESC),
+    call(289, "signalfd4", &[Fd, Hex, Uint, Hex], DESC | SIGNAL),
+    call(290, "eventfd2", &[Uint, Hex], DESC),
+    call(291, "epoll_create1", &[Hex], DESC),
+    call(292, "dup3", &[Fd, Fd, OpenFlags], DESC),
+    call(293, "pipe2", &[Hex, OpenFlags], DESC),
+    call(294, "inotify_init1", &[OpenFlags], DESC),
+    call(295, "preadv", &[Fd, Hex, Int, Int], DESC),
+    call(296, "pwritev", &[Fd, Hex, Int, Int], DESC),
+    call(297, "rt_tgsigqueueinfo", &[Int, Int, Signal, Hex], SIGNAL),
+    call(298, "perf_event_open", &[Hex, Int, Int, Fd, Hex], DESC),
+    call(299, "recvmmsg", &[Fd, Hex, Uint, Hex, Hex], NETWORK | DESC),
+    call(300, "fanotify_init", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(301, "fanotify_mark", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(302, "prlimit64", &[Int, Int, Hex, Hex], 0),
+    call(
+        303,
+        "name_to_handle_at",
+        &[DirFd, Str, Hex, Hex, Int],
+        FILE | DESC,
+    ),
+    call(304, "open_by_handle_at", &[Fd, Hex, OpenFlags], DESC),
+    call(305, "clock_adjtime", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(306, "syncfs", &[Fd], DESC),
+    call(307, "sendmmsg", &[Fd, Hex, Uint, Hex], NETWORK | DESC),
+    call(308, "setns", &[Fd, Int], DESC),
+    call(309, "getcpu", &[Hex, Hex, Hex], 0),
+    call(
+        310,
+        "process_vm_readv",
+        &[Int, Hex, Uint, Hex, Uint, Hex],
+        0,
+    ),
+    call(
+        311,
+        "process_vm_writev",
+        &[Int, Hex, Uint, Hex, Uint, Hex],
+        0,
+    ),
+    call(312, "kcmp", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(313, "finit_module", &[Fd, Str, Int], DESC),
+    call(314, "sched_setattr", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(315, "sched_getattr", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(
+        316,
+        "renameat2",
+        &[DirFd, Str, DirFd, Str, Hex],
+        FILE | DESC,
+    ),
+    call(317, "seccomp", &[Uint, Uint, Hex], 0),
+    call(318, "getrandom", &[OutBuf, Uint, Hex], 0),
+    call(319, "memfd_create", &[Str, Hex], DESC),
+    call(320, "kexec_file_load", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(321, "bpf", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(
+        322,
+        "execveat",
+        &[DirFd, Str, StrArray, Environment, Hex],
+        FILE | DESC | PROCESS,
+    ),
+    call(323, "userfaultfd", &[Hex], DESC),
+    call(324, "membarrier", &[Int, Int], 0),
+    call(325, "mlock2", &[Hex, Uint, Hex], MEMORY),
+    call(326, "copy_file_range", &[Fd, Hex, Fd, Hex, Uint, Hex], DESC),
+    call(327, "preadv2", &[Fd, Hex, Int, Int, Hex], DESC),
+    call(328, "pwritev2", &[Fd, Hex, Int, Int, Hex], DESC),
+    call(329, "pkey_mprotect", &[Hex, Uint, Prot, Int], MEMORY),
+    call(330, "pkey_alloc", &[Hex, Hex], 0),
+    call(331, "pkey_free", &[Int], 0),
+    call(332, "statx", &[DirFd, Str, Hex, Hex, Hex], FILE | DESC),
+    call(333, "io_pgetevents", &[Hex, Hex, Hex, Hex, Hex, Hex], 0),
+    call(334, "rseq", &[Hex, Uint, Int, Hex], 0),
+    call(
+        424,
+        "pidfd_send_signal",
+        &[Fd, Signal, Hex, Hex],
+        DESC | SIGNAL,
+    ),
+    call(425, "io_uring_setup", &[Uint, Hex], DESC),
+    call(
+        426,
+        "io_uring_enter",
+        &[Fd, Uint, Uint, Hex, Hex, Uint],
+        DESC,
+    ),
+    call(427, "io_uring_register", &[Fd, Uint, Hex, Uint], DESC),
+    call(428, "open_tree", &[DirFd, Str, Hex], FILE | DESC),
+    call(
+        429,
+        "move_mount",
+        &[DirFd, Str, DirFd, Str, Hex],
+        FILE | DESC,
+    ),
+    call(430, "fsopen", &[Str, Hex], DESC),
+    call(431, "fsconfig", &[Fd, Uint, Str, Hex, Int], DESC),
+    call(432, "fsmount", &[Fd, Hex, Hex], DESC),
+    call(433, "fspick", &[DirFd, Str, Hex], FILE | DESC),
+    call(434, "pidfd_open", &[Int, Hex], DESC),
+    call(435, "clone3", &[Hex, Uint], PROCESS),
+    call(436, "close_range", &[Uint, Uint, Hex], DESC),
+    call(437, "openat2", &[DirFd, Str, Hex, Uint], FILE | DESC),
+    call(438, "pidfd_getfd", &[Fd, Fd, Hex], DESC),
+    call(439, "faccessat2", &[DirFd, Str, Int, Int], FILE | DESC),
+    call(440, "process_madvise", &[Fd, Hex, Uint, Int, Hex], DESC),
+    call(441, "epoll_pwait2", &[Fd, Hex, Int, Hex, Hex, Uint], DESC),
+    call(
+        442,
+        "mount_setattr",
+        &[DirFd, Str, Hex, Hex, Uint],
+        FILE | DESC,
+    ),
+    call(443, "quotactl_fd", &[Fd, Int, Int, Hex], DESC),
+    call(444, "landlock_create_ruleset", &[Hex, Uint, Hex], DESC),
+    call(445, "landlock_add_rule", &[Fd, Int, Hex, Hex], DESC),
+    call(446, "landlock_restrict_self", &[Fd, Hex], DESC),
+    call(447, "memfd_secret", &[Hex], DESC),
+    call(448, "process_mrelease", &[Fd, Hex], DESC),
+    call(449, "futex_waitv", &[Hex, Uint, Hex, Hex, Int], 0),
+    call(
+        450,
+        "set_mempolicy_home_node",
+        &[Hex, Hex, Hex, Hex, Hex, Hex],
+        0,
+    ),
+];
+
+/// Looks up a system call by number.
+pub fn lookup(number: usize) -> Option<&'static Syscall> {
+    SYSCALLS
+        .binary_search_by_key(&number, |syscall| syscall.number)
+        .ok()
+        .map(|index| &SYSCALLS[index])
+}
+
+/// Looks up a system call by name.
+pub fn lookup_name(name: &str) -> Option<&'static Syscall> {
+    SYSCALLS.iter().find(|syscall| syscall.name == name)
+}
+
+/// Returns the name of a system call, or `syscall_N` for numbers we don't know.
+pub fn name(number: usize) -> String {
+    match lookup(number) {
+        Some(syscall) => syscall.name.to_string(),
+        None => format!("syscall_{}", number),
+    }
+}
+
+/// Returns the class bit for a name accepted by `-e trace=`.
+pub fn class(name: &str) -> Option<u8> {
+    match name {
+        "file" | "%file" => Some(FILE),
+        "network" | "%network" | "%net" => Some(NETWORK),
+        "process" | "%process" => Some(PROCESS),
+        "signal" | "%signal" => Some(SIGNAL),
+        "memory" | "%memory" => Some(MEMORY),
+        "desc" | "%desc" => Some(DESC),
+        _ => None,
+    }
+}
+
+/// Formats the arguments of a call to system call `number`, reading the memory they point to
+/// with `read`. `ret` is the value the call returned, if it has; buffers the kernel fills in are
+/// only shown after a successful return.
+pub fn format_arguments(
+    number: usize,
+    args: &[u64; 6],
+    ret: Option<i64>,
+    read: &dyn Fn(usize, usize) -> Option<Vec<u8>>,
+) -> Vec<String> {
+    let kinds = match lookup(number) {
+        Some(syscall) => syscall.args,
+        None => &[Hex, Hex, Hex, Hex, Hex, Hex],
+    };
+    // The mode of open() is only used, and shown, when a file may be created
+    let creates = |flags: u64| flags as i32 & (libc::O_CREAT | libc::O_TMPFILE) != 0;
+    let kinds = match kinds.iter().position(|kind| *kind == OpenFlags) {
+        Some(flags) if !creates(args[flags]) && kinds.get(flags + 1) == Some(&Mode) => {
+            &kinds[..=flags]
+        }
+        _ => kinds,
+    };
+    kinds
+        .iter()
+        .zip(args.iter())
+        .map(|(kind, &value)| match *kind {
+            Int => (value as i64).to_string(),
+            Uint => value.to_string(),
+            Hex => format_pointer(value),
+            Fd => (value as i32).to_string(),
+            DirFd if value as i32 == libc::AT_FDCWD => "AT_FDCWD".to_string(),
+            DirFd => (value as i32).to_string(),
+            Str => format_string(value, read),
+            InBuf(len) => format_buffer(value, args[len] as usize, read),
+            OutBuf => match ret {
+                Some(len) if len >= 0 => format_buffer(value, len as usize, read),
+                _ => format_pointer(value),
+            },
+            OpenFlags => format_open_flags(value),
+            Mode if value == 0 => "0".to_string(),
+            Mode => format!("0{:o}", value),
+            Prot => format_flags(value, PROT_FLAGS, "PROT_NONE"),
+            MapFlags => format_flags(value, MAP_FLAGS, "0"),
+            Signal => format_signal(value as i32),
+            StrArray => format_string_array(value, read),
+            Environment => format_environment(value, read),
+        })
+        .collect()
+}
+
+/// Fills in the arguments formatted on entry to a call that the kernel has written to since.
+/// The rest are left as they were, since their memory may be gone by the time the call returns
+/// (as for execve).
+pub fn update_arguments(
+    number: usize,
+    formatted: &mut [String],
+    args: &[u64; 6],
+    ret: i64,
+    read: &dyn Fn(usize, usize) -> Option<Vec<u8>>,
+) {
+    let kinds = match lookup(number) {
+        Some(syscall) => syscall.args,
+        None => return,
+    };
+    if !kinds.contains(&OutBuf) {
+        return;
+    }
+    let returned = format_arguments(number, args, Some(ret), read);
+    for (index, kind) in kinds.iter().enumerate() {
+        if *kind == OutBuf && index < formatted.len() {
+            formatted[index] = returned[index].clone();
+        }
+    }
+}
+
+/// Formats a whole call, e.g. `write(1, "hi\n", 3)`.
+pub fn format_call(
+    number: usize,
+    args: &[u64; 6],
+    ret: Option<i64>,
+    read: &dyn Fn(usize, usize) -> Option<Vec<u8>>,
+) -> String {
+    format!(
+        "{}({})",
+        name(number),
+        format_arguments(number, args, ret, read).join(", ")
+    )
+}
+
+/// Formats the value a system call returned. Errors come back from the kernel as -errno, which
+/// is shown the way the C library would report it.
+pub fn format_return(number: usize, ret: i64) -> String {
+    if (-4095..0).contains(&ret) {
+        let errno = -ret as i32;
+        return match Errno::from_i32(errno) {
+            Errno::UnknownErrno => format!("-1 (errno {})", errno),
+            known => format!("-1 {:?} ({})", known, known.desc()),
+        };
+    }
+    let returns_address = match lookup(number) {
+        Some(syscall) => ["mmap", "mremap", "brk", "shmat"].contains(&syscall.name),
+        None => false,
+    };
+    if returns_address {
+        format!("{:#x}", ret)
+    } else {
+        ret.to_string()
+    }
+}
+
+fn format_pointer(value: u64) -> String {
+    if value == 0 {
+        "NULL".to_string()
+    } else {
+        format!("{:#x}", value)
+    }
+}
+
+fn format_signal(number: i32) -> String {
+    match Signal::try_from(number) {
+        Ok(signal) => signal.to_string(),
+        Err(_) => number.to_string(),
+    }
+}
+
+/// Quotes bytes the way strace does, cutting them short after STRING_LIMIT bytes.
+fn quote(bytes: &[u8], truncated: bool) -> String {
+    let mut quoted = String::from("\"");
+    for &byte in bytes.iter().take(STRING_LIMIT) {
+        match byte {
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b'\n' => quoted.push_str("\\n"),
+            b'\t' => quoted.push_str("\\t"),
+            b'\r' => quoted.push_str("\\r"),
+            0x20..=0x7e => quoted.push(byte as char),
+            _ => quoted.push_str(&format!("\\{:o}", byte)),
+        }
+    }
+    quoted.push('"');
+    if truncated || bytes.len() > STRING_LIMIT {
+        quoted.push_str("...");
+    }
+    quoted
+}
+
+/// Reads a NUL-terminated string of at most STRING_LIMIT bytes, a word at a time so that a
+/// string near the end of a mapping can still be read. Returns the bytes and whether the string
+/// goes on past the limit.
+fn read_string(
+    addr: usize,
+    read: &dyn Fn(usize, usize) -> Option<Vec<u8>>,
+) -> Option<(Vec<u8>, bool)> {
+    let mut bytes = Vec::new();
+    while bytes.len() <= STRING_LIMIT {
+        let chunk = read(addr + bytes.len(), 8)?;
+        match chunk.iter().position(|&byte| byte == 0) {
+            Some(end) => {
+                bytes.extend_from_slice(&chunk[..end]);
+                return Some((bytes, false));
+            }
+            None => bytes.extend_from_slice(&chunk),
+        }
+    }
+    bytes.truncate(STRING_LIMIT);
+    Some((bytes, true))
+}
+
+fn format_string(addr: u64, read: &dyn Fn(usize, usize) -> Option<Vec<u8>>) -> String {
+    if addr == 0 {
+        return "NULL".to_string();
+    }
+    match read_string(addr as usize, read) {
+        Some((bytes, truncated)) => quote(&bytes, truncated),
+        None => format_pointer(addr),
+    }
+}
+
+fn format_buffer(addr: u64, len: usize, read: &dyn Fn(usize, usize) -> Option<Vec<u8>>) -> String {
+    if addr == 0 {
+        return "NULL".to_string();
+    }
+    match read(addr as usize, len.min(STRING_LIMIT)) {
+        Some(bytes) => quote(&bytes, len > STRING_LIMIT),
+        None => format_pointer(addr),
+    }
+}
+
+fn format_string_array(addr: u64, read: &dyn Fn(usize, usize) -> Option<Vec<u8>>) -> String {
+    if addr == 0 {
+        return "NULL".to_string();
+    }
+    let mut strings = Vec::new();
+    loop {
+        let pointer = match read(addr as usize + 8 * strings.len(), 8) {
+            Some(bytes) => u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            None => return format_pointer(addr),
+        };
+        if pointer == 0 {
+            break;
+        }
+        if strings.len() == ARRAY_LIMIT {
+            strings.push("...".to_string());
+            break;
+        }
+        strings.push(format_string(pointer, read));
+    }
+    format!("[{}]", strings.join(", "))
+}
+
+/// Shows where an environment is and how many variables it has, as in `0x7ffd... /* 20 vars */`.
+fn format_environment(addr: u64, read: &dyn Fn(usize, usize) -> Option<Vec<u8>>) -> String {
+    if addr == 0 {
+        return "NULL".to_string();
+    }
+    let mut count = 0;
+    loop {
+        match read(addr as usize + 8 * count, 8) {
+            Some(bytes) if bytes.iter().any(|&byte| byte != 0) => count += 1,
+            Some(_) => break,
+            None => return format_pointer(addr),
+        }
+    }
+    format!("{:#x} /* {} vars */", addr, count)
+}
+
+const OPEN_FLAGS: &[(i32, &str)] = &[
+    (libc::O_CREAT, "O_CREAT"),
+    (libc::O_EXCL, "O_EXCL"),
+    (libc::O_NOCTTY, "O_NOCTTY"),
+    (libc::O_TRUNC, "O_TRUNC"),
+    (libc::O_APPEND, "O_APPEND"),
+    (libc::O_NONBLOCK, "O_NONBLOCK"),
+    (libc::O_DSYNC, "O_DSYNC"),
+    (libc::O_ASYNC, "O_ASYNC"),
+    (libc::O_DIRECT, "O_DIRECT"),
+    (libc::O_LARGEFILE, "O_LARGEFILE"),
+    (libc::O_DIRECTORY, "O_DIRECTORY"),
+    (libc::O_NOFOLLOW, "O_NOFOLLOW"),
+    (libc::O_NOATIME, "O_NOATIME"),
+    (libc::O_CLOEXEC, "O_CLOEXEC"),
+    (libc::O_PATH, "O_PATH"),
+    (libc::O_TMPFILE & !libc::O_DIRECTORY, "O_TMPFILE"),
+    (libc::O_SYNC & !libc::O_DSYNC, "O_SYNC"),
+];
+
+const PROT_FLAGS: &[(i32, &str)] = &[
+    (libc::PROT_READ, "PROT_READ"),
+    (libc::PROT_WRITE, "PROT_WRITE"),
+    (libc::PROT_EXEC, "PROT_EXEC"),
+];
+
+const MAP_FLAGS: &[(i32, &str)] = &[
+    (libc::MAP_SHARED, "MAP_SHARED"),
+    (libc::MAP_PRIVATE, "MAP_PRIVATE"),
+    (libc::MAP_FIXED, "MAP_FIXED"),
+    (libc::MAP_ANONYMOUS, "MAP_ANONYMOUS"),
+    (libc::MAP_GROWSDOWN, "MAP_GROWSDOWN"),
+    (libc::MAP_DENYWRITE, "MAP_DENYWRITE"),
+    (libc::MAP_EXECUTABLE, "MAP_EXECUTABLE"),
+    (libc::MAP_LOCKED, "MAP_LOCKED"),
+    (libc::MAP_NORESERVE, "MAP_NORESERVE"),
+    (libc::MAP_POPULATE, "MAP_POPULATE"),
+    (libc::MAP_NONBLOCK, "MAP_NONBLOCK"),
+    (libc::MAP_STACK, "MAP_STACK"),
+    (libc::MAP_HUGETLB, "MAP_HUGETLB"),
+];
+
+/// Shows the names of the flags set in `value`, then whatever bits are left in hex.
+fn format_flags(value: u64, flags: &[(i32, &str)], none: &str) -> String {
+    let mut names = Vec::new();
+    let mut rest = value;
+    for &(flag, name) in flags {
+        if rest & flag as u64 != 0 {
+            names.push(name.to_string());
+            rest &= !(flag as u64);
+        }
+    }
+    if rest != 0 {
+        names.push(format!("{:#x}", rest));
+    }
+    if names.is_empty() {
+        none.to_string()
+    } else {
+        names.join("|")
+    }
+}
+
+fn format_open_flags(value: u64) -> String {
+    let access = match value as i32 & libc::O_ACCMODE {
+        libc::O_RDONLY => "O_RDONLY",
+        libc::O_WRONLY => "O_WRONLY",
+        _ => "O_RDWR",
+    };
+    let rest = value & !(libc::O_ACCMODE as u64);
+    if rest == 0 {
+        access.to_string()
+    } else {
+        format!("{}|{}", access, format_flags(rest, OPEN_FLAGS, "0"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: usize = 0x1000;
+
+    /// Inferior memory for the arguments of a call, which data is put in one piece at a time.
+    #[derive(Default)]
+    struct Memory {
+        bytes: Vec<u8>,
+    }
+
+    impl Memory {
+        fn put(&mut self, data: &[u8]) -> u64 {
+            let addr = BASE + self.bytes.len();
+            self.bytes.extend_from_slice(data);
+            self.bytes.resize((self.bytes.len() + 8) & !7, 0);
+            addr as u64
+        }
+
+        fn put_pointers(&mut self, pointers: &[u64]) -> u64 {
+            let bytes: Vec<u8> = pointers
+                .iter()
+                .flat_map(|p| p.to_le_bytes().to_vec())
+                .collect();
+            self.put(&bytes)
+        }
+
+        fn read(&self, addr: usize, len: usize) -> Option<Vec<u8>> {
+            let start = addr.checked_sub(BASE)?;
+            self.bytes
+                .get(start..start + len)
+                .map(|bytes| bytes.to_vec())
+        }
+
+        fn call(&self, number: usize, args: &[u64], ret: Option<i64>) -> String {
+            let mut registers = [0; 6];
+            registers[..args.len()].copy_from_slice(args);
+            format_call(number, &registers, ret, &|addr, len| self.read(addr, len))
+        }
+    }
+
+    #[test]
+    fn paths_and_open_flags() {
+        let mut memory = Memory::default();
+        let hosts = memory.put(b"/etc/hosts\0");
+        let out = memory.put(b"out\0");
+        let at_fdcwd = libc::AT_FDCWD as u64;
+        let flags = (libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC | libc::O_CLOEXEC) as u64;
+        assert_eq!(
+            memory.call(257, &[at_fdcwd, hosts, 0, 0o644], None),
+            "openat(AT_FDCWD, \"/etc/hosts\", O_RDONLY)"
+        );
+        assert_eq!(
+            memory.call(257, &[3, out, flags, 0o644], None),
+            "openat(3, \"out\", O_WRONLY|O_CREAT|O_TRUNC|O_CLOEXEC, 0644)"
+        );
+        assert_eq!(
+            memory.call(2, &[out, libc::O_RDWR as u64 | 0x4000_0000], None),
+            "open(\"out\", O_RDWR|0x40000000)"
+        );
+        // Strings that can't be read are shown as addresses
+        assert_eq!(memory.call(2, &[0x10, 0, 0], None), "open(0x10, O_RDONLY)");
+        assert_eq!(memory.call(4, &[0, 0], None), "stat(NULL, NULL)");
+    }
+
+    #[test]
+    fn buffers_and_strings() {
+        let mut memory = Memory::default();
+        let hi = memory.put(b"hi\n");
+        let long = memory.put(&[b'a'; 40]);
+        let odd = memory.put(b"\x01\"\\\t\0");
+        let program = memory.put(b"true\0");
+        let option = memory.put(b"-x\0");
+        let argv = memory.put_pointers(&[program, option, 0]);
+        let envp = memory.put_pointers(&[1, 2, 0]);
+        assert_eq!(memory.call(1, &[1, hi, 3], None), "write(1, \"hi\\n\", 3)");
+        assert_eq!(
+            memory.call(1, &[2, long, 40], None),
+            format!("write(2, \"{}\"..., 40)", "a".repeat(STRING_LIMIT))
+        );
+        assert_eq!(
+            memory.call(1, &[1, odd, 4], None),
+            "write(1, \"\\1\\\"\\\\\\t\", 4)"
+        );
+        // Read buffers are only shown once the call has filled them in
+        assert_eq!(
+            memory.call(0, &[0, hi, 10], None),
+            format!("read(0, {:#x}, 10)", hi)
+        );
+        assert_eq!(memory.call(0, &[0, hi, 10], Some(2)), "read(0, \"hi\", 10)");
+        assert_eq!(
+            memory.call(0, &[0, hi, 10], Some(-libc::EAGAIN as i64)),
+            format!("read(0, {:#x}, 10)", hi)
+        );
+        assert_eq!(
+            memory.call(59, &[program, argv, envp], None),
+            format!(
+                "execve(\"true\", [\"true\", \"-x\"], {:#x} /* 2 vars */)",
+                envp
+            )
+        );
+    }
+
+    #[test]
+    fn updating_arguments_on_return() {
+        let mut memory = Memory::default();
+        let buffer = memory.put(b"data");
+        let args = [0, buffer, 4, 0, 0, 0];
+        let read = |addr, len| memory.read(addr, len);
+        let mut formatted = format_arguments(0, &args, None, &read);
+        update_arguments(0, &mut formatted, &args, 4, &read);
+        assert_eq!(formatted, ["0", "\"data\"", "4"]);
+    }
+
+    #[test]
+    fn memory_signals_and_unknown_calls() {
+        let memory = Memory::default();
+        let prot = (libc::PROT_READ | libc::PROT_WRITE) as u64;
+        let flags = (libc::MAP_PRIVATE | libc::MAP_ANONYMOUS) as u64;
+        assert_eq!(
+            memory.call(9, &[0, 8192, prot, flags, -1i64 as u64, 0], None),
+            "mmap(NULL, 8192, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0)"
+        );
+        assert_eq!(
+            memory.call(10, &[0x1000, 4096, 0], None),
+            "mprotect(0x1000, 4096, PROT_NONE)"
+        );
+        assert_eq!(memory.call(62, &[42, 10], None), "kill(42, SIGUSR1)");
+        assert_eq!(memory.call(62, &[42, 99], None), "kill(42, 99)");
+        assert_eq!(
+            memory.call(1000, &[1, 0, 0x20], None),
+            "syscall_1000(0x1, NULL, 0x20, NULL, NULL, NULL)"
+        );
+    }
+
+    #[test]
+    fn return_values_and_errors() {
+        assert_eq!(format_return(0, 3), "3");
+        assert_eq!(format_return(9, 0x7f00_0000), "0x7f000000");
+        assert_eq!(
+            format_return(2, -libc::ENOENT as i64),
+            "-1 ENOENT (No such file or directory)"
+        );
+        assert_eq!(format_return(2, -4000), "-1 (errno 4000)");
+        // Negative values below -4095 aren't errors
+        assert_eq!(format_return(8, -5000), "-5000");
+    }
+
+    #[test]
+    fn names_and_classes() {
+        assert_eq!(
+            lookup_name("openat").map(|syscall| syscall.number),
+            Some(257)
+        );
+        assert_eq!(name(231), "exit_group");
+        assert_eq!(name(1000), "syscall_1000");
+        assert_eq!(class("%net"), Some(NETWORK));
+        assert_eq!(class("files"), None);
+        // The table is sorted, so that lookup can search it
+        assert!(SYSCALLS
+            .windows(2)
+            .all(|pair| pair[0].number < pair[1].number));
+    }
+}
@@ -0,0 +1,258 @@
+//! This file contains `deet --trace-syscalls`, which runs a program without the interactive
+//! prompt and prints every system call it makes, the way strace does. Each traced process gets
+//! an Inferior; with `-f` the children they fork are traced too, so stops are collected from
+//! all of them with `wait_any`.
+
+use crate::inferior::{self, Event, Inferior, Launch, Status};
+use crate::syscalls;
+use nix::unistd::Pid;
+use std::collections::{HashMap, HashSet};
+
+/// What `--trace-syscalls` was asked to do.
+#[derive(Default)]
+pub struct TraceOptions {
+    /// Trace forked children as well (`-f`)
+    pub follow_forks: bool,
+    /// Only show these system calls (`-e trace=`), as classes and call numbers
+    pub filter: Option<(u8, Vec<usize>)>,
+}
+
+impl TraceOptions {
+    /// Parses the set of a `-e trace=file,network,openat` option, saying what is wrong with it.
+    pub fn parse_filter(expression: &str) -> Result<(u8, Vec<usize>), String> {
+        let set = if expression.starts_with("trace=") {
+            &expression["trace=".len()..]
+        } else {
+            expression
+        };
+        let mut classes = 0;
+        let mut numbers = Vec::new();
+        for name in set.split(',') {
+            if let Some(class) = syscalls::class(name) {
+                classes |= class;
+            } else if let Some(syscall) = syscalls::lookup_name(name) {
+                numbers.push(syscall.number);
+            } else {
+                return Err(format!("invalid system call '{}'", name));
+            }
+        }
+        Ok((classes, numbers))
+    }
+
+    fn shows(&self, number: usize) -> bool {
+        match &self.filter {
+            None => true,
+            Some((classes, numbers)) => {
+                numbers.contains(&number)
+                    || syscalls::lookup(number)
+                        .map_or(false, |syscall| syscall.classes & classes != 0)
+            }
+        }
+    }
+}
+
+/// A traced process.
+struct Tracee {
+    inferior: Inferior,
+    /// The system call it is in and its arguments as formatted on entry, if it is to be shown
+    call: Option<(usize, [u64; 6], Vec<String>)>,
+    /// False until a followed child has reported its first stop
+    started: bool,
+}
+
+impl Tracee {
+    fn new(inferior: Inferior, started: bool) -> Tracee {
+        Tracee {
+            inferior,
+            call: None,
+            started,
+        }
+    }
+}
+
+/// Runs `target` with `args`, printing its system calls to stderr. Returns the exit status deet
+/// should exit with, which is the traced program's.
+pub fn trace_syscalls(target: &str, args: &Vec<String>, options: &TraceOptions) -> i32 {
+    let mut inferior = match Inferior::new(target, args, &Vec::new(), &Launch::default()) {
+        Ok(inferior) => inferior,
+        Err(err) => {
+            eprintln!("deet: Could not start {}: {}", target, err);
+            return 1;
+        }
+    };
+    inferior.set_syscall_stops(true);
+    if options.follow_forks {
+        inferior.set_follow_forks();
+    }
+    let main_pid = inferior.pid();
+    let mut tracer = Tracer {
+        options,
+        main_pid,
+        tracees: HashMap::new(),
+        early_children: HashSet::new(),
+        exit_status: 0,
+    };
+    if let Err(err) = inferior.resume_to_syscall(None) {
+        eprintln!("deet: Could not start {}: {}", target, err);
+        return 1;
+    }
+    tracer.tracees.insert(main_pid, Tracee::new(inferior, true));
+    while !tracer.tracees.is_empty() {
+        let status = match inferior::wait_any() {
+            Ok(status) => status,
+            Err(err) => {
+                eprintln!("deet: waitpid: {}", err);
+                break;
+            }
+        };
+        if let Some(pid) = status.pid() {
+            if let Err(err) = tracer.handle(pid, status) {
+                eprintln!("deet: Lost process {}: {}", pid, err);
+                tracer.tracees.remove(&pid);
+            }
+        }
+    }
+    tracer.exit_status
+}
+
+struct Tracer<'a> {
+    options: &'a TraceOptions,
+    main_pid: Pid,
+    tracees: HashMap<Pid, Tracee>,
+    /// Children whose first stop came before their parent's fork event
+    early_children: HashSet<Pid>,
+    exit_status: i32,
+}
+
+impl Tracer<'_> {
+    /// Prefixes lines about processes other than the one we started, as strace -f does.
+    fn prefix(&self, pid: Pid) -> String {
+        if pid == self.main_pid {
+            String::new()
+        } else {
+            format!("[pid {:>5}] ", pid)
+        }
+    }
+
+    /// Deals with a change of state of process `pid` and lets it run on if it still can.
+    fn handle(&mut self, pid: Pid, status: nix::sys::wait::WaitStatus) -> Result<(), nix::Error> {
+        let tracee = match self.tracees.get_mut(&pid) {
+            Some(tracee) => tracee,
+            None => {
+                // A followed child can stop before its parent's fork event is seen
+                self.early_children.insert(pid);
+                return Ok(());
+            }
+        };
+        if !tracee.started {
+            tracee.started = true;
+            return tracee.inferior.resume_to_syscall(None);
+        }
+        let mut signal = None;
+        match tracee.inferior.status(status)? {
+            Status::Syscall(true, number) => {
+                if self.options.shows(number) {
+                    let regs = tracee.inferior.registers()?;
+                    let args = [regs.rdi, regs.rsi, regs.rdx, regs.r10, regs.r8, regs.r9];
+                    let inf = &tracee.inferior;
+                    let read = |addr, len| inf.read_memory(addr, len).ok();
+                    let formatted = syscalls::format_arguments(number, &args, None, &read);
+                    tracee.call = Some((number, args, formatted));
+                }
+            }
+            Status::Syscall(false, number) => {
+                if let Some((entered, args, mut formatted)) = tracee.call.take() {
+                    if entered == number {
+                        let ret = tracee.inferior.registers()?.rax as i64;
+                        let inf = &tracee.inferior;
+                        let read = |addr, len| inf.read_memory(addr, len).ok();
+                        syscalls::update_arguments(number, &mut formatted, &args, ret, &read);
+                        eprintln!(
+                            "{}{}({}) = {}",
+                            self.prefix(pid),
+                            syscalls::name(number),
+                            formatted.join(", "),
+                            syscalls::format_return(number, ret)
+                        );
+                    }
+                }
+            }
+            Status::Event(Event::Fork(child)) | Status::Event(Event::Vfork(child)) => {
+                let child_tracee = Tracee::new(tracee.inferior.traced_child(child), false);
+                self.tracees.insert(child, child_tracee);
+                if self.early_children.remove(&child) {
+                    let child_tracee = self.tracees.get_mut(&child).unwrap();
+                    child_tracee.started = true;
+                    child_tracee.inferior.resume_to_syscall(None)?;
+                }
+            }
+            Status::Event(Event::Exit(_)) => self.finish_call(pid),
+            Status::Event(Event::Exec) => {}
+            Status::Stopped(stop_signal, _) => {
+                eprintln!("{}--- {} ---", self.prefix(pid), stop_signal);
+                signal = Some(stop_signal);
+            }
+            Status::Exited(code) => {
+                self.finish_call(pid);
+                eprintln!("{}+++ exited with {} +++", self.prefix(pid), code);
+                self.process_ended(pid, code);
+                return Ok(());
+            }
+            Status::Signaled(killed_by) => {
+                self.finish_call(pid);
+                eprintln!("{}+++ killed by {} +++", self.prefix(pid), killed_by);
+                self.process_ended(pid, 128 + killed_by as i32);
+                return Ok(());
+            }
+        }
+        self.tracees[&pid].inferior.resume_to_syscall(signal)
+    }
+
+    /// Shows the call a process is in as one that never returns, such as exit_group.
+    fn finish_call(&mut self, pid: Pid) {
+        let prefix = self.prefix(pid);
+        if let Some((number, _, formatted)) = self.tracees.get_mut(&pid).and_then(|t| t.call.take())
+        {
+            eprintln!(
+                "{}{}({}) = ?",
+                prefix,
+                syscalls::name(number),
+                formatted.join(", ")
+            );
+        }
+    }
+
+    fn process_ended(&mut self, pid: Pid, exit_status: i32) {
+        self.tracees.remove(&pid);
+        if pid == self.main_pid {
+            self.exit_status = exit_status;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters() {
+        let (classes, numbers) = TraceOptions::parse_filter("trace=file,%net,openat").unwrap();
+        assert_eq!(classes, syscalls::FILE | syscalls::NETWORK);
+        assert_eq!(numbers, [257]);
+        assert_eq!(
+            TraceOptions::parse_filter("write,bogus"),
+            Err("invalid system call 'bogus'".to_string())
+        );
+
+        let options = TraceOptions {
+            follow_forks: false,
+            filter: Some((syscalls::PROCESS, vec![1])),
+        };
+        // write, exit_group (a process call) and not read or an unknown call
+        assert!(options.shows(1));
+        assert!(options.shows(231));
+        assert!(!options.shows(0));
+        assert!(!options.shows(1000));
+        assert!(TraceOptions::default().shows(1000));
+    }
+}
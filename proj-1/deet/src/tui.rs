@@ -0,0 +1,326 @@
+//! The terminal UI of `deet --tui`. The screen is divided into boxed panes (source on the left;
+//! locals, registers and the backtrace on the right) above a command area at the bottom. The
+//! command area is a scrolling region of the terminal, so the prompt, the output of commands and
+//! the program's own output scroll there as they would without the UI, while the panes stay put
+//! and are redrawn by the debugger whenever what they show may have changed. Only ANSI/VT100
+//! escape sequences are used, so it works in any terminal emulator and over SSH.
+
+use std::io::{self, Write};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Least number of lines given to the command area.
+const MIN_COMMAND_LINES: usize = 6;
+/// Terminals narrower than this only get the source pane.
+const MIN_WIDTH_FOR_COLUMN: usize = 80;
+
+/// True while the terminal is on the alternate screen with the command area's scrolling region.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// A line of a pane, drawn in reverse video if it is highlighted.
+pub struct PaneLine {
+    pub text: String,
+    pub highlight: bool,
+}
+
+impl PaneLine {
+    pub fn new(text: String) -> PaneLine {
+        PaneLine {
+            text,
+            highlight: false,
+        }
+    }
+}
+
+pub struct Pane {
+    pub title: String,
+    pub lines: Vec<PaneLine>,
+    /// The line the pane scrolls to keep in the middle, or None to show the first lines
+    pub focus: Option<usize>,
+}
+
+impl Pane {
+    pub fn new(title: &str, lines: Vec<PaneLine>) -> Pane {
+        Pane {
+            title: title.to_string(),
+            lines,
+            focus: None,
+        }
+    }
+
+    /// Renders the pane as `height` rows of `width` columns, borders included. A pane with no room
+    /// for its borders is left blank.
+    fn render(&self, width: usize, height: usize) -> Vec<String> {
+        if width < 2 || height < 2 {
+            return vec![" ".repeat(width); height];
+        }
+        let inner_width = width - 2;
+        let inner_height = height - 2;
+        let title = truncate(&format!(" {} ", self.title), inner_width);
+        let mut rows = vec![format!(
+            "┌{}{}┐",
+            title,
+            "─".repeat(inner_width - title.chars().count())
+        )];
+        let first = match self.focus {
+            Some(focus) if self.lines.len() > inner_height => focus
+                .saturating_sub(inner_height / 2)
+                .min(self.lines.len() - inner_height),
+            _ => 0,
+        };
+        for index in first..first + inner_height {
+            let (text, highlight) = match self.lines.get(index) {
+                Some(line) => (truncate(&line.text, inner_width), line.highlight),
+                None => (String::new(), false),
+            };
+            let padding = " ".repeat(inner_width - text.chars().count());
+            if highlight {
+                rows.push(format!("│\x1b[7m{}{}\x1b[0m│", text, padding));
+            } else {
+                rows.push(format!("│{}{}│", text, padding));
+            }
+        }
+        rows.push(format!("└{}┘", "─".repeat(inner_width)));
+        rows
+    }
+}
+
+/// What the panes show.
+pub struct Screen {
+    pub source: Pane,
+    pub locals: Pane,
+    pub registers: Pane,
+    pub backtrace: Pane,
+}
+
+/// Takes a string to at most `width` characters, expanding tabs and leaving out control
+/// characters that would move the cursor.
+fn truncate(text: &str, width: usize) -> String {
+    let mut truncated = String::new();
+    let mut count = 0;
+    for c in text.chars() {
+        let expanded = match c {
+            '\t' => " ".repeat(4 - count % 4),
+            c if c.is_control() => continue,
+            c => c.to_string(),
+        };
+        for c in expanded.chars() {
+            if count == width {
+                return truncated;
+            }
+            truncated.push(c);
+            count += 1;
+        }
+    }
+    truncated
+}
+
+/// Returns the number of columns and lines of the terminal on stdout.
+fn terminal_size() -> Option<(usize, usize)> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } != 0 {
+        return None;
+    }
+    if size.ws_col == 0 || size.ws_row == 0 {
+        return None;
+    }
+    Some((size.ws_col as usize, size.ws_row as usize))
+}
+
+pub struct Tui {
+    /// The terminal size the command area was set up for
+    size: (usize, usize),
+}
+
+impl Tui {
+    /// Switches the terminal to the alternate screen, which is left again when the Tui is
+    /// dropped, when deet panics or when it exits through `exit`.
+    pub fn new() -> Result<Tui, String> {
+        if unsafe { libc::isatty(libc::STDIN_FILENO) } == 0 || terminal_size().is_none() {
+            return Err("--tui needs to be run in a terminal".to_string());
+        }
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            previous_hook(info);
+        }));
+        ACTIVE.store(true, Ordering::SeqCst);
+        print!("\x1b[?1049h\x1b[2J");
+        let _ = io::stdout().flush();
+        Ok(Tui { size: (0, 0) })
+    }
+
+    /// Draws the panes, leaving the cursor where it was in the command area.
+    pub fn draw(&mut self, screen: &Screen) {
+        let (width, height) = match terminal_size() {
+            Some(size) => size,
+            None => return,
+        };
+        let command_lines = (height / 3).max(MIN_COMMAND_LINES).min(height);
+        let pane_lines = height - command_lines;
+        let mut output = String::new();
+        if (width, height) != self.size {
+            // Setting the scrolling region homes the cursor, so the command area starts afresh
+            output += &format!(
+                "\x1b[r\x1b[2J\x1b[{};{}r\x1b[{};1H",
+                pane_lines + 1,
+                height,
+                height
+            );
+            self.size = (width, height);
+        }
+        // Too small a terminal has no room for panes
+        if pane_lines >= 6 {
+            output += "\x1b7";
+            let rows = Tui::layout(screen, width, pane_lines);
+            for (index, row) in rows.iter().enumerate() {
+                output += &format!("\x1b[{};1H{}", index + 1, row);
+            }
+            output += "\x1b8";
+        }
+        print!("{}", output);
+        let _ = io::stdout().flush();
+    }
+
+    /// Places the panes on `height` rows of `width` columns.
+    fn layout(screen: &Screen, width: usize, height: usize) -> Vec<String> {
+        if width < MIN_WIDTH_FOR_COLUMN {
+            return screen.source.render(width, height);
+        }
+        let right_width = (width / 3).max(30);
+        let mut rows = screen.source.render(width - right_width, height);
+        let locals_height = height / 3;
+        let registers_height = height / 3;
+        let backtrace_height = height - locals_height - registers_height;
+        let mut right = screen.locals.render(right_width, locals_height);
+        right.extend(screen.registers.render(right_width, registers_height));
+        right.extend(screen.backtrace.render(right_width, backtrace_height));
+        for (row, right) in rows.iter_mut().zip(right) {
+            row.push_str(&right);
+        }
+        rows
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Resets the scrolling region and leaves the alternate screen, if the Tui switched to it.
+fn restore_terminal() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        print!("\x1b[r\x1b[?1049l");
+        let _ = io::stdout().flush();
+    }
+}
+
+/// Exits deet with `status`. `std::process::exit` runs no destructors, so the terminal is
+/// restored here first.
+pub fn exit(status: i32) -> ! {
+    restore_terminal();
+    std::process::exit(status);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the number of columns a rendered row takes, leaving out the escape sequences of
+    /// highlighted lines.
+    fn columns(row: &str) -> usize {
+        row.replace("\x1b[7m", "")
+            .replace("\x1b[0m", "")
+            .chars()
+            .count()
+    }
+
+    fn numbered_pane(count: usize) -> Pane {
+        let lines = (0..count)
+            .map(|number| PaneLine::new(format!("line {}", number)))
+            .collect();
+        Pane::new("Source", lines)
+    }
+
+    fn screen() -> Screen {
+        Screen {
+            source: numbered_pane(100),
+            locals: numbered_pane(3),
+            registers: numbered_pane(18),
+            backtrace: numbered_pane(5),
+        }
+    }
+
+    #[test]
+    fn truncation() {
+        assert_eq!(truncate("a\tbc\td", 20), "a   bc  d");
+        assert_eq!(truncate("\tx", 2), "  ");
+        assert_eq!(truncate("ab\x1b[2Jcd\r", 20), "ab[2Jcd");
+        assert_eq!(truncate("héllo", 3), "hél");
+        assert_eq!(truncate("anything", 0), "");
+    }
+
+    #[test]
+    fn narrow_panes() {
+        let pane = numbered_pane(3);
+        for width in 0..12 {
+            for row in pane.render(width, 5) {
+                assert_eq!(columns(&row), width, "{:?}", row);
+            }
+        }
+        let rows = pane.render(6, 4);
+        assert_eq!(rows, vec!["┌ Sou┐", "│line│", "│line│", "└────┘"]);
+    }
+
+    #[test]
+    fn small_heights() {
+        let pane = numbered_pane(3);
+        for height in 0..5 {
+            assert_eq!(pane.render(20, height).len(), height);
+        }
+        assert_eq!(pane.render(6, 2), vec!["┌ Sou┐", "└────┘"]);
+        for height in 0..20 {
+            let rows = Tui::layout(&screen(), 120, height);
+            assert_eq!(rows.len(), height);
+            assert!(rows.iter().all(|row| columns(row) == 120));
+        }
+    }
+
+    #[test]
+    fn focus_scrolling() {
+        let mut pane = numbered_pane(100);
+        pane.lines[50].highlight = true;
+        pane.focus = Some(50);
+        // 10 lines fit, with the focused one in the middle
+        let rows = pane.render(20, 12);
+        assert!(rows[1].contains("line 45 "));
+        assert_eq!(rows[6], "│\x1b[7mline 50           \x1b[0m│");
+        // Near either end, the pane stops scrolling rather than showing empty lines
+        pane.focus = Some(98);
+        assert!(pane.render(20, 12)[10].contains("line 99"));
+        pane.focus = Some(2);
+        assert!(pane.render(20, 12)[1].contains("line 0 "));
+        let mut short = numbered_pane(3);
+        short.focus = Some(2);
+        let rows = short.render(20, 12);
+        assert!(rows[1].contains("line 0"));
+        assert_eq!(rows[4], format!("│{}│", " ".repeat(18)));
+    }
+
+    #[test]
+    fn layouts() {
+        // Narrow terminals only get the source pane
+        let rows = Tui::layout(&screen(), 79, 20);
+        assert_eq!(rows.len(), 20);
+        assert!(rows[0].starts_with("┌ Source "));
+        assert!(rows.iter().all(|row| columns(row) == 79));
+        assert_eq!(rows.iter().filter(|row| row.contains('┐')).count(), 1);
+        // Wide ones get a column of the other panes, a third of the width
+        let rows = Tui::layout(&screen(), 120, 20);
+        assert!(rows.iter().all(|row| columns(row) == 120));
+        let tops: Vec<usize> = (0..20).filter(|&i| rows[i].ends_with('┐')).collect();
+        assert_eq!(tops, vec![0, 6, 12]);
+        assert_eq!(rows[0].chars().nth(80), Some('┌'));
+    }
+}
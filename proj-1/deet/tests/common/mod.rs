@@ -0,0 +1,61 @@
+//! Compiling the programs in samples/ for the end-to-end tests.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of samples compiled so far, which keeps tests that run at the same time from writing
+/// the same executable.
+static COMPILED: AtomicUsize = AtomicUsize::new(0);
+
+/// A directory of this test run, for the compiled samples and as deet's home directory.
+pub fn scratch_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("deet-tests-{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("Error creating scratch directory");
+    dir
+}
+
+/// Compiles samples/<name>.c, .cpp or .rs the way the Makefile does, adding `extra_flags` to the
+/// compiler's arguments, and returns the path of the executable.
+pub fn compile(name: &str, extra_flags: &[&str]) -> String {
+    let c_flags: &[&str] = &["-O0", "-g", "-no-pie", "-fno-omit-frame-pointer"];
+    let rust_flags: &[&str] = &[
+        "-g",
+        "-Copt-level=0",
+        "-Crelocation-model=static",
+        "-Cforce-frame-pointers=yes",
+    ];
+    let compilers = [
+        ("c", "cc", c_flags),
+        ("cpp", "c++", c_flags),
+        ("rs", "rustc", rust_flags),
+    ];
+    let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples");
+    let (source, compiler, flags) = compilers
+        .iter()
+        .map(|(extension, compiler, flags)| {
+            let source = samples.join(format!("{}.{}", name, extension));
+            (source, *compiler, *flags)
+        })
+        .find(|(source, _, _)| source.exists())
+        .expect("No such sample");
+    let count = COMPILED.fetch_add(1, Ordering::SeqCst);
+    let output = scratch_dir().join(format!("{}-{}", name, count));
+    let status = Command::new(compiler)
+        .args(flags)
+        .args(extra_flags)
+        .arg("-o")
+        .arg(&output)
+        .arg(&source)
+        .status()
+        .unwrap_or_else(|_| panic!("Error running {}", compiler));
+    assert!(
+        status.success(),
+        "{} {:?} failed on {}",
+        compiler,
+        extra_flags,
+        source.display()
+    );
+    output.to_str().unwrap().to_string()
+}
@@ -0,0 +1,956 @@
+//! End-to-end tests that compile the programs in samples/ and debug them with deet, feeding it
+//! commands on stdin and checking what it prints.
+
+mod common;
+
+use common::{compile, scratch_dir};
+use deet::{Frame, Session, Stop};
+use nix::sys::signal::Signal;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Runs deet on a sample with `commands` as its input and returns everything it and the
+/// program printed. deet runs in the scratch directory with it as $HOME, so that the user's
+/// .deetinit and history are left alone.
+fn deet(name: &str, commands: &[&str]) -> String {
+    deet_in(&scratch_dir(), &scratch_dir(), name, commands)
+}
+
+/// Runs deet like `deet`, in directory `cwd` with `home` as $HOME.
+fn deet_in(home: &Path, cwd: &Path, name: &str, commands: &[&str]) -> String {
+    let program = compile(name, &[]);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_deet"))
+        .arg(&program)
+        .current_dir(cwd)
+        .env("HOME", home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Error running deet");
+    let mut input = commands.join("\n");
+    input.push_str("\nquit\n");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().expect("Error waiting for deet");
+    assert!(output.status.success(), "deet failed: {:?}", output);
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+/// Asserts that `output` has each of `expected`, in this order.
+fn assert_in_order(output: &str, expected: &[&str]) {
+    let mut rest = output;
+    for text in expected {
+        match rest.find(text) {
+            Some(index) => rest = &rest[index + text.len()..],
+            None => panic!("{:?} missing or out of order in:\n{}", text, output),
+        }
+    }
+}
+
+#[test]
+fn exit_status() {
+    let output = deet("count", &["run"]);
+    assert_in_order(&output, &["Child exited (status 0)"]);
+    for number in 1..=5 {
+        assert!(output.contains(&format!("{}\n", number)));
+    }
+    let output = deet("exit", &["run"]);
+    assert_in_order(&output, &["Child exited (status 0)"]);
+    // Without an argument, sleepy_print prints its usage and exits with 1
+    let output = deet("sleepy_print", &["run"]);
+    assert_in_order(&output, &["Child exited (status 1)"]);
+}
+
+#[test]
+fn segfault_report_and_backtrace() {
+    let output = deet("segfault", &["run", "backtrace", "print a"]);
+    assert_in_order(
+        &output,
+        &[
+            "Child stopped (signal SIGSEGV)",
+            "Stop at func2 (",
+            "segfault.c:5)",
+            "#0  func2 (a=2) at ",
+            "segfault.c:5",
+            "#1  func1 (a=42) at ",
+            "segfault.c:11",
+            "#2  main () at ",
+            "segfault.c:15",
+            "a = 2",
+        ],
+    );
+    // The signal is delivered when the program continues, which kills it
+    let output = deet("segfault", &["run", "continue"]);
+    assert_in_order(
+        &output,
+        &[
+            "Child stopped (signal SIGSEGV)",
+            "Child exited (signal SIGSEGV)",
+        ],
+    );
+}
+
+#[test]
+fn breakpoint_stops() {
+    let output = deet(
+        "function_calls",
+        &[
+            "break func2",
+            "break function_calls.c:19",
+            "run",
+            "backtrace",
+            "continue",
+            "print a",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "Breakpoint 1 at 0x",
+            "Breakpoint 2 at 0x",
+            "Stop at func2 (",
+            "function_calls.c:10)",
+            "#0  func2 (a=42, b=5) at ",
+            "#1  func1 (a=42) at ",
+            "#2  main () at ",
+            "Stop at func1 (",
+            "function_calls.c:19)",
+            "a = 42",
+            "Child exited (status 0)",
+        ],
+    );
+    // The program ran to the end with the breakpoints' original instructions intact
+    assert_in_order(
+        &output,
+        &["func2(42, 5) was called", "sum = 47", "end of func1"],
+    );
+}
+
+#[test]
+fn temporary_breakpoints_until_advance_and_start() {
+    let output = deet(
+        "function_calls",
+        &[
+            "tbreak func3",
+            "start",
+            "advance func2",
+            "until 13",
+            "continue",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "Temporary breakpoint 1 at 0x",
+            "Stop at main (",
+            "function_calls.c:24)",
+            "Stop at func2 (",
+            "function_calls.c:10)",
+            "Stop at func2 (",
+            "function_calls.c:13)",
+            "Temporary breakpoint 1 deleted",
+            "Stop at func3 (",
+            "function_calls.c:6)",
+            "Child exited (status 0)",
+        ],
+    );
+    // func3 is called twice, but the temporary breakpoint only stops it once
+    assert_eq!(output.matches("Stop at func3 (").count(), 1);
+}
+
+#[test]
+fn breakpoint_command_lists() {
+    let output = deet(
+        "function_calls",
+        &[
+            "break func2",
+            "commands",
+            "print a",
+            "backtrace 1",
+            "continue",
+            "end",
+            "break func3",
+            "commands 2",
+            "silent",
+            "print a",
+            "end",
+            "break func3",
+            "commands",
+            "silent",
+            "print a * 2",
+            "end",
+            "commands 5",
+            "end",
+            "run",
+            "continue",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "No breakpoint number 5.",
+            "Stop at func2 (",
+            "a = 42",
+            "#0  func2 (a=42, b=5) at ",
+            "(More stack frames follow...)",
+            "a = 100",
+            "a * 2 = 200",
+            "a = 100",
+            "a * 2 = 200",
+            "Child exited (status 0)",
+        ],
+    );
+    // The lists of breakpoints 2 and 3 start with silent, so their stops are not announced
+    assert!(!output.contains("Stop at func3 ("), "{}", output);
+    assert!(!output.contains("Unrecognized"), "{}", output);
+}
+
+#[test]
+fn stepping_and_displays() {
+    let output = deet(
+        "function_calls",
+        &[
+            "break func2",
+            "run",
+            "display a + b",
+            "display sum",
+            "next",
+            "next",
+            "stepi",
+            "next",
+            "step",
+            "undisplay 1",
+            "finish",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "Stop at func2 (",
+            "1: a + b = 47",
+            "function_calls.c:11",
+            "1: a + b = 47",
+            // next runs through printf, which has no debugging information
+            "function_calls.c:12",
+            "2: sum = 47",
+            "function_calls.c:12",
+            "2: sum = 47",
+            "function_calls.c:13",
+            "2: sum = 47",
+            "#0  func3 (a=100) at ",
+            "function_calls.c:6",
+            "Run till exit from ",
+            "Stop at func2 (",
+            "2: sum = 47",
+            "Child exited (status 0)",
+        ],
+    );
+    // Neither expression can be evaluated in func3, and display 1 is gone after it
+    let in_func3 = &output[output.find("#0  func3 (").unwrap()..output.find("Run till").unwrap()];
+    assert!(!in_func3.contains(": sum = "), "{}", output);
+    let after_func3 = &output[output.find("Run till").unwrap()..];
+    assert!(!after_func3.contains("1: a + b = "), "{}", output);
+}
+
+#[test]
+fn breakpoint_in_a_loop() {
+    let output = deet(
+        "containers",
+        &[
+            "break containers.c:69",
+            "run",
+            "print numbers.length",
+            "continue",
+            "print numbers.length",
+            "continue",
+            "print numbers.length",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "containers.c:69)",
+            "numbers.length = 0",
+            "containers.c:69)",
+            "numbers.length = 1",
+            "containers.c:69)",
+            "numbers.length = 2",
+            "3 4 7",
+            "Child exited (status 0)",
+        ],
+    );
+}
+
+#[test]
+fn checkpoints_restart_from_where_they_were_taken() {
+    let output = deet(
+        "count",
+        &[
+            "break count.c:6",
+            "run",
+            "checkpoint",
+            "continue",
+            "restart 1",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "count.c:6)",
+            "checkpoint 1: fork returned pid",
+            "Child exited (status 0)",
+            "Switching to process",
+            "6\t    printf(\"3\\n\");",
+            "Child exited (status 0)",
+        ],
+    );
+    // The rest of the program runs again from the checkpoint
+    assert_eq!(output.matches("3\n4\n5\n").count(), 2, "{}", output);
+
+    let output = deet(
+        "containers",
+        &[
+            "break containers.c:69",
+            "run",
+            "continue",
+            "checkpoint",
+            "continue",
+            "continue",
+            "print numbers.length",
+            "restart 1",
+            "print numbers.length",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "checkpoint 1: fork returned pid",
+            "Child exited (status 0)",
+            "Switching to process",
+            "numbers.length = 1",
+        ],
+    );
+}
+
+#[test]
+fn returned_structs_and_floats() {
+    let output = deet(
+        "returns",
+        &[
+            "tbreak make_vector",
+            "tbreak make_mixed",
+            "tbreak make_triple",
+            "tbreak make_big",
+            "tbreak make_long_double",
+            "run",
+            "finish",
+            "continue",
+            "finish",
+            "continue",
+            "finish",
+            "continue",
+            "finish",
+            "continue",
+            "finish",
+            "print make_vector(1.5, -2)",
+            "print make_mixed(3)",
+            "print make_long_double()",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "Value returned is {x = 3, y = 4}",
+            "Value returned is {count = 7, mean = 3.5}",
+            "Value returned is {a = 1.5, b = 2.5, c = 3.5}",
+            "Value returned is {values = {1, 2, 3, 4}}",
+            "Could not read the returned value: long double is returned in %st0",
+            "make_vector(1.5, -2) = {x = 1.5, y = -2}",
+            "make_mixed(3) = {count = 3, mean = 1.5}",
+            "Cannot call function make_long_double: returning long double in %st0",
+            "3 4 7 3.5 3.5 4 1.25",
+            "Child exited (status 0)",
+        ],
+    );
+}
+
+#[test]
+fn finish_and_return() {
+    let output = deet(
+        "function_calls",
+        &[
+            "break func2",
+            "run",
+            "finish",
+            "up",
+            "return global * 8 + 2",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "Stop at func2 (",
+            "Run till exit from #0  func2 (a=42, b=5) at ",
+            "Stop at func1 (",
+            "#1  main () at ",
+            "Child exited (status 42)",
+        ],
+    );
+    // Structs may be returned in two registers or in memory, so only scalars can be returned
+    let output = deet(
+        "returns",
+        &[
+            "break make_vector",
+            "run",
+            "return v",
+            "up",
+            "return 'a' - 90",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "Stop at make_vector (",
+            "Returning a struct vector is not supported.",
+            "#1  main () at ",
+            "Child exited (status 7)",
+        ],
+    );
+}
+
+#[test]
+fn reverse_continue_refuses_calls_in_conditions() {
+    let output = deet(
+        "returns",
+        &[
+            "break returns.c:46",
+            "run",
+            "record",
+            "tbreak returns.c:47",
+            "continue",
+            "break make_mixed",
+            "condition 3 make_big().values[0] == 1",
+            "reverse-continue",
+            "record stop",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "returns.c:46)",
+            "returns.c:47)",
+            "Error in testing condition for breakpoint 3:",
+            "Cannot call function make_big while running backwards.",
+            "Breakpoint 3",
+            "make_mixed (count=7) at ",
+            "Child exited (status 0)",
+        ],
+    );
+}
+
+#[test]
+fn reverse_stepping_undoes_stores() {
+    let output = deet(
+        "containers",
+        &[
+            "break containers.c:69",
+            "run",
+            "continue",
+            "record",
+            "next",
+            "print numbers.length",
+            "reverse-next",
+            "print numbers.length",
+            "next",
+            "reverse-step",
+            "print list->length",
+            "reverse-step",
+            "print list->length",
+            "record stop",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "containers.c:69)",
+            "containers.c:69)",
+            "68\t",
+            "numbers.length = 2",
+            "69\t        push(&numbers, i);",
+            "numbers.length = 1",
+            "68\t",
+            // Back into push, which has already incremented the length
+            "43\t}",
+            "list->length = 2",
+            "42\t    list->length++;",
+            "list->length = 1",
+        ],
+    );
+}
+
+#[test]
+fn syscall_catchpoints_after_stepping() {
+    let output = deet(
+        "function_calls",
+        &["catch syscall", "run", "stepi", "continue", "record"],
+    );
+    // Stepping over the first system call makes no stop on its return, so the next stop is
+    // another call
+    assert_in_order(
+        &output,
+        &[
+            "Catchpoint 1 (any syscall)",
+            "Catchpoint 1 (call to syscall ",
+            "Catchpoint 1 (call to syscall ",
+            "Cannot record while syscall catchpoints are set.",
+        ],
+    );
+    assert!(!output.contains("returned from"), "{}", output);
+}
+
+#[test]
+fn signal_and_exit_catchpoints() {
+    let output = deet(
+        "segfault",
+        &["catch signal SIGSEGV", "run", "backtrace", "continue"],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "Catchpoint 1 (signal SIGSEGV)",
+            "Catchpoint 1 (signal SIGSEGV)",
+            "segfault.c:5)",
+            "#1  func1 (a=42) at ",
+            "Child exited (signal SIGSEGV)",
+        ],
+    );
+
+    // The process can still be looked at just before it exits, and killed there by quitting
+    let output = deet(
+        "function_calls",
+        &["catch exit", "run", "print global", "continue", "run"],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "Catchpoint 1 (exit)",
+            "Catchpoint 1 (exiting with status 0)",
+            "global = 5",
+            "Child exited (status 0)",
+            "Catchpoint 1 (exiting with status 0)",
+        ],
+    );
+}
+
+#[test]
+fn rerunning_keeps_breakpoints() {
+    let output = deet("count", &["break 6", "run", "run", "continue"]);
+    assert_in_order(
+        &output,
+        &["count.c:6)", "count.c:6)", "Child exited (status 0)"],
+    );
+}
+
+#[test]
+fn rust_names_and_values() {
+    let output = deet(
+        "rust_types",
+        &[
+            "break show",
+            "break rust_types.rs:22",
+            "run",
+            "print greeting",
+            "print slice",
+            "print numbers",
+            "print some",
+            "print none",
+            "print boxed",
+            "print shared",
+            "continue",
+            "backtrace",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "Stop at rust_types::main (",
+            "rust_types.rs:22)",
+            "greeting = \"hello\"",
+            "slice = \"a slice\"",
+            "numbers = [1, 2, 3]",
+            "some = Some(7)",
+            "none = None",
+            "boxed = Box({x = 1, y = 2})",
+            "shared = Rc(\"shared\", strong = 2, weak = 0)",
+            "Stop at rust_types::show (",
+            "#0  rust_types::show (name=\"a slice\", numbers=0x",
+            "#1  rust_types::main () at ",
+            "a slice has 3 numbers",
+            "hello 3 Some(7) None",
+            "Child exited (status 0)",
+        ],
+    );
+}
+
+#[test]
+fn cpp_names() {
+    let output = deet(
+        "namespaces",
+        &[
+            "break add",
+            "break geometry::Point::sum",
+            "run",
+            "backtrace",
+            "continue",
+            "backtrace",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "Stop at geometry::Point::sum (",
+            "#0  geometry::Point::sum (this=0x",
+            "#1  main () at ",
+            "Stop at geometry::add (",
+            "#0  geometry::add (a=1, b=2) at ",
+            "#1  main () at ",
+            "3 3",
+            "Child exited (status 0)",
+        ],
+    );
+}
+
+#[test]
+fn init_files_and_saved_breakpoints() {
+    let dir = scratch_dir().join("init");
+    let home = dir.join("home");
+    let work = dir.join("work");
+    fs::create_dir_all(&home).unwrap();
+    fs::create_dir_all(&work).unwrap();
+    let home_init = "break func2 if a == 42\ncommands\n  silent\n  print b\nend\n";
+    fs::write(home.join(".deetinit"), home_init).unwrap();
+    fs::write(work.join(".deetinit"), "tbreak func3\ncatch exit\n").unwrap();
+
+    // ~/.deetinit runs before ./.deetinit
+    let output = deet_in(
+        &home,
+        &work,
+        "function_calls",
+        &["run", "continue", "continue", "save breakpoints saved.deet"],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "Breakpoint 1 at ",
+            "Temporary breakpoint 2 at ",
+            "Catchpoint 3 (exit)",
+            "b = 5",
+            "Stop at func3 (",
+            "Catchpoint 3 (exiting with status 0)",
+            "Saved to file 'saved.deet'.",
+        ],
+    );
+    // The temporary breakpoint is gone by the time they are saved
+    let saved = fs::read_to_string(work.join("saved.deet")).unwrap();
+    assert_eq!(saved, format!("{}catch exit\n", home_init));
+
+    let source = format!("source {}", work.join("saved.deet").display());
+    let output = deet_in(&dir, &dir, "function_calls", &[&source, "run", "continue"]);
+    assert_in_order(
+        &output,
+        &[
+            "Breakpoint 1 at ",
+            "Catchpoint 2 (exit)",
+            "b = 5",
+            "Catchpoint 2 (exiting with status 0)",
+        ],
+    );
+    assert!(!output.contains("Stop at func2 ("), "{}", output);
+}
+
+#[test]
+fn help() {
+    let output = deet(
+        "function_calls",
+        &["help", "help b", "help info", "help bogus"],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "List of commands:",
+            "run              -- Start the program",
+            "break            -- Set a breakpoint",
+            "help             -- List the commands",
+            "Type \"help\" followed by a command name for full documentation.",
+            "Usage: break <location> [if <condition>]\nSet a breakpoint",
+            "Aliases: b\n",
+            "Usage: info frame|display|checkpoints|record|functions [regex]|",
+            "info line tells where the code of a line is",
+            "Aliases: i\n",
+            "Undefined command: \"bogus\".  Try \"help\".",
+        ],
+    );
+}
+
+#[test]
+fn info_symbols_and_lines() {
+    let output = deet(
+        "function_calls",
+        &[
+            "info functions ^func[12]$",
+            "info variables",
+            "info line function_calls.c:18",
+            "info address func3",
+            "info address global",
+            "info source",
+            "info functions [",
+            "break func2",
+            "run",
+            "info line",
+            "info address sum",
+            "info address a",
+            "info address nothere",
+            "info source",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "All functions matching regular expression \"^func[12]$\":\n\nFile ",
+            "function_calls.c:\n16:\tvoid func1(int);\n9:\tvoid func2(int, int);\n",
+            "All defined variables:\n\nFile ",
+            "function_calls.c:\n3:\tint global;\n",
+            "Line 18 of \"",
+            "function_calls.c\" starts at address 0x",
+            " <func1+",
+            "Symbol \"func3\" is a function at address 0x",
+            "Symbol \"global\" is static storage at address 0x",
+            "No current source file.",
+            "Invalid regexp: Unmatched [",
+            "Stop at func2 (",
+            "Line 10 of \"",
+            "function_calls.c\" starts at address 0x",
+            " <func2+",
+            "Symbol \"sum\" is a variable at offset ",
+            "Symbol \"a\" is an argument at offset ",
+            "No symbol \"nothere\" in current context.",
+            "Current source file is ",
+            "function_calls.c\nLocated in ",
+            "Contains 25 lines.",
+        ],
+    );
+    assert!(!output.contains("func3(int);"));
+
+    let output = deet("containers", &["info types", "info types ^node$"]);
+    assert_in_order(
+        &output,
+        &[
+            "All defined types:\n",
+            "\tint\n",
+            "\tstruct list\n",
+            "\tstruct node\n",
+            "All types matching regular expression \"^node$\":\n\tstruct node\n",
+        ],
+    );
+}
+
+#[test]
+fn trace_syscalls() {
+    let program = compile("exit", &[]);
+    let output = Command::new(env!("CARGO_BIN_EXE_deet"))
+        .args(&["--trace-syscalls", "-f", "-e", "trace=process,openat"])
+        .arg(&program)
+        .output()
+        .expect("Error running deet");
+    assert!(output.status.success(), "deet failed: {:?}", output);
+    let trace = String::from_utf8_lossy(&output.stderr);
+    assert_in_order(
+        &trace,
+        &[
+            "openat(AT_FDCWD, \"",
+            "O_RDONLY|O_CLOEXEC) = 3",
+            "exit(0) = ?",
+            "+++ exited with 0 +++",
+        ],
+    );
+    // Calls outside the filter are left out
+    assert!(!trace.contains("mmap("), "{}", trace);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_deet"))
+        .args(&["--trace-syscalls", "-e", "trace=bogus"])
+        .arg(&program)
+        .output()
+        .expect("Error running deet");
+    assert!(!output.status.success());
+    let error = String::from_utf8_lossy(&output.stderr);
+    assert!(error.contains("invalid system call 'bogus'"), "{}", error);
+}
+
+#[test]
+fn user_pretty_printers() {
+    let printers = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/containers.deet");
+    let source = format!("source {}", printers.display());
+    let output = deet(
+        "containers",
+        &[
+            "break containers.c:80",
+            "run",
+            "print numbers",
+            &source,
+            "print numbers",
+            "print recent",
+            "print ages",
+            "print corner",
+            "pretty-printer struct node = list . next",
+            "print *numbers.head",
+            "pretty-printer struct ring = ring data",
+            "continue",
+        ],
+    );
+    assert_in_order(
+        &output,
+        &[
+            "numbers = {head = 0x",
+            "numbers = [1, 2, 3]",
+            "recent = [30, 40, 50, 60]",
+            "ages = {[\"carol\"] = 45, [\"bob\"] = 27, [\"alice\"] = 31}",
+            "corner = (3, 4)",
+            "*numbers.head = [{value = 1, next = 0x",
+            "{value = 3, next = 0x0}]",
+            "Usage: pretty-printer TYPE|/REGEX/ = ring BUFFER START COUNT CAPACITY",
+            "3 4 7",
+            "Child exited (status 0)",
+        ],
+    );
+}
+
+#[test]
+fn profile_collapsed_stacks() {
+    let program = compile("busy", &[]);
+    let folded = format!("{}.folded", program);
+    let output = Command::new(env!("CARGO_BIN_EXE_deet"))
+        .args(&["--profile", "--top", "5", "-o"])
+        .arg(&folded)
+        .arg(&program)
+        .output()
+        .expect("Error running deet");
+    assert!(output.status.success(), "deet failed: {:?}", output);
+    // Which functions the samples land in depends on timing, so only the shape of the report
+    // and of the stacks is checked
+    let report = String::from_utf8_lossy(&output.stderr);
+    assert_in_order(
+        &report,
+        &[
+            " samples at ",
+            " Hz; stacks written to ",
+            "Functions with the most samples:",
+            "Source lines with the most samples:",
+        ],
+    );
+    let samples: usize = report
+        .split_whitespace()
+        .nth(1)
+        .and_then(|count| count.parse().ok())
+        .unwrap_or_else(|| panic!("No sample count in:\n{}", report));
+    let stacks = fs::read_to_string(&folded).expect("No collapsed stacks");
+    let mut total = 0;
+    for line in stacks.lines() {
+        let space = line.rfind(' ').expect("No count on a line of the stacks");
+        let (stack, count) = (&line[..space], &line[space + 1..]);
+        assert!(
+            stack.split(';').all(|frame| !frame.is_empty()),
+            "bad stack {:?}",
+            line
+        );
+        total += count.parse::<usize>().expect("bad count");
+    }
+    assert_eq!(total, samples);
+}
+
+#[test]
+fn session_reports_stops() {
+    let mut session = Session::new(&compile("segfault", &[])).unwrap();
+    let breakpoint = session.set_breakpoint("func1", false, None).unwrap();
+    session.launch(&[]).unwrap();
+    assert_eq!(session.cont().unwrap(), Stop::Breakpoint(breakpoint.number));
+    assert_eq!(session.cont().unwrap(), Stop::Signal(Signal::SIGSEGV));
+    let frames = session.backtrace().unwrap();
+    assert_eq!(frames[0].function.as_deref(), Some("func2"));
+    assert_eq!(frames[0].line.as_ref().unwrap().number, 5);
+    let a = session.read_variable("a", 0).unwrap();
+    assert_eq!(session.format_value(&a), "2");
+    assert_eq!(session.cont().unwrap(), Stop::Signaled(Signal::SIGSEGV));
+    assert!(!session.is_running());
+
+    let mut session = Session::new(&compile("exit", &[])).unwrap();
+    session.launch(&[]).unwrap();
+    assert_eq!(session.cont().unwrap(), Stop::Exited(0));
+}
+
+fn line_of(frame: &Frame) -> usize {
+    frame.line.as_ref().unwrap().number
+}
+
+#[test]
+fn session_breakpoints_backtrace_and_variables() {
+    let mut session = Session::new(&compile("function_calls", &[])).unwrap();
+    let breakpoint = session.set_breakpoint("func2", false, None).unwrap();
+    assert!(session.set_breakpoint("nonexistent", false, None).is_err());
+    assert!(session.cont().is_err());
+    session.launch(&[]).unwrap();
+    assert_eq!(session.cont().unwrap(), Stop::Breakpoint(breakpoint.number));
+
+    let frames = session.backtrace().unwrap();
+    let functions: Vec<&str> = frames
+        .iter()
+        .map(|frame| frame.function.as_deref().unwrap())
+        .collect();
+    assert_eq!(functions, vec!["func2", "func1", "main"]);
+    assert_eq!(line_of(&frames[0]), 10);
+
+    let b = session.read_variable("b", 0).unwrap();
+    assert_eq!(b.ty.name, "int");
+    assert_eq!(session.format_value(&b), "5");
+    let a = session.read_variable("a", 1).unwrap();
+    assert_eq!(session.format_value(&a), "42");
+    assert_eq!(
+        session.format_value(&session.read_variable("global", 1).unwrap()),
+        "5"
+    );
+    assert!(session.read_variable("sum", 1).is_err());
+    assert!(session.read_variable("a", 3).is_err());
+
+    // Over printf, which has no debugging information, to the next line
+    assert_eq!(session.step().unwrap(), Stop::Step);
+    assert_eq!(line_of(&session.backtrace().unwrap()[0]), 11);
+    session.step().unwrap();
+    session.step().unwrap();
+    // Into func3, past its prologue
+    assert_eq!(session.step().unwrap(), Stop::Step);
+    let frames = session.backtrace().unwrap();
+    assert_eq!(frames[0].function.as_deref(), Some("func3"));
+    assert_eq!(line_of(&frames[0]), 6);
+
+    session.delete_breakpoint(breakpoint.number).unwrap();
+    assert_eq!(session.cont().unwrap(), Stop::Exited(0));
+    assert!(!session.is_running());
+}